@@ -4,8 +4,14 @@ pub use shared::*;
 #[cfg(feature = "use-metal")]
 pub mod metal;
 
+#[cfg(feature = "use-null")]
+pub mod null;
+
 #[cfg(feature = "use-opengl")]
 pub mod opengl;
 
+#[cfg(feature = "use-software")]
+pub mod software;
+
 #[cfg(feature = "use-webgl")]
 pub mod webgl;
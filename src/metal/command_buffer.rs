@@ -1,11 +1,43 @@
 use crate::metal::err::Result;
 use crate::metal::*;
+use crate::{
+    CullMode, Frame, FrontFace, IndexFormat, LoadAction, Operations, PassDesc, PolygonMode,
+    RasterizerState, Rect, StoreAction, TextureFormat,
+};
+
+/// A texture drawn into, and the texture it is resolved into.
+pub(crate) type ResolvedTexture = (metal::Texture, Option<metal::Texture>);
 
 pub struct CommandBuffer {
     pub(crate) screen: metal::CoreAnimationDrawable,
-    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
     pub(crate) command_buffer: metal::CommandBuffer,
     pub(crate) encoder: Option<metal::RenderCommandEncoder>,
+    /// The encoder of the dispatches since the last render pass.
+    pub(crate) compute_encoder: Option<metal::ComputeCommandEncoder>,
+    pub(crate) workgroup_size: metal::MTLSize,
+    pub(crate) primitive_type: metal::MTLPrimitiveType,
+    /// Kept to be set on every encoder, which start out covering the whole
+    /// attachments.
+    pub(crate) viewport: Option<Rect>,
+    pub(crate) scissor: Option<Rect>,
+    /// The color and depth textures of the render target drawn into, each
+    /// with the texture it is resolved into if it is multisampled. `None`
+    /// when drawing to the screen.
+    pub(crate) render_target: Option<(
+        Vec<ResolvedTexture>,
+        Option<(ResolvedTexture, TextureFormat)>,
+    )>,
+    /// See `set_clear_color`.
+    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
+    /// The buffers bound to the vertex and fragment functions and the
+    /// textures bound to the fragment function, by location. Encoders start
+    /// out without bindings, so these are set on every render encoder.
+    pub(crate) render_buffers: Vec<(u32, metal::Buffer)>,
+    pub(crate) textures: Vec<(u32, (metal::Texture, metal::SamplerState))>,
+    /// The buffers and textures bound to the compute function, set on every
+    /// compute encoder.
+    pub(crate) compute_buffers: Vec<(u32, metal::Buffer)>,
+    pub(crate) storage_textures: Vec<(u32, metal::Texture)>,
 }
 
 impl CommandBuffer {
@@ -15,58 +47,334 @@ impl CommandBuffer {
     ) -> Result<CommandBuffer> {
         Ok(CommandBuffer {
             screen: screen.to_owned(),
-            clear_color: None,
             command_buffer: command_buffer.to_owned(),
             encoder: None,
+            compute_encoder: None,
+            workgroup_size: metal::MTLSize {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+            primitive_type: metal::MTLPrimitiveType::TriangleStrip,
+            viewport: None,
+            scissor: None,
+            render_target: None,
+            clear_color: None,
+            render_buffers: Vec::new(),
+            textures: Vec::new(),
+            compute_buffers: Vec::new(),
+            storage_textures: Vec::new(),
         })
     }
 
+    /// Ends the current render pass, the next pass draws into the render
+    /// target.
+    pub fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        self.end_pass();
+
+        self.render_target = render_target.map(|render_target| {
+            let resolved = |texture: &Texture, multisample_texture: Option<&metal::Texture>| {
+                match multisample_texture {
+                    Some(multisample_texture) => {
+                        (multisample_texture.clone(), Some(texture.texture.clone()))
+                    }
+                    None => (texture.texture.clone(), None),
+                }
+            };
+            (
+                render_target
+                    .color_textures
+                    .iter()
+                    .enumerate()
+                    .map(|(i, texture)| {
+                        resolved(texture, render_target.multisample_color_textures.get(i))
+                    })
+                    .collect(),
+                match (&render_target.depth_texture, render_target.depth_format) {
+                    (Some(texture), Some(format)) => Some((
+                        resolved(texture, render_target.multisample_depth_texture.as_ref()),
+                        format,
+                    )),
+                    _ => None,
+                },
+            )
+        });
+        self.viewport = None;
+        self.scissor = None;
+    }
+
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        self.viewport = viewport;
+        if let Some(encoder) = &self.encoder {
+            self.set_viewport_and_scissor(encoder);
+        }
+    }
+
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        self.scissor = scissor;
+        if let Some(encoder) = &self.encoder {
+            self.set_viewport_and_scissor(encoder);
+        }
+    }
+
+    fn set_viewport_and_scissor(&self, encoder: &metal::RenderCommandEncoderRef) {
+        let (width, height) = self.target_size();
+        let full = Rect {
+            x: 0,
+            y: 0,
+            width: width as u32,
+            height: height as u32,
+        };
+
+        let viewport = self.viewport.unwrap_or(full);
+        encoder.set_viewport(metal::MTLViewport {
+            originX: viewport.x as f64,
+            originY: viewport.y as f64,
+            width: viewport.width as f64,
+            height: viewport.height as f64,
+            znear: 0.0,
+            zfar: 1.0,
+        });
+
+        // Metal requires the scissor rectangle to lie inside of the
+        // attachments.
+        let scissor = self.scissor.unwrap_or(full);
+        let x = (scissor.x as u64).min(width);
+        let y = (scissor.y as u64).min(height);
+        encoder.set_scissor_rect(metal::MTLScissorRect {
+            x,
+            y,
+            width: (scissor.width as u64).min(width - x),
+            height: (scissor.height as u64).min(height - y),
+        });
+    }
+
+    fn set_render_bindings(&self, encoder: &metal::RenderCommandEncoderRef) {
+        for (location, buffer) in &self.render_buffers {
+            encoder.set_vertex_buffer(*location as u64, Some(buffer), 0);
+            encoder.set_fragment_buffer(*location as u64, Some(buffer), 0);
+        }
+        for (location, (texture, sampler)) in &self.textures {
+            encoder.set_fragment_texture(*location as u64, Some(texture));
+            encoder.set_fragment_sampler_state(*location as u64, Some(sampler));
+        }
+    }
+
+    fn set_compute_bindings(&self, compute_encoder: &metal::ComputeCommandEncoderRef) {
+        for (location, buffer) in &self.compute_buffers {
+            compute_encoder.set_buffer(*location as u64, Some(buffer), 0);
+        }
+        for (location, texture) in &self.storage_textures {
+            compute_encoder.set_texture(*location as u64, Some(texture));
+        }
+    }
+
+    /// The size of the attachments drawn into.
+    fn target_size(&self) -> (u64, u64) {
+        let texture: &metal::TextureRef = match &self.render_target {
+            Some((color_textures, depth_texture)) => {
+                match (color_textures.first(), depth_texture) {
+                    (Some((texture, _)), _) | (None, Some(((texture, _), _))) => &**texture,
+                    (None, None) => return (0, 0),
+                }
+            }
+            None => self.screen.texture(),
+        };
+        (texture.width(), texture.height())
+    }
+
+    /// Deprecated, see `Frame::set_clear_color`.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
     pub fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
         self.clear_color = clear_color;
     }
 
-    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+    /// Starts a render pass, which is a new encoder in Metal, with the
+    /// bindings of the previous passes.
+    pub fn begin_pass(&mut self, desc: &PassDesc) {
+        self.end_pass();
+        if let Some(compute_encoder) = self.compute_encoder.take() {
+            compute_encoder.end_encoding();
+        }
+
         let render_pass_desc = metal::RenderPassDescriptor::new();
-        let color_attachment = render_pass_desc.color_attachments().object_at(0).unwrap();
-        color_attachment.set_texture(Some(self.screen.texture()));
+        let (color_textures, depth_texture) = match &self.render_target {
+            Some((color_textures, depth_texture)) => (
+                color_textures
+                    .iter()
+                    .map(|(texture, resolve_texture)| {
+                        (texture.as_ref(), resolve_texture.as_deref())
+                    })
+                    .collect(),
+                depth_texture.as_ref(),
+            ),
+            None => (vec![(self.screen.texture(), None)], None),
+        };
 
-        if let Some((red, green, blue, alpha)) = self.clear_color {
-            color_attachment.set_load_action(metal::MTLLoadAction::Clear);
-            color_attachment.set_clear_color(metal::MTLClearColor::new(
-                red as f64,
-                green as f64,
-                blue as f64,
-                alpha as f64,
-            ));
+        for (i, (texture, resolve_texture)) in color_textures.into_iter().enumerate() {
+            let color_attachment = render_pass_desc
+                .color_attachments()
+                .object_at(i as u64)
+                .unwrap();
+            color_attachment.set_texture(Some(texture));
+            color_attachment.set_resolve_texture(resolve_texture);
+
+            let operations = desc.color_operations(i);
+            if let LoadAction::Clear([red, green, blue, alpha]) = operations.load {
+                color_attachment.set_clear_color(metal::MTLClearColor::new(
+                    red as f64,
+                    green as f64,
+                    blue as f64,
+                    alpha as f64,
+                ));
+            }
+            color_attachment.set_load_action(load_action(operations.load));
+            color_attachment
+                .set_store_action(store_action(operations.store, resolve_texture.is_some()));
+        }
+
+        if let Some(((texture, resolve_texture), format)) = depth_texture {
+            let depth_attachment = render_pass_desc.depth_attachment().unwrap();
+            depth_attachment.set_texture(Some(texture));
+            depth_attachment.set_resolve_texture(resolve_texture.as_deref());
+            if let LoadAction::Clear(depth) = desc.depth.load {
+                depth_attachment.set_clear_depth(depth.max(0.0).min(1.0) as f64);
+            }
+            depth_attachment.set_load_action(load_action(desc.depth.load));
+            depth_attachment
+                .set_store_action(store_action(desc.depth.store, resolve_texture.is_some()));
+
+            if format.has_stencil() {
+                let stencil_attachment = render_pass_desc.stencil_attachment().unwrap();
+                stencil_attachment.set_texture(Some(texture));
+                if let LoadAction::Clear(stencil) = desc.stencil.load {
+                    stencil_attachment.set_clear_stencil(stencil as u32);
+                }
+                stencil_attachment.set_load_action(load_action(desc.stencil.load));
+                stencil_attachment.set_store_action(store_action(desc.stencil.store, false));
+            }
         }
-        color_attachment.set_store_action(metal::MTLStoreAction::Store);
 
         let encoder = self
             .command_buffer
             .new_render_command_encoder(render_pass_desc)
             .to_owned();
 
-        // encoder.set_scissor_rect(metal::MTLScissorRect {
-        //     x: 0,
-        //     y: 0,
-        //     width: 100,
-        //     height: 100,
-        // });
-
-        encoder.set_render_pipeline_state(&pipeline.pipeline_state);
+        self.set_viewport_and_scissor(&encoder);
+        self.set_render_bindings(&encoder);
         self.encoder = Some(encoder);
     }
 
+    pub fn end_pass(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.end_encoding();
+        }
+    }
+
+    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        if let Some((red, green, blue, alpha)) = self.clear_color {
+            let color_attachment_count = self
+                .render_target
+                .as_ref()
+                .map_or(1, |(color_textures, _depth_texture)| color_textures.len());
+            let color = vec![Operations::clear([red, green, blue, alpha]); color_attachment_count];
+            self.begin_pass(&PassDesc {
+                color: &color,
+                ..Default::default()
+            });
+        } else if self.encoder.is_none() {
+            self.begin_pass(&PassDesc::default());
+        }
+
+        if let Some(encoder) = &self.encoder {
+            encoder.set_render_pipeline_state(&pipeline.pipeline_state);
+            let [red, green, blue, alpha] = pipeline.blend_constant;
+            encoder.set_blend_color(red, green, blue, alpha);
+            set_rasterizer_state(encoder, &pipeline.rasterizer);
+            encoder.set_depth_stencil_state(&pipeline.depth_stencil_state);
+            encoder.set_stencil_reference_value(pipeline.stencil_reference);
+        }
+        self.primitive_type = pipeline.primitive_type;
+    }
+
+    /// Dispatches are recorded by a compute encoder, which is kept until the
+    /// next render pass begins.
+    pub fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        self.end_pass();
+
+        if self.compute_encoder.is_none() {
+            let compute_encoder = self.command_buffer.new_compute_command_encoder().to_owned();
+            self.set_compute_bindings(&compute_encoder);
+            self.compute_encoder = Some(compute_encoder);
+        }
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.set_compute_pipeline_state(&compute_pipeline.pipeline_state);
+        }
+        self.workgroup_size = compute_pipeline.workgroup_size;
+    }
+
     pub fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, _binding: u32) {
+        let buffer = &uniform_buffer.buffer;
+        bind(&mut self.render_buffers, location, buffer.clone());
+        bind(&mut self.compute_buffers, location, buffer.clone());
+
         if let Some(encoder) = &self.encoder {
             encoder.set_vertex_buffer(location as u64, Some(&uniform_buffer.buffer), 0);
             encoder.set_fragment_buffer(location as u64, Some(&uniform_buffer.buffer), 0);
         }
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.set_buffer(location as u64, Some(&uniform_buffer.buffer), 0);
+        }
     }
 
-    pub fn use_texture(&mut self, texture: &Texture, location: u32) {
+    pub fn use_texture(&mut self, texture: &Texture, location: u32, _binding: u32) {
+        let binding = (texture.texture.clone(), texture.sampler.clone());
+        bind(&mut self.textures, location, binding);
+
         if let Some(encoder) = &self.encoder {
             encoder.set_fragment_texture(location as u64, Some(&texture.texture));
+            encoder.set_fragment_sampler_state(location as u64, Some(&texture.sampler));
+        }
+    }
+
+    /// Binds to the buffer index `location` of the compute function.
+    pub fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        bind(
+            &mut self.compute_buffers,
+            location,
+            vertex_buffer.buffer.clone(),
+        );
+
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.set_buffer(location as u64, Some(&vertex_buffer.buffer), 0);
+        }
+    }
+
+    /// Binds to the buffer index `location` of every function.
+    pub fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        let buffer = &storage_buffer.buffer;
+        bind(&mut self.render_buffers, location, buffer.clone());
+        bind(&mut self.compute_buffers, location, buffer.clone());
+
+        if let Some(encoder) = &self.encoder {
+            encoder.set_vertex_buffer(location as u64, Some(&storage_buffer.buffer), 0);
+            encoder.set_fragment_buffer(location as u64, Some(&storage_buffer.buffer), 0);
+        }
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.set_buffer(location as u64, Some(&storage_buffer.buffer), 0);
+        }
+    }
+
+    /// Binds to the texture index `location` of the compute function.
+    pub fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        bind(
+            &mut self.storage_textures,
+            location,
+            texture.texture.clone(),
+        );
+
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.set_texture(location as u64, Some(&texture.texture));
         }
     }
 
@@ -77,7 +385,7 @@ impl CommandBuffer {
             }
 
             encoder.draw_primitives_instanced(
-                metal::MTLPrimitiveType::TriangleStrip,
+                self.primitive_type,
                 0,
                 vertex_count as u64,
                 instance_count as u64,
@@ -85,7 +393,141 @@ impl CommandBuffer {
         }
     }
 
-    // pub fn draw_indexed(&self, renderable: &Renderable, index_count: usize, instance_count: usize) {}
+    pub fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        if let (Some(encoder), Some((index_buffer, format))) =
+            (&self.encoder, &renderable.index_buffer)
+        {
+            for (i, buffer) in &renderable.vertex_buffers {
+                encoder.set_vertex_buffer(*i as u64, Some(&buffer), 0);
+            }
+
+            encoder.draw_indexed_primitives_instanced_base_instance(
+                self.primitive_type,
+                index_count as u64,
+                match format {
+                    IndexFormat::UInt16 => metal::MTLIndexType::UInt16,
+                    IndexFormat::UInt32 => metal::MTLIndexType::UInt32,
+                },
+                index_buffer,
+                (index_offset * format.size()) as u64,
+                instance_count as u64,
+                base_vertex as i64,
+                0,
+            );
+        }
+    }
+
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.dispatch_thread_groups(
+                metal::MTLSize {
+                    width: x as u64,
+                    height: y as u64,
+                    depth: z as u64,
+                },
+                self.workgroup_size,
+            );
+        }
+    }
+
+    /// The dispatches of an encoder run one after the other, and Metal
+    /// tracks the buffers written by one encoder and read by the next.
+    pub fn memory_barrier(&mut self) {}
+}
+
+impl Frame for CommandBuffer {
+    type Device = Context;
+    type TextureLocation = u32;
+
+    #[allow(deprecated)]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        CommandBuffer::set_clear_color(self, clear_color)
+    }
+
+    fn begin_pass(&mut self, desc: &PassDesc) {
+        CommandBuffer::begin_pass(self, desc)
+    }
+
+    fn end_pass(&mut self) {
+        CommandBuffer::end_pass(self)
+    }
+
+    fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        CommandBuffer::use_pipeline(self, pipeline)
+    }
+
+    fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        CommandBuffer::use_compute_pipeline(self, compute_pipeline)
+    }
+
+    fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        CommandBuffer::use_uniform(self, uniform_buffer, location, binding)
+    }
+
+    fn use_texture(&mut self, texture: &Texture, location: u32, binding: u32) {
+        CommandBuffer::use_texture(self, texture, location, binding)
+    }
+
+    fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        CommandBuffer::use_storage_vertex_buffer(self, vertex_buffer, location)
+    }
+
+    fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        CommandBuffer::use_storage_buffer(self, storage_buffer, location)
+    }
+
+    fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        CommandBuffer::use_storage_texture(self, texture, location)
+    }
+
+    fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        CommandBuffer::draw(self, renderable, vertex_count, instance_count)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        CommandBuffer::draw_indexed(
+            self,
+            renderable,
+            index_count,
+            instance_count,
+            index_offset,
+            base_vertex,
+        )
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        CommandBuffer::dispatch(self, x, y, z)
+    }
+
+    fn memory_barrier(&mut self) {
+        CommandBuffer::memory_barrier(self)
+    }
+
+    fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        CommandBuffer::set_render_target(self, render_target)
+    }
+
+    fn set_viewport(&mut self, viewport: Option<Rect>) {
+        CommandBuffer::set_viewport(self, viewport)
+    }
+
+    fn set_scissor(&mut self, scissor: Option<Rect>) {
+        CommandBuffer::set_scissor(self, scissor)
+    }
 }
 
 impl Drop for CommandBuffer {
@@ -93,9 +535,64 @@ impl Drop for CommandBuffer {
         if let Some(encoder) = &self.encoder {
             encoder.end_encoding();
         }
+        if let Some(compute_encoder) = &self.compute_encoder {
+            compute_encoder.end_encoding();
+        }
 
         let command_buffer = self.command_buffer.as_ref();
         command_buffer.present_drawable(&self.screen);
         command_buffer.commit();
     }
 }
+
+/// Replaces the binding at `location`.
+fn bind<T>(bindings: &mut Vec<(u32, T)>, location: u32, value: T) {
+    bindings.retain(|(i, _value)| *i != location);
+    bindings.push((location, value));
+}
+
+fn load_action<T>(load: LoadAction<T>) -> metal::MTLLoadAction {
+    match load {
+        LoadAction::Load => metal::MTLLoadAction::Load,
+        LoadAction::Clear(_) => metal::MTLLoadAction::Clear,
+        LoadAction::DontCare => metal::MTLLoadAction::DontCare,
+    }
+}
+
+/// Multisampled attachments are resolved whether they are stored or not.
+fn store_action(store: StoreAction, resolve: bool) -> metal::MTLStoreAction {
+    match (store, resolve) {
+        (StoreAction::Store, false) => metal::MTLStoreAction::Store,
+        (StoreAction::DontCare, false) => metal::MTLStoreAction::DontCare,
+        (StoreAction::Store, true) => metal::MTLStoreAction::StoreAndMultisampleResolve,
+        (StoreAction::DontCare, true) => metal::MTLStoreAction::MultisampleResolve,
+    }
+}
+
+/// Metal keeps the rasterizer state in the encoder rather than in the
+/// pipeline state.
+fn set_rasterizer_state(encoder: &metal::RenderCommandEncoderRef, rasterizer: &RasterizerState) {
+    encoder.set_cull_mode(match rasterizer.cull_mode {
+        CullMode::None => metal::MTLCullMode::None,
+        CullMode::Front => metal::MTLCullMode::Front,
+        CullMode::Back => metal::MTLCullMode::Back,
+    });
+    encoder.set_front_facing_winding(match rasterizer.front_face {
+        FrontFace::CounterClockwise => metal::MTLWinding::CounterClockwise,
+        FrontFace::Clockwise => metal::MTLWinding::Clockwise,
+    });
+    encoder.set_triangle_fill_mode(match rasterizer.polygon_mode {
+        PolygonMode::Fill => metal::MTLTriangleFillMode::Fill,
+        PolygonMode::Line => metal::MTLTriangleFillMode::Lines,
+    });
+    encoder.set_depth_bias(
+        rasterizer.depth_bias,
+        rasterizer.depth_bias_slope_scale,
+        0.0,
+    );
+    encoder.set_depth_clip_mode(if rasterizer.depth_clamp {
+        metal::MTLDepthClipMode::Clamp
+    } else {
+        metal::MTLDepthClipMode::Clip
+    });
+}
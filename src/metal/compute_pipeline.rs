@@ -0,0 +1,37 @@
+use crate::metal::err::Result;
+use crate::metal::shader::Library;
+use crate::Error;
+use metal;
+
+pub struct ComputePipeline {
+    pub(crate) pipeline_state: metal::ComputePipelineState,
+    /// Given to every dispatch, Metal does not keep it in the pipeline state.
+    pub(crate) workgroup_size: metal::MTLSize,
+}
+
+impl ComputePipeline {
+    pub(crate) fn new(
+        device: &metal::Device,
+        library: &Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        let function = library
+            .library
+            .get_function(function_name, None)
+            .map_err(|_| Error::MissingFunction(function_name.into()))?;
+        let pipeline_state = device
+            .new_compute_pipeline_state_with_function(&function)
+            .map_err(|log| Error::ProgramLink { log })?;
+
+        let [width, height, depth] = workgroup_size;
+        Ok(ComputePipeline {
+            pipeline_state,
+            workgroup_size: metal::MTLSize {
+                width: width as u64,
+                height: height as u64,
+                depth: depth as u64,
+            },
+        })
+    }
+}
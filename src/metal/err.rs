@@ -1 +1 @@
-pub type Result<T> = std::result::Result<T, String>;
+pub type Result<T> = std::result::Result<T, crate::Error>;
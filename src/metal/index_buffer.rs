@@ -0,0 +1,28 @@
+use crate::metal::err::Result;
+use crate::{Index, IndexFormat};
+use metal;
+use std::mem::size_of_val;
+
+pub struct IndexBuffer {
+    pub(crate) buffer: metal::Buffer,
+    pub(crate) format: IndexFormat,
+}
+
+impl IndexBuffer {
+    pub(crate) fn with_data<T>(device: &metal::Device, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        let buffer = device.new_buffer_with_data(
+            indices.as_ptr() as *const _,
+            size_of_val(indices) as u64,
+            metal::MTLResourceOptions::CPUCacheModeDefaultCache
+                | metal::MTLResourceOptions::StorageModeManaged,
+        );
+
+        Ok(IndexBuffer {
+            buffer,
+            format: T::FORMAT,
+        })
+    }
+}
@@ -1,9 +1,21 @@
 use crate::metal::err::Result;
 use crate::metal::shader::Shader;
-use crate::shared::PipelineDesc;
+use crate::metal::texture::{compare_function, pixel_format};
+use crate::shared::{PipelineDesc, PrimitiveTopology, RasterizerState, StencilFaceState};
+use crate::{
+    BlendFactor, BlendOperation, ColorWriteMask, CompareFunction, Error, StencilOperation,
+    VertexFormat,
+};
 
 pub struct Pipeline {
     pub(crate) pipeline_state: metal::RenderPipelineState,
+    pub(crate) primitive_type: metal::MTLPrimitiveType,
+    pub(crate) depth_stencil_state: metal::DepthStencilState,
+    /// Set on the encoder, Metal does not keep it in the depth stencil state.
+    pub(crate) stencil_reference: u32,
+    /// Set on the encoder, Metal does not keep it in the pipeline state.
+    pub(crate) blend_constant: [f32; 4],
+    pub(crate) rasterizer: RasterizerState,
 }
 
 impl Pipeline {
@@ -16,47 +28,206 @@ impl Pipeline {
         pipeline_state_desc.set_vertex_function(Some(&shader.vertex_function));
         pipeline_state_desc.set_fragment_function(Some(&shader.fragment_function));
 
-        let attachment = pipeline_state_desc
-            .color_attachments()
-            .object_at(0)
-            .unwrap();
-        attachment.set_pixel_format(metal::MTLPixelFormat::BGRA8Unorm);
+        pipeline_state_desc.set_sample_count(desc.sample_count as u64);
+        pipeline_state_desc.set_alpha_to_coverage_enabled(desc.alpha_to_coverage);
+
+        let color_formats: Vec<metal::MTLPixelFormat> = if desc.color_formats.is_empty() {
+            vec![metal::MTLPixelFormat::BGRA8Unorm]
+        } else {
+            desc.color_formats
+                .iter()
+                .map(|format| pixel_format(*format))
+                .collect()
+        };
+        for (i, format) in color_formats.iter().enumerate() {
+            let attachment = pipeline_state_desc
+                .color_attachments()
+                .object_at(i as u64)
+                .unwrap();
+            attachment.set_pixel_format(*format);
+
+            let state = desc.color_attachment(i);
+            if let Some(blend) = &state.blend {
+                attachment.set_blending_enabled(true);
+                attachment.set_rgb_blend_operation(blend_operation(blend.color.operation));
+                attachment.set_alpha_blend_operation(blend_operation(blend.alpha.operation));
+                attachment.set_source_rgb_blend_factor(blend_factor(blend.color.src_factor));
+                attachment.set_source_alpha_blend_factor(blend_factor(blend.alpha.src_factor));
+                attachment.set_destination_rgb_blend_factor(blend_factor(blend.color.dst_factor));
+                attachment.set_destination_alpha_blend_factor(blend_factor(blend.alpha.dst_factor));
+            }
+            attachment.set_write_mask(write_mask(state.write_mask));
+        }
+
+        if let Some(format) = desc.depth_format {
+            pipeline_state_desc.set_depth_attachment_pixel_format(pixel_format(format));
+            if format.has_stencil() {
+                pipeline_state_desc.set_stencil_attachment_pixel_format(pixel_format(format));
+            }
+        }
 
         let vertex_desc = metal::VertexDescriptor::new();
         let attributes = vertex_desc.attributes();
         let layouts = vertex_desc.layouts();
         for (i, binding) in desc.bindings.iter().enumerate() {
             if let Some(attr) = attributes.object_at(i as u64) {
-                attr.set_format(metal::MTLVertexFormat::Float4);
+                attr.set_format(vertex_format(binding.format));
                 attr.set_buffer_index(binding.buffer as u64);
                 attr.set_offset(binding.offset as u64);
             }
 
             if let Some(layout) = layouts.object_at(binding.buffer as u64) {
                 layout.set_stride(binding.stride as u64);
-                layout.set_step_rate(1);
-                layout.set_step_function(if binding.instanced {
-                    metal::MTLVertexStepFunction::PerInstance
+                if binding.instance_step_rate == 0 {
+                    layout.set_step_function(metal::MTLVertexStepFunction::PerVertex);
+                    layout.set_step_rate(1);
                 } else {
-                    metal::MTLVertexStepFunction::PerVertex
-                });
+                    layout.set_step_function(metal::MTLVertexStepFunction::PerInstance);
+                    layout.set_step_rate(binding.instance_step_rate as u64);
+                }
             }
         }
         pipeline_state_desc.set_vertex_descriptor(Some(&vertex_desc));
 
-        if desc.alpha_blend {
-            attachment.set_blending_enabled(true);
-            attachment.set_rgb_blend_operation(metal::MTLBlendOperation::Add);
-            attachment.set_alpha_blend_operation(metal::MTLBlendOperation::Add);
-            attachment.set_source_rgb_blend_factor(metal::MTLBlendFactor::SourceAlpha);
-            attachment.set_source_alpha_blend_factor(metal::MTLBlendFactor::SourceAlpha);
-            attachment.set_destination_rgb_blend_factor(metal::MTLBlendFactor::OneMinusSourceAlpha);
-            attachment
-                .set_destination_alpha_blend_factor(metal::MTLBlendFactor::OneMinusSourceAlpha);
+        let pipeline_state = device
+            .new_render_pipeline_state(&pipeline_state_desc)
+            .map_err(|log| Error::ProgramLink { log })?;
+
+        // A disabled depth test neither rejects nor writes fragments, like on
+        // the OpenGL based backends.
+        let depth_stencil_desc = metal::DepthStencilDescriptor::new();
+        depth_stencil_desc.set_depth_compare_function(compare_function(
+            desc.depth_compare.unwrap_or(CompareFunction::Always),
+        ));
+        depth_stencil_desc
+            .set_depth_write_enabled(desc.depth_compare.is_some() && desc.depth_write);
+        if let Some(stencil) = &desc.stencil {
+            let front = stencil_descriptor(&stencil.front, stencil.read_mask, stencil.write_mask);
+            let back = stencil_descriptor(&stencil.back, stencil.read_mask, stencil.write_mask);
+            depth_stencil_desc.set_front_face_stencil(Some(&front));
+            depth_stencil_desc.set_back_face_stencil(Some(&back));
         }
+        let depth_stencil_state = device.new_depth_stencil_state(&depth_stencil_desc);
+
+        Ok(Pipeline {
+            pipeline_state,
+            primitive_type: match desc.topology {
+                PrimitiveTopology::Points => metal::MTLPrimitiveType::Point,
+                PrimitiveTopology::Lines => metal::MTLPrimitiveType::Line,
+                PrimitiveTopology::LineStrip => metal::MTLPrimitiveType::LineStrip,
+                PrimitiveTopology::Triangles => metal::MTLPrimitiveType::Triangle,
+                PrimitiveTopology::TriangleStrip => metal::MTLPrimitiveType::TriangleStrip,
+            },
+            depth_stencil_state,
+            stencil_reference: desc.stencil.map_or(0, |stencil| stencil.reference as u32),
+            blend_constant: desc.blend_constant,
+            rasterizer: desc.rasterizer,
+        })
+    }
+}
 
-        let pipeline_state = device.new_render_pipeline_state(&pipeline_state_desc)?;
+fn stencil_descriptor(
+    state: &StencilFaceState,
+    read_mask: u8,
+    write_mask: u8,
+) -> metal::StencilDescriptor {
+    let descriptor = metal::StencilDescriptor::new();
+    descriptor.set_stencil_compare_function(compare_function(state.compare));
+    descriptor.set_stencil_failure_operation(stencil_operation(state.fail_op));
+    descriptor.set_depth_failure_operation(stencil_operation(state.depth_fail_op));
+    descriptor.set_depth_stencil_pass_operation(stencil_operation(state.pass_op));
+    descriptor.set_read_mask(read_mask as u32);
+    descriptor.set_write_mask(write_mask as u32);
+    descriptor
+}
+
+fn stencil_operation(operation: StencilOperation) -> metal::MTLStencilOperation {
+    match operation {
+        StencilOperation::Keep => metal::MTLStencilOperation::Keep,
+        StencilOperation::Zero => metal::MTLStencilOperation::Zero,
+        StencilOperation::Replace => metal::MTLStencilOperation::Replace,
+        StencilOperation::IncrementClamp => metal::MTLStencilOperation::IncrementClamp,
+        StencilOperation::DecrementClamp => metal::MTLStencilOperation::DecrementClamp,
+        StencilOperation::Invert => metal::MTLStencilOperation::Invert,
+        StencilOperation::IncrementWrap => metal::MTLStencilOperation::IncrementWrap,
+        StencilOperation::DecrementWrap => metal::MTLStencilOperation::DecrementWrap,
+    }
+}
+
+fn blend_factor(factor: BlendFactor) -> metal::MTLBlendFactor {
+    match factor {
+        BlendFactor::Zero => metal::MTLBlendFactor::Zero,
+        BlendFactor::One => metal::MTLBlendFactor::One,
+        BlendFactor::SrcColor => metal::MTLBlendFactor::SourceColor,
+        BlendFactor::OneMinusSrcColor => metal::MTLBlendFactor::OneMinusSourceColor,
+        BlendFactor::SrcAlpha => metal::MTLBlendFactor::SourceAlpha,
+        BlendFactor::OneMinusSrcAlpha => metal::MTLBlendFactor::OneMinusSourceAlpha,
+        BlendFactor::DstColor => metal::MTLBlendFactor::DestinationColor,
+        BlendFactor::OneMinusDstColor => metal::MTLBlendFactor::OneMinusDestinationColor,
+        BlendFactor::DstAlpha => metal::MTLBlendFactor::DestinationAlpha,
+        BlendFactor::OneMinusDstAlpha => metal::MTLBlendFactor::OneMinusDestinationAlpha,
+        BlendFactor::SrcAlphaSaturated => metal::MTLBlendFactor::SourceAlphaSaturated,
+        BlendFactor::Constant => metal::MTLBlendFactor::BlendColor,
+        BlendFactor::OneMinusConstant => metal::MTLBlendFactor::OneMinusBlendColor,
+        BlendFactor::ConstantAlpha => metal::MTLBlendFactor::BlendAlpha,
+        BlendFactor::OneMinusConstantAlpha => metal::MTLBlendFactor::OneMinusBlendAlpha,
+    }
+}
+
+fn blend_operation(operation: BlendOperation) -> metal::MTLBlendOperation {
+    match operation {
+        BlendOperation::Add => metal::MTLBlendOperation::Add,
+        BlendOperation::Subtract => metal::MTLBlendOperation::Subtract,
+        BlendOperation::ReverseSubtract => metal::MTLBlendOperation::ReverseSubtract,
+        BlendOperation::Min => metal::MTLBlendOperation::Min,
+        BlendOperation::Max => metal::MTLBlendOperation::Max,
+    }
+}
+
+fn write_mask(mask: ColorWriteMask) -> metal::MTLColorWriteMask {
+    let mut write_mask = metal::MTLColorWriteMask::empty();
+    if mask.red {
+        write_mask |= metal::MTLColorWriteMask::Red;
+    }
+    if mask.green {
+        write_mask |= metal::MTLColorWriteMask::Green;
+    }
+    if mask.blue {
+        write_mask |= metal::MTLColorWriteMask::Blue;
+    }
+    if mask.alpha {
+        write_mask |= metal::MTLColorWriteMask::Alpha;
+    }
+    write_mask
+}
 
-        Ok(Pipeline { pipeline_state })
+fn vertex_format(format: VertexFormat) -> metal::MTLVertexFormat {
+    match format {
+        VertexFormat::Float => metal::MTLVertexFormat::Float,
+        VertexFormat::Float2 => metal::MTLVertexFormat::Float2,
+        VertexFormat::Float3 => metal::MTLVertexFormat::Float3,
+        VertexFormat::Float4 => metal::MTLVertexFormat::Float4,
+        VertexFormat::Half2 => metal::MTLVertexFormat::Half2,
+        VertexFormat::Half4 => metal::MTLVertexFormat::Half4,
+        VertexFormat::UChar4 => metal::MTLVertexFormat::UChar4,
+        VertexFormat::Char4 => metal::MTLVertexFormat::Char4,
+        VertexFormat::UChar4Normalized => metal::MTLVertexFormat::UChar4Normalized,
+        VertexFormat::Char4Normalized => metal::MTLVertexFormat::Char4Normalized,
+        VertexFormat::UShort2 => metal::MTLVertexFormat::UShort2,
+        VertexFormat::UShort4 => metal::MTLVertexFormat::UShort4,
+        VertexFormat::Short2 => metal::MTLVertexFormat::Short2,
+        VertexFormat::Short4 => metal::MTLVertexFormat::Short4,
+        VertexFormat::UShort2Normalized => metal::MTLVertexFormat::UShort2Normalized,
+        VertexFormat::UShort4Normalized => metal::MTLVertexFormat::UShort4Normalized,
+        VertexFormat::Short2Normalized => metal::MTLVertexFormat::Short2Normalized,
+        VertexFormat::Short4Normalized => metal::MTLVertexFormat::Short4Normalized,
+        VertexFormat::Int => metal::MTLVertexFormat::Int,
+        VertexFormat::Int2 => metal::MTLVertexFormat::Int2,
+        VertexFormat::Int3 => metal::MTLVertexFormat::Int3,
+        VertexFormat::Int4 => metal::MTLVertexFormat::Int4,
+        VertexFormat::UInt => metal::MTLVertexFormat::UInt,
+        VertexFormat::UInt2 => metal::MTLVertexFormat::UInt2,
+        VertexFormat::UInt3 => metal::MTLVertexFormat::UInt3,
+        VertexFormat::UInt4 => metal::MTLVertexFormat::UInt4,
     }
 }
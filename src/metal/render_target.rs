@@ -0,0 +1,126 @@
+use crate::metal::err::Result;
+use crate::metal::texture::pixel_format;
+use crate::metal::Texture;
+use crate::shared::image::check_sample_count;
+use crate::{Attachments, TextureFormat};
+use metal;
+
+pub struct RenderTarget {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) sample_count: u32,
+    pub(crate) color_textures: Vec<Texture>,
+    pub(crate) depth_texture: Option<Texture>,
+    pub(crate) depth_format: Option<TextureFormat>,
+    /// Drawn into instead of the textures with more than one sample, and
+    /// resolved into them when a pass ends.
+    pub(crate) multisample_color_textures: Vec<metal::Texture>,
+    pub(crate) multisample_depth_texture: Option<metal::Texture>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        check_sample_count(sample_count)?;
+
+        let color_textures = color_formats
+            .iter()
+            .map(|format| Texture::with_format(device, command_queue, width, height, *format))
+            .collect::<Result<Vec<Texture>>>()?;
+        let depth_texture = match depth_format {
+            Some(format) => Some(Texture::with_format(
+                device,
+                command_queue,
+                width,
+                height,
+                format,
+            )?),
+            None => None,
+        };
+
+        let (multisample_color_textures, multisample_depth_texture) = if sample_count > 1 {
+            (
+                color_formats
+                    .iter()
+                    .map(|format| {
+                        new_multisample_texture(device, width, height, *format, sample_count)
+                    })
+                    .collect(),
+                depth_format.map(|format| {
+                    new_multisample_texture(device, width, height, format, sample_count)
+                }),
+            )
+        } else {
+            (Vec::new(), None)
+        };
+
+        Ok(RenderTarget {
+            width,
+            height,
+            sample_count,
+            color_textures,
+            depth_texture,
+            depth_format,
+            multisample_color_textures,
+            multisample_depth_texture,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.color_textures
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+}
+
+impl Attachments for RenderTarget {
+    type Texture = Texture;
+
+    fn color_textures(&self) -> &[Texture] {
+        RenderTarget::color_textures(self)
+    }
+
+    fn depth_texture(&self) -> Option<&Texture> {
+        RenderTarget::depth_texture(self)
+    }
+}
+
+/// Multisampled textures only live on the GPU, they are never sampled.
+fn new_multisample_texture(
+    device: &metal::Device,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    sample_count: u32,
+) -> metal::Texture {
+    let desc = metal::TextureDescriptor::new();
+    desc.set_texture_type(metal::MTLTextureType::D2Multisample);
+    desc.set_width(width as u64);
+    desc.set_height(height as u64);
+    desc.set_pixel_format(pixel_format(format));
+    desc.set_sample_count(sample_count as u64);
+    desc.set_usage(metal::MTLTextureUsage::RenderTarget);
+    desc.set_storage_mode(metal::MTLStorageMode::Private);
+    device.new_texture(&desc)
+}
@@ -1,10 +1,11 @@
 use crate::metal::err::Result;
-use crate::metal::VertexBuffer;
+use crate::metal::{IndexBuffer, VertexBuffer};
+use crate::IndexFormat;
 use metal;
-// use crate::metal::index_buffer::IndexBuffer;
 
 pub struct Renderable {
     pub(crate) vertex_buffers: Vec<(u32, metal::Buffer)>,
+    pub(crate) index_buffer: Option<(metal::Buffer, IndexFormat)>,
 }
 
 impl Renderable {
@@ -14,35 +15,16 @@ impl Renderable {
                 .iter()
                 .map(|(i, vertex_buffer)| (*i, vertex_buffer.buffer.clone()))
                 .collect(),
+            index_buffer: None,
         })
     }
 
-    // pub(crate) fn from_bindings_and_index(
-    //     bindings: &[Binding<VertexBuffer>],
-    //     index_buffer: &IndexBuffer,
-    // ) -> CrystalResult<Renderable> {
-    //     unsafe {
-    //         let mut vertex_array = 0;
-    //         gl::GenVertexArrays(1, &mut vertex_array);
-    //         gl::BindVertexArray(vertex_array);
-
-    //         for binding in bindings {
-    //             gl::BindBuffer(gl::ARRAY_BUFFER, binding.buffer.buffer);
-    //             gl::VertexAttribPointer(
-    //                 binding.attribute,
-    //                 4,
-    //                 gl::FLOAT,
-    //                 gl::FALSE,
-    //                 binding.stride as i32,
-    //                 binding.offset as usize as *const _,
-    //             );
-    //             gl::VertexAttribDivisor(binding.attribute, if binding.instanced { 1 } else { 0 });
-    //             gl::EnableVertexAttribArray(binding.attribute);
-    //         }
-
-    //         gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer.buffer);
-
-    //         Ok(Renderable { vertex_array })
-    //     }
-    // }
+    pub(crate) fn with_index(
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        let mut renderable = Renderable::new(vertex_buffers)?;
+        renderable.index_buffer = Some((index_buffer.buffer.clone(), index_buffer.format));
+        Ok(renderable)
+    }
 }
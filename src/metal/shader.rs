@@ -1,4 +1,5 @@
 use crate::metal::err::Result;
+use crate::Error;
 use metal;
 use std::str;
 
@@ -8,7 +9,9 @@ pub struct Library {
 
 impl Library {
     pub(crate) fn new(device: &metal::Device, library_path: &str) -> Result<Library> {
-        let library = device.new_library_with_file(library_path)?;
+        let library = device
+            .new_library_with_file(library_path)
+            .map_err(|e| Error::Backend(e.into()))?;
 
         Ok(Library { library })
     }
@@ -25,8 +28,12 @@ impl Shader {
         vertex_name: &str,
         fragment_name: &str,
     ) -> Result<Shader> {
-        let vertex_function = library.get_function(vertex_name, None)?;
-        let fragment_function = library.get_function(fragment_name, None)?;
+        let vertex_function = library
+            .get_function(vertex_name, None)
+            .map_err(|_| Error::MissingFunction(vertex_name.into()))?;
+        let fragment_function = library
+            .get_function(fragment_name, None)
+            .map_err(|_| Error::MissingFunction(fragment_name.into()))?;
 
         Ok(Shader {
             vertex_function,
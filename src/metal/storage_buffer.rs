@@ -0,0 +1,96 @@
+use crate::metal::err::Result;
+use crate::Error;
+use metal;
+use std::mem::size_of_val;
+use std::slice;
+
+/// Storage buffers are shared with the CPU, so they can be read back
+/// without copying them first.
+pub struct StorageBuffer {
+    pub(crate) buffer: metal::Buffer,
+    /// The number of bytes that can be stored in this buffer.
+    pub(crate) capacity: usize,
+    /// Used to wait for the frames writing into the buffer.
+    pub(crate) command_queue: metal::CommandQueue,
+}
+
+impl StorageBuffer {
+    pub(crate) fn with_capacity(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        capacity: usize,
+    ) -> Result<StorageBuffer> {
+        let buffer = device.new_buffer(
+            capacity as u64,
+            metal::MTLResourceOptions::StorageModeShared,
+        );
+
+        Ok(StorageBuffer {
+            buffer,
+            capacity,
+            command_queue: command_queue.clone(),
+        })
+    }
+
+    pub(crate) fn with_data<T>(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        data: &[T],
+    ) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        let capacity = size_of_val(data);
+        let buffer = device.new_buffer_with_data(
+            data.as_ptr() as *const _,
+            capacity as u64,
+            metal::MTLResourceOptions::StorageModeShared,
+        );
+
+        Ok(StorageBuffer {
+            buffer,
+            capacity,
+            command_queue: command_queue.clone(),
+        })
+    }
+
+    /// Replaces the start of the buffer, the rest keeps its contents.
+    pub(crate) fn update<T>(&mut self, data: &[T]) -> Result<()>
+    where
+        T: Sized,
+    {
+        let length = size_of_val(data);
+        if length > self.capacity {
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.buffer.contents() as *mut T,
+                data.len(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Command buffers run in the order they are committed, so an empty one
+    /// finishes after every frame submitted before.
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        let command_buffer = self.command_queue.new_command_buffer();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        let contents =
+            unsafe { slice::from_raw_parts(self.buffer.contents() as *const u8, self.capacity) };
+        Ok(contents.to_vec())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -1,41 +1,471 @@
 use crate::metal::err::Result;
-use crate::shared::image::load_bgra_image;
+use crate::shared::f16_to_f32;
+use crate::shared::image::{
+    check_mip_levels, check_region, check_update_kind, kind_mip_level_count, layer_count,
+    load_rgba_image, mip_level_size, rgba_image_from_depth, rgba_image_from_f32,
+};
+use crate::{
+    AddressMode, BorderColor, CompareFunction, Error, FilterMode, SamplerDesc, TextureFormat,
+    TextureKind, TextureRegion,
+};
 use foreign_types::ForeignType;
+use image::RgbaImage;
 use metal;
+use objc::{msg_send, sel, sel_impl};
+use std::convert::TryInto;
 use std::ptr::null_mut;
+use std::slice;
 
 pub struct Texture {
     pub(crate) texture: metal::Texture,
+    pub(crate) kind: TextureKind,
+    pub(crate) format: TextureFormat,
+    /// Bound along with the texture, at the same index.
+    pub(crate) sampler: metal::SamplerState,
+    /// The queue the texture is drawn with, reads are queued behind it.
+    pub(crate) command_queue: metal::CommandQueue,
 }
 
 impl Texture {
-    pub(crate) fn new(device: &metal::Device, image_path: &str) -> Result<Texture> {
-        let img = load_bgra_image(image_path)?;
+    pub(crate) fn new(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        let img = load_rgba_image(image_path)?;
 
-        let width = img.width();
-        let height = img.height();
+        Texture::with_pixels(
+            device,
+            command_queue,
+            TextureKind::D2,
+            img.width(),
+            img.height(),
+            TextureFormat::Rgba8,
+            &img,
+            sampler,
+        )
+    }
+
+    /// Creates a texture from tightly packed texels, with a full mip chain
+    /// generated by the GPU if the sampler filters between mip levels.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn with_pixels(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, &[data])?;
+        let mip_level_count = match sampler.mip_filter {
+            Some(_) => kind_mip_level_count(kind, width, height),
+            None => 1,
+        };
+
+        let texture = Texture::with_levels(
+            device,
+            command_queue,
+            kind,
+            width,
+            height,
+            format,
+            mip_level_count,
+            &[data],
+            sampler,
+        )?;
+        if mip_level_count > 1 {
+            let command_buffer = command_queue.new_command_buffer();
+            let encoder = command_buffer.new_blit_command_encoder();
+            // Not wrapped by the metal crate.
+            let () = unsafe { msg_send![encoder, generateMipmapsForTexture: &*texture.texture] };
+            encoder.end_encoding();
+            command_buffer.commit();
+        }
+
+        Ok(texture)
+    }
+
+    /// Every level holds the layers, slices or cube faces of the texture one
+    /// after the other.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn with_mip_levels(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, levels)?;
+
+        Texture::with_levels(
+            device,
+            command_queue,
+            kind,
+            width,
+            height,
+            format,
+            levels.len() as u32,
+            levels,
+            sampler,
+        )
+    }
+
+    /// Creates a texture with `mip_level_count` levels and fills the first
+    /// ones with `levels`. Depth textures are private to the GPU and can not
+    /// be filled from the CPU.
+    #[allow(clippy::too_many_arguments)]
+    fn with_levels(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        mip_level_count: u32,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        if format.is_depth() {
+            return Err(Error::Unsupported(
+                "creating depth textures from pixels on Metal".into(),
+            ));
+        }
 
         let desc = metal::TextureDescriptor::new();
+        desc.set_texture_type(texture_type(kind));
         desc.set_width(width as u64);
         desc.set_height(height as u64);
-        desc.set_pixel_format(metal::MTLPixelFormat::BGRA8Unorm);
+        match kind {
+            TextureKind::D2Array { layers } => desc.set_array_length(layers as u64),
+            TextureKind::D3 { depth } => desc.set_depth(depth as u64),
+            TextureKind::D2 | TextureKind::Cube => {}
+        }
+        desc.set_pixel_format(pixel_format(format));
+        desc.set_mipmap_level_count(mip_level_count as u64);
+        desc.set_usage(shader_usage(format));
 
         let texture = device.new_texture(&desc);
-        texture.replace_region(
+        for (mip_level, data) in levels.iter().enumerate() {
+            let (width, height) = mip_level_size(width, height, mip_level as u32);
+            let bytes_per_row = width as usize * format.texel_size();
+            let bytes_per_image = bytes_per_row * height as usize;
+
+            // The slices of a 3D texture are replaced as one region, array
+            // layers and cube faces are slices of their own.
+            let layers = layer_count(kind, mip_level as u32);
+            let (slice_count, depth) = match kind {
+                TextureKind::D3 { .. } => (1, layers),
+                _ => (layers, 1),
+            };
+            for slice in 0..slice_count as usize {
+                texture.replace_region_in_slice(
+                    metal::MTLRegion {
+                        origin: metal::MTLOrigin { x: 0, y: 0, z: 0 },
+                        size: metal::MTLSize {
+                            width: width as u64,
+                            height: height as u64,
+                            depth: depth as u64,
+                        },
+                    },
+                    mip_level as u64,
+                    slice as u64,
+                    data[slice * bytes_per_image..].as_ptr() as *const _,
+                    bytes_per_row as u64,
+                    bytes_per_image as u64,
+                );
+            }
+        }
+
+        Ok(Texture {
+            texture,
+            kind,
+            format,
+            sampler: new_sampler(device, sampler),
+            command_queue: command_queue.clone(),
+        })
+    }
+
+    /// Creates a texture with undefined contents, to be rendered into.
+    pub(crate) fn with_format(
+        device: &metal::Device,
+        command_queue: &metal::CommandQueue,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<Texture> {
+        let desc = metal::TextureDescriptor::new();
+        desc.set_width(width as u64);
+        desc.set_height(height as u64);
+        desc.set_pixel_format(pixel_format(format));
+        desc.set_usage(metal::MTLTextureUsage::RenderTarget | shader_usage(format));
+        if format.is_depth() {
+            desc.set_storage_mode(metal::MTLStorageMode::Private);
+        }
+
+        Ok(Texture {
+            texture: device.new_texture(&desc),
+            kind: TextureKind::D2,
+            format,
+            sampler: new_sampler(device, &SamplerDesc::default()),
+            command_queue: command_queue.clone(),
+        })
+    }
+
+    /// Depth textures are private to the GPU and can not be updated from the
+    /// CPU.
+    pub(crate) fn update(
+        &mut self,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        check_update_kind(self.kind)?;
+        check_region(
+            self.texture.width() as u32,
+            self.texture.height() as u32,
+            self.texture.mipmap_level_count() as u32,
+            self.format,
+            region,
+            mip_level,
+            data,
+        )?;
+        if self.format.is_depth() {
+            return Err(Error::Unsupported(
+                "updating depth textures on Metal".into(),
+            ));
+        }
+
+        self.texture.replace_region(
             metal::MTLRegion {
-                origin: metal::MTLOrigin { x: 0, y: 0, z: 0 },
+                origin: metal::MTLOrigin {
+                    x: region.x as u64,
+                    y: region.y as u64,
+                    z: 0,
+                },
                 size: metal::MTLSize {
-                    width: width as u64,
-                    height: height as u64,
+                    width: region.width as u64,
+                    height: region.height as u64,
                     depth: 1,
                 },
             },
-            0,
-            img.as_ptr() as *const _,
-            width as u64 * 4,
+            mip_level as u64,
+            data.as_ptr() as *const _,
+            (region.width as usize * self.format.texel_size()) as u64,
         );
+        Ok(())
+    }
+
+    /// Reads back the texture as an 8 bit RGBA image with the first row at
+    /// the top. Float formats are clamped to `[0, 1]` and depth formats are
+    /// read as grayscale. Only the first layer, slice or face is read.
+    pub fn read_to_image(&self) -> Result<RgbaImage> {
+        read_texture(&self.command_queue, &self.texture)
+    }
+}
+
+/// Metal has to know up front which textures shaders write into, so every
+/// texture that can be bound as a storage image is created writable.
+fn shader_usage(format: TextureFormat) -> metal::MTLTextureUsage {
+    if format.is_depth() || format == TextureFormat::Rgba8Srgb {
+        metal::MTLTextureUsage::ShaderRead
+    } else {
+        metal::MTLTextureUsage::ShaderRead | metal::MTLTextureUsage::ShaderWrite
+    }
+}
+
+/// Copies the texture into a shared buffer once the work queued before has
+/// finished, and converts the texels to RGBA.
+pub(crate) fn read_texture(
+    command_queue: &metal::CommandQueueRef,
+    texture: &metal::TextureRef,
+) -> Result<RgbaImage> {
+    let width = texture.width();
+    let height = texture.height();
+    let pixel_format = texture.pixel_format();
+    let (texel_size, options) = match pixel_format {
+        metal::MTLPixelFormat::R8Unorm => (1, metal::MTLBlitOption::empty()),
+        metal::MTLPixelFormat::RG8Unorm | metal::MTLPixelFormat::R16Float => {
+            (2, metal::MTLBlitOption::empty())
+        }
+        metal::MTLPixelFormat::BGRA8Unorm
+        | metal::MTLPixelFormat::RGBA8Unorm
+        | metal::MTLPixelFormat::RGBA8Unorm_sRGB
+        | metal::MTLPixelFormat::R32Float
+        | metal::MTLPixelFormat::Depth32Float => (4, metal::MTLBlitOption::empty()),
+        metal::MTLPixelFormat::Depth32Float_Stencil8 => {
+            (4, metal::MTLBlitOption::DepthFromDepthStencil)
+        }
+        metal::MTLPixelFormat::RGBA16Float => (8, metal::MTLBlitOption::empty()),
+        metal::MTLPixelFormat::RGBA32Float => (16, metal::MTLBlitOption::empty()),
+        _ => {
+            return Err(Error::Unsupported(format!(
+                "reading {:?} textures",
+                pixel_format
+            )))
+        }
+    };
+
+    let bytes_per_row = width * texel_size;
+    let length = bytes_per_row * height;
+    let buffer = command_queue
+        .device()
+        .new_buffer(length, metal::MTLResourceOptions::StorageModeShared);
+
+    let command_buffer = command_queue.new_command_buffer();
+    let encoder = command_buffer.new_blit_command_encoder();
+    encoder.copy_from_texture_to_buffer(
+        texture,
+        0,
+        0,
+        metal::MTLOrigin { x: 0, y: 0, z: 0 },
+        metal::MTLSize {
+            width,
+            height,
+            depth: 1,
+        },
+        &buffer,
+        0,
+        bytes_per_row,
+        length,
+        options,
+    );
+    encoder.end_encoding();
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+
+    let data = unsafe { slice::from_raw_parts(buffer.contents() as *const u8, length as usize) };
+    let (width, height) = (width as u32, height as u32);
+    let img = match pixel_format {
+        metal::MTLPixelFormat::BGRA8Unorm => {
+            let mut data = data.to_vec();
+            for texel in data.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+            RgbaImage::from_raw(width, height, data).unwrap()
+        }
+        metal::MTLPixelFormat::R8Unorm
+        | metal::MTLPixelFormat::RG8Unorm
+        | metal::MTLPixelFormat::RGBA8Unorm
+        | metal::MTLPixelFormat::RGBA8Unorm_sRGB => {
+            let components = texel_size as usize;
+            let mut img = RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+            for (pixel, texel) in img.pixels_mut().zip(data.chunks_exact(components)) {
+                pixel.0[..components].copy_from_slice(texel);
+            }
+            img
+        }
+        metal::MTLPixelFormat::R16Float | metal::MTLPixelFormat::RGBA16Float => {
+            let texels: Vec<f32> = data
+                .chunks_exact(2)
+                .map(|bytes| f16_to_f32(u16::from_ne_bytes(bytes.try_into().unwrap())))
+                .collect();
+            rgba_image_from_f32(width, height, texel_size as usize / 2, &texels)
+        }
+        metal::MTLPixelFormat::R32Float | metal::MTLPixelFormat::RGBA32Float => {
+            let texels: Vec<f32> = data
+                .chunks_exact(4)
+                .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect();
+            rgba_image_from_f32(width, height, texel_size as usize / 4, &texels)
+        }
+        _ => {
+            let depths: Vec<f32> = data
+                .chunks_exact(4)
+                .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect();
+            rgba_image_from_depth(width, height, &depths)
+        }
+    };
+
+    Ok(img)
+}
+
+fn texture_type(kind: TextureKind) -> metal::MTLTextureType {
+    match kind {
+        TextureKind::D2 => metal::MTLTextureType::D2,
+        TextureKind::D2Array { .. } => metal::MTLTextureType::D2Array,
+        TextureKind::D3 { .. } => metal::MTLTextureType::D3,
+        TextureKind::Cube => metal::MTLTextureType::Cube,
+    }
+}
+
+pub(crate) fn pixel_format(format: TextureFormat) -> metal::MTLPixelFormat {
+    match format {
+        TextureFormat::R8 => metal::MTLPixelFormat::R8Unorm,
+        TextureFormat::Rg8 => metal::MTLPixelFormat::RG8Unorm,
+        TextureFormat::Rgba8 => metal::MTLPixelFormat::RGBA8Unorm,
+        TextureFormat::Rgba8Srgb => metal::MTLPixelFormat::RGBA8Unorm_sRGB,
+        TextureFormat::R16F => metal::MTLPixelFormat::R16Float,
+        TextureFormat::Rgba16F => metal::MTLPixelFormat::RGBA16Float,
+        TextureFormat::R32F => metal::MTLPixelFormat::R32Float,
+        TextureFormat::Rgba32F => metal::MTLPixelFormat::RGBA32Float,
+        // 24 bit depth is not available on every Mac, the 32 bit float
+        // format with stencil is.
+        TextureFormat::Depth24Stencil8 => metal::MTLPixelFormat::Depth32Float_Stencil8,
+        TextureFormat::Depth32F => metal::MTLPixelFormat::Depth32Float,
+    }
+}
+
+fn new_sampler(device: &metal::Device, sampler: &SamplerDesc) -> metal::SamplerState {
+    let desc = metal::SamplerDescriptor::new();
+    desc.set_address_mode_s(address_mode(sampler.address_mode_u));
+    desc.set_address_mode_t(address_mode(sampler.address_mode_v));
+    desc.set_address_mode_r(address_mode(sampler.address_mode_w));
+    desc.set_mag_filter(filter_mode(sampler.mag_filter));
+    desc.set_min_filter(filter_mode(sampler.min_filter));
+    desc.set_mip_filter(match sampler.mip_filter {
+        None => metal::MTLSamplerMipFilter::NotMipmapped,
+        Some(FilterMode::Nearest) => metal::MTLSamplerMipFilter::Nearest,
+        Some(FilterMode::Linear) => metal::MTLSamplerMipFilter::Linear,
+    });
+    desc.set_lod_min_clamp(sampler.lod_min_clamp);
+    desc.set_lod_max_clamp(sampler.lod_max_clamp);
+    desc.set_max_anisotropy(sampler.max_anisotropy.clamp(1, 16) as u64);
+    if let Some(compare) = sampler.compare {
+        desc.set_compare_function(compare_function(compare));
+    }
+    desc.set_border_color(match sampler.border_color {
+        BorderColor::TransparentBlack => metal::MTLSamplerBorderColor::TransparentBlack,
+        BorderColor::OpaqueBlack => metal::MTLSamplerBorderColor::OpaqueBlack,
+        BorderColor::OpaqueWhite => metal::MTLSamplerBorderColor::OpaqueWhite,
+    });
+
+    device.new_sampler(&desc)
+}
+
+fn address_mode(mode: AddressMode) -> metal::MTLSamplerAddressMode {
+    match mode {
+        AddressMode::ClampToEdge => metal::MTLSamplerAddressMode::ClampToEdge,
+        AddressMode::Repeat => metal::MTLSamplerAddressMode::Repeat,
+        AddressMode::MirrorRepeat => metal::MTLSamplerAddressMode::MirrorRepeat,
+        AddressMode::ClampToBorder => metal::MTLSamplerAddressMode::ClampToBorderColor,
+    }
+}
+
+fn filter_mode(mode: FilterMode) -> metal::MTLSamplerMinMagFilter {
+    match mode {
+        FilterMode::Nearest => metal::MTLSamplerMinMagFilter::Nearest,
+        FilterMode::Linear => metal::MTLSamplerMinMagFilter::Linear,
+    }
+}
 
-        Ok(Texture { texture })
+pub(crate) fn compare_function(function: CompareFunction) -> metal::MTLCompareFunction {
+    match function {
+        CompareFunction::Never => metal::MTLCompareFunction::Never,
+        CompareFunction::Less => metal::MTLCompareFunction::Less,
+        CompareFunction::Equal => metal::MTLCompareFunction::Equal,
+        CompareFunction::LessEqual => metal::MTLCompareFunction::LessEqual,
+        CompareFunction::Greater => metal::MTLCompareFunction::Greater,
+        CompareFunction::NotEqual => metal::MTLCompareFunction::NotEqual,
+        CompareFunction::GreaterEqual => metal::MTLCompareFunction::GreaterEqual,
+        CompareFunction::Always => metal::MTLCompareFunction::Always,
     }
 }
 
@@ -43,6 +473,10 @@ impl Default for Texture {
     fn default() -> Texture {
         Texture {
             texture: unsafe { metal::Texture::from_ptr(null_mut()) },
+            kind: TextureKind::D2,
+            format: TextureFormat::Rgba8,
+            sampler: unsafe { metal::SamplerState::from_ptr(null_mut()) },
+            command_queue: unsafe { metal::CommandQueue::from_ptr(null_mut()) },
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::metal::err::Result;
+use crate::Error;
 use cocoa::foundation::NSRange;
 use foreign_types::ForeignType;
 use metal;
@@ -44,7 +45,10 @@ impl UniformBuffer {
     {
         let length = size_of::<T>();
         if length > self.capacity {
-            return Err("updating uniform buffer: new data is longer than buffer capacity".into());
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
         }
 
         let p = self.buffer.contents();
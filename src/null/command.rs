@@ -0,0 +1,77 @@
+use crate::{Operations, Rect};
+
+/// A single call recorded by a `CommandBuffer`.
+///
+/// Resources are referred to by the `id()` of the object that was used, and
+/// buffer contents are copied at the time of the call, so updating a buffer
+/// afterwards does not change what was recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    SetClearColor(Option<(f32, f32, f32, f32)>),
+    BeginPass {
+        color: Vec<Operations<[f32; 4]>>,
+        depth: Operations<f32>,
+        stencil: Operations<u8>,
+    },
+    EndPass,
+    SetRenderTarget {
+        render_target: Option<u32>,
+    },
+    SetViewport(Option<Rect>),
+    SetScissor(Option<Rect>),
+    UsePipeline {
+        pipeline: u32,
+    },
+    UseComputePipeline {
+        compute_pipeline: u32,
+    },
+    UseStorageVertexBuffer {
+        vertex_buffer: u32,
+        location: u32,
+    },
+    UseStorageBuffer {
+        storage_buffer: u32,
+        location: u32,
+        data: Vec<u8>,
+    },
+    UseStorageTexture {
+        texture: u32,
+        location: u32,
+    },
+    UseUniform {
+        uniform_buffer: u32,
+        location: u32,
+        binding: u32,
+        data: Vec<u8>,
+    },
+    UseTexture {
+        texture: u32,
+        location: u32,
+        binding: u32,
+    },
+    Draw {
+        renderable: u32,
+        /// The contents of every vertex buffer of the renderable, along with
+        /// the buffer index it is bound to.
+        vertex_buffers: Vec<(u32, Vec<u8>)>,
+        vertex_count: usize,
+        instance_count: usize,
+    },
+    DrawIndexed {
+        renderable: u32,
+        vertex_buffers: Vec<(u32, Vec<u8>)>,
+        index_buffer: u32,
+        /// Every index of the index buffer, widened to `u32`.
+        indices: Vec<u32>,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    },
+    Dispatch {
+        x: u32,
+        y: u32,
+        z: u32,
+    },
+    MemoryBarrier,
+}
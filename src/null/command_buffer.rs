@@ -0,0 +1,253 @@
+use crate::null::err::Result;
+use crate::null::*;
+use crate::{Frame, PassDesc, Rect};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct CommandBuffer {
+    pub(crate) frames: Rc<RefCell<Vec<Vec<Command>>>>,
+    pub(crate) commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub(crate) fn new(frames: &Rc<RefCell<Vec<Vec<Command>>>>) -> Result<CommandBuffer> {
+        Ok(CommandBuffer {
+            frames: Rc::clone(frames),
+            commands: Vec::new(),
+        })
+    }
+
+    /// This is a NULL ONLY API, it returns the commands recorded so far.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Deprecated, see `Frame::set_clear_color`.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
+    pub fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        self.commands.push(Command::SetClearColor(clear_color));
+    }
+
+    pub fn begin_pass(&mut self, desc: &PassDesc) {
+        self.commands.push(Command::BeginPass {
+            color: desc.color.to_vec(),
+            depth: desc.depth,
+            stencil: desc.stencil,
+        });
+    }
+
+    pub fn end_pass(&mut self) {
+        self.commands.push(Command::EndPass);
+    }
+
+    pub fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        self.commands.push(Command::SetRenderTarget {
+            render_target: render_target.map(|render_target| render_target.id),
+        });
+    }
+
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        self.commands.push(Command::SetViewport(viewport));
+    }
+
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        self.commands.push(Command::SetScissor(scissor));
+    }
+
+    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        self.commands.push(Command::UsePipeline {
+            pipeline: pipeline.id,
+        });
+    }
+
+    pub fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        self.commands.push(Command::UseComputePipeline {
+            compute_pipeline: compute_pipeline.id,
+        });
+    }
+
+    pub fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        self.commands.push(Command::UseUniform {
+            uniform_buffer: uniform_buffer.id,
+            location,
+            binding,
+            data: uniform_buffer.buffer.0.borrow().clone(),
+        });
+    }
+
+    pub fn use_texture(&mut self, texture: &Texture, location: u32, binding: u32) {
+        self.commands.push(Command::UseTexture {
+            texture: texture.id,
+            location,
+            binding,
+        });
+    }
+
+    /// Nothing is run, so the contents written by dispatches are not
+    /// recorded.
+    pub fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        self.commands.push(Command::UseStorageVertexBuffer {
+            vertex_buffer: vertex_buffer.id,
+            location,
+        });
+    }
+
+    pub fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        self.commands.push(Command::UseStorageBuffer {
+            storage_buffer: storage_buffer.id,
+            location,
+            data: storage_buffer.buffer.0.borrow().clone(),
+        });
+    }
+
+    pub fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        self.commands.push(Command::UseStorageTexture {
+            texture: texture.id,
+            location,
+        });
+    }
+
+    pub fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        self.commands.push(Command::Draw {
+            renderable: renderable.id,
+            vertex_buffers: renderable
+                .vertex_buffers
+                .iter()
+                .map(|(i, buffer)| (*i, buffer.0.borrow().clone()))
+                .collect(),
+            vertex_count,
+            instance_count,
+        });
+    }
+
+    /// Records nothing if the renderable was created without an index
+    /// buffer, as the other backends don't draw anything in that case.
+    pub fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        if let Some((index_buffer, indices)) = &renderable.index_buffer {
+            self.commands.push(Command::DrawIndexed {
+                renderable: renderable.id,
+                vertex_buffers: renderable
+                    .vertex_buffers
+                    .iter()
+                    .map(|(i, buffer)| (*i, buffer.0.borrow().clone()))
+                    .collect(),
+                index_buffer: *index_buffer,
+                indices: indices.as_ref().clone(),
+                index_count,
+                instance_count,
+                index_offset,
+                base_vertex,
+            });
+        }
+    }
+
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        self.commands.push(Command::Dispatch { x, y, z });
+    }
+
+    pub fn memory_barrier(&mut self) {
+        self.commands.push(Command::MemoryBarrier);
+    }
+}
+
+impl Frame for CommandBuffer {
+    type Device = Context;
+    type TextureLocation = u32;
+
+    #[allow(deprecated)]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        CommandBuffer::set_clear_color(self, clear_color)
+    }
+
+    fn begin_pass(&mut self, desc: &PassDesc) {
+        CommandBuffer::begin_pass(self, desc)
+    }
+
+    fn end_pass(&mut self) {
+        CommandBuffer::end_pass(self)
+    }
+
+    fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        CommandBuffer::use_pipeline(self, pipeline)
+    }
+
+    fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        CommandBuffer::use_compute_pipeline(self, compute_pipeline)
+    }
+
+    fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        CommandBuffer::use_uniform(self, uniform_buffer, location, binding)
+    }
+
+    fn use_texture(&mut self, texture: &Texture, location: u32, binding: u32) {
+        CommandBuffer::use_texture(self, texture, location, binding)
+    }
+
+    fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        CommandBuffer::use_storage_vertex_buffer(self, vertex_buffer, location)
+    }
+
+    fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        CommandBuffer::use_storage_buffer(self, storage_buffer, location)
+    }
+
+    fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        CommandBuffer::use_storage_texture(self, texture, location)
+    }
+
+    fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        CommandBuffer::draw(self, renderable, vertex_count, instance_count)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        CommandBuffer::draw_indexed(
+            self,
+            renderable,
+            index_count,
+            instance_count,
+            index_offset,
+            base_vertex,
+        )
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        CommandBuffer::dispatch(self, x, y, z)
+    }
+
+    fn memory_barrier(&mut self) {
+        CommandBuffer::memory_barrier(self)
+    }
+
+    fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        CommandBuffer::set_render_target(self, render_target)
+    }
+
+    fn set_viewport(&mut self, viewport: Option<Rect>) {
+        CommandBuffer::set_viewport(self, viewport)
+    }
+
+    fn set_scissor(&mut self, scissor: Option<Rect>) {
+        CommandBuffer::set_scissor(self, scissor)
+    }
+}
+
+impl Drop for CommandBuffer {
+    fn drop(&mut self) {
+        let commands = std::mem::take(&mut self.commands);
+        self.frames.borrow_mut().push(commands);
+    }
+}
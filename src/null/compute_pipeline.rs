@@ -0,0 +1,33 @@
+use crate::null::err::Result;
+
+pub struct ComputePipeline {
+    pub(crate) id: u32,
+    pub(crate) function_name: String,
+    pub(crate) workgroup_size: [u32; 3],
+}
+
+impl ComputePipeline {
+    pub(crate) fn new(
+        id: u32,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        Ok(ComputePipeline {
+            id,
+            function_name: function_name.into(),
+            workgroup_size,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+
+    pub fn workgroup_size(&self) -> [u32; 3] {
+        self.workgroup_size
+    }
+}
@@ -0,0 +1,629 @@
+use crate::null::err::Result;
+use crate::null::*;
+use crate::shared::image::{decode_image, image_to_pixels, join_layers};
+use crate::shared::{Device, PipelineDesc};
+use crate::{Error, Index, SamplerDesc, TextureFormat, TextureKind, TextureRegion};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A backend that creates no GPU objects and records the commands of every
+/// frame instead of executing them, so the way a scene is submitted can be
+/// inspected in tests without a window or a GPU.
+pub struct Context {
+    pub(crate) next_id: u32,
+    pub(crate) frames: Rc<RefCell<Vec<Vec<Command>>>>,
+}
+
+impl Context {
+    pub fn new() -> Result<Context> {
+        Ok(Context {
+            next_id: 0,
+            frames: Rc::new(RefCell::new(Vec::new())),
+        })
+    }
+
+    /// This is a NULL ONLY API, it returns the commands of every frame that
+    /// has been submitted (dropped) since the last call, oldest first.
+    pub fn take_frames(&mut self) -> Vec<Vec<Command>> {
+        self.frames.replace(Vec::new())
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        id
+    }
+
+    pub fn next_frame(&mut self) -> Result<CommandBuffer> {
+        CommandBuffer::new(&self.frames)
+    }
+
+    pub fn create_library(&mut self, library_path: &str) -> Result<Library> {
+        Library::new(library_path)
+    }
+
+    pub fn create_shader(
+        &mut self,
+        _library: &Library,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) -> Result<Shader> {
+        Shader::new(vertex_name, fragment_name)
+    }
+
+    pub fn create_pipeline(&mut self, shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        Pipeline::new(self.next_id(), shader, desc)
+    }
+
+    pub fn create_compute_pipeline(
+        &mut self,
+        _library: &Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        ComputePipeline::new(self.next_id(), function_name, workgroup_size)
+    }
+
+    pub fn create_texture(&mut self, image_path: &str) -> Result<Texture> {
+        Texture::new(self.next_id(), image_path, &SamplerDesc::default())
+    }
+
+    pub fn create_texture_with_sampler(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::new(self.next_id(), image_path, sampler)
+    }
+
+    pub fn create_texture_from_image(
+        &mut self,
+        image: &DynamicImage,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            self.next_id(),
+            TextureKind::D2,
+            image.width(),
+            image.height(),
+            format,
+            &image_to_pixels(image, format)?,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            self.next_id(),
+            TextureKind::D2,
+            width,
+            height,
+            format,
+            data,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_from_memory(
+        &mut self,
+        bytes: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        let img = decode_image(bytes)?;
+        self.create_texture_from_image(&img, TextureFormat::Rgba8, sampler)
+    }
+
+    pub fn create_texture_from_mip_levels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_mip_levels(
+            self.next_id(),
+            TextureKind::D2,
+            width,
+            height,
+            format,
+            levels,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_array_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        let kind = TextureKind::D2Array {
+            layers: layers.len() as u32,
+        };
+        Texture::with_pixels(
+            self.next_id(),
+            kind,
+            width,
+            height,
+            format,
+            &join_layers(width, height, format, layers)?,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_3d_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            self.next_id(),
+            TextureKind::D3 { depth },
+            width,
+            height,
+            format,
+            data,
+            sampler,
+        )
+    }
+
+    pub fn create_cube_texture(
+        &mut self,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_image_paths(self.next_id(), TextureKind::Cube, image_paths, sampler)
+    }
+
+    pub fn create_cube_texture_from_cross(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_image_paths(self.next_id(), TextureKind::Cube, &[image_path], sampler)
+    }
+
+    pub fn create_cube_texture_from_pixels(
+        &mut self,
+        size: u32,
+        format: TextureFormat,
+        faces: &[&[u8]; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            self.next_id(),
+            TextureKind::Cube,
+            size,
+            size,
+            format,
+            &join_layers(size, size, format, faces)?,
+            sampler,
+        )
+    }
+
+    pub fn update_texture(
+        &mut self,
+        texture: &mut Texture,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        texture.update(region, mip_level, data)
+    }
+
+    pub fn create_uniform_buffer_with_capacity(
+        &mut self,
+        capacity: usize,
+    ) -> Result<UniformBuffer> {
+        UniformBuffer::with_capacity(self.next_id(), capacity)
+    }
+
+    pub fn create_uniform_buffer_with_value<T>(&mut self, value: &T) -> Result<UniformBuffer>
+    where
+        T: Sized,
+    {
+        UniformBuffer::with_data(self.next_id(), value)
+    }
+
+    pub fn update_uniform_buffer<T>(
+        &mut self,
+        uniform_buffer: &mut UniformBuffer,
+        data: &T,
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        uniform_buffer.update(data)
+    }
+
+    pub fn create_vertex_buffer_with_capacity(&mut self, capacity: usize) -> Result<VertexBuffer> {
+        VertexBuffer::with_capacity(self.next_id(), capacity)
+    }
+
+    pub fn create_vertex_buffer_with_data<T>(&mut self, data: &[T]) -> Result<VertexBuffer>
+    where
+        T: Sized,
+    {
+        VertexBuffer::with_data(self.next_id(), data)
+    }
+
+    pub fn update_vertex_buffer<T>(
+        &mut self,
+        vertex_buffer: &mut VertexBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        vertex_buffer.update(data)
+    }
+
+    pub fn create_renderable(
+        &mut self,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+    ) -> Result<Renderable> {
+        Renderable::new(self.next_id(), vertex_buffers)
+    }
+
+    pub fn create_storage_buffer_with_capacity(
+        &mut self,
+        capacity: usize,
+    ) -> Result<StorageBuffer> {
+        StorageBuffer::with_capacity(self.next_id(), capacity)
+    }
+
+    pub fn create_storage_buffer_with_data<T>(&mut self, data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        StorageBuffer::with_data(self.next_id(), data)
+    }
+
+    pub fn update_storage_buffer<T>(
+        &mut self,
+        storage_buffer: &mut StorageBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        storage_buffer.update(data)
+    }
+
+    pub fn read_storage_buffer(&mut self, storage_buffer: &StorageBuffer) -> Result<Vec<u8>> {
+        storage_buffer.read()
+    }
+
+    pub fn create_index_buffer<T>(&mut self, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        IndexBuffer::with_data(self.next_id(), indices)
+    }
+
+    pub fn create_renderable_with_index(
+        &mut self,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        Renderable::with_index(self.next_id(), vertex_buffers, index_buffer)
+    }
+
+    pub fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        let id = self.next_id();
+        RenderTarget::new(
+            id,
+            width,
+            height,
+            color_formats,
+            depth_format,
+            sample_count,
+            || self.next_id(),
+        )
+    }
+
+    /// Nothing is rendered, so there are no pixels to read back.
+    pub fn read_pixels(&mut self) -> Result<RgbaImage> {
+        Err(Error::Unsupported(
+            "reading pixels on the null backend".into(),
+        ))
+    }
+
+    pub fn read_texture(&mut self, texture: &Texture) -> Result<RgbaImage> {
+        texture.read_to_image()
+    }
+}
+
+impl Device for Context {
+    type Frame = CommandBuffer;
+    type Library = Library;
+    type Shader = Shader;
+    type Pipeline = Pipeline;
+    type ComputePipeline = ComputePipeline;
+    type Texture = Texture;
+    type UniformBuffer = UniformBuffer;
+    type VertexBuffer = VertexBuffer;
+    type StorageBuffer = StorageBuffer;
+    type IndexBuffer = IndexBuffer;
+    type Renderable = Renderable;
+    type RenderTarget = RenderTarget;
+
+    fn next_frame(&mut self) -> Result<CommandBuffer> {
+        Context::next_frame(self)
+    }
+
+    fn create_library(&mut self, library_path: &str) -> Result<Library> {
+        Context::create_library(self, library_path)
+    }
+
+    fn create_shader(
+        &mut self,
+        library: &Library,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) -> Result<Shader> {
+        Context::create_shader(self, library, vertex_name, fragment_name)
+    }
+
+    fn create_pipeline(&mut self, shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        Context::create_pipeline(self, shader, desc)
+    }
+
+    fn create_compute_pipeline(
+        &mut self,
+        library: &Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        Context::create_compute_pipeline(self, library, function_name, workgroup_size)
+    }
+
+    fn create_texture(&mut self, image_path: &str) -> Result<Texture> {
+        Context::create_texture(self, image_path)
+    }
+
+    fn create_texture_with_sampler(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_with_sampler(self, image_path, sampler)
+    }
+
+    fn create_texture_from_image(
+        &mut self,
+        image: &DynamicImage,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_image(self, image, format, sampler)
+    }
+
+    fn create_texture_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_pixels(self, width, height, format, data, sampler)
+    }
+
+    fn create_texture_from_memory(
+        &mut self,
+        bytes: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_memory(self, bytes, sampler)
+    }
+
+    fn create_texture_from_mip_levels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_mip_levels(self, width, height, format, levels, sampler)
+    }
+
+    fn create_texture_array_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_array_from_pixels(self, width, height, format, layers, sampler)
+    }
+
+    fn create_texture_3d_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_3d_from_pixels(self, width, height, depth, format, data, sampler)
+    }
+
+    fn create_cube_texture(
+        &mut self,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture(self, image_paths, sampler)
+    }
+
+    fn create_cube_texture_from_cross(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture_from_cross(self, image_path, sampler)
+    }
+
+    fn create_cube_texture_from_pixels(
+        &mut self,
+        size: u32,
+        format: TextureFormat,
+        faces: &[&[u8]; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture_from_pixels(self, size, format, faces, sampler)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Texture,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        Context::update_texture(self, texture, region, mip_level, data)
+    }
+
+    fn create_uniform_buffer_with_capacity(&mut self, capacity: usize) -> Result<UniformBuffer> {
+        Context::create_uniform_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_uniform_buffer_with_value<T>(&mut self, value: &T) -> Result<UniformBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_uniform_buffer_with_value(self, value)
+    }
+
+    fn update_uniform_buffer<T>(
+        &mut self,
+        uniform_buffer: &mut UniformBuffer,
+        data: &T,
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_uniform_buffer(self, uniform_buffer, data)
+    }
+
+    fn create_vertex_buffer_with_capacity(&mut self, capacity: usize) -> Result<VertexBuffer> {
+        Context::create_vertex_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_vertex_buffer_with_data<T>(&mut self, data: &[T]) -> Result<VertexBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_vertex_buffer_with_data(self, data)
+    }
+
+    fn update_vertex_buffer<T>(
+        &mut self,
+        vertex_buffer: &mut VertexBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_vertex_buffer(self, vertex_buffer, data)
+    }
+
+    fn create_renderable(&mut self, vertex_buffers: &[(u32, &VertexBuffer)]) -> Result<Renderable> {
+        Context::create_renderable(self, vertex_buffers)
+    }
+
+    fn create_storage_buffer_with_capacity(&mut self, capacity: usize) -> Result<StorageBuffer> {
+        Context::create_storage_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_storage_buffer_with_data<T>(&mut self, data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_storage_buffer_with_data(self, data)
+    }
+
+    fn update_storage_buffer<T>(
+        &mut self,
+        storage_buffer: &mut StorageBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_storage_buffer(self, storage_buffer, data)
+    }
+
+    fn read_storage_buffer(&mut self, storage_buffer: &StorageBuffer) -> Result<Vec<u8>> {
+        Context::read_storage_buffer(self, storage_buffer)
+    }
+
+    fn create_index_buffer<T>(&mut self, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        Context::create_index_buffer(self, indices)
+    }
+
+    fn create_renderable_with_index(
+        &mut self,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        Context::create_renderable_with_index(self, vertex_buffers, index_buffer)
+    }
+
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        Context::create_render_target(
+            self,
+            width,
+            height,
+            color_formats,
+            depth_format,
+            sample_count,
+        )
+    }
+
+    fn read_pixels(&mut self) -> Result<RgbaImage> {
+        Context::read_pixels(self)
+    }
+
+    fn read_texture(&mut self, texture: &Texture) -> Result<RgbaImage> {
+        Context::read_texture(self, texture)
+    }
+}
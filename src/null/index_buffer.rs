@@ -0,0 +1,24 @@
+use crate::null::err::Result;
+use crate::Index;
+use std::rc::Rc;
+
+pub struct IndexBuffer {
+    pub(crate) id: u32,
+    pub(crate) indices: Rc<Vec<u32>>,
+}
+
+impl IndexBuffer {
+    pub(crate) fn with_data<T>(id: u32, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        Ok(IndexBuffer {
+            id,
+            indices: Rc::new(indices.iter().map(|index| (*index).into()).collect()),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
@@ -0,0 +1,3 @@
+mod buffer;
+
+pub(crate) use buffer::*;
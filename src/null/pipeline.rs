@@ -0,0 +1,106 @@
+use crate::null::err::Result;
+use crate::null::Shader;
+use crate::shared::{
+    Binding, BlendState, ColorAttachmentState, ColorWriteMask, CompareFunction, PipelineDesc,
+    PrimitiveTopology, RasterizerState, StencilState,
+};
+
+pub struct Pipeline {
+    pub(crate) id: u32,
+    pub(crate) vertex_name: String,
+    pub(crate) fragment_name: String,
+    pub(crate) topology: PrimitiveTopology,
+    pub(crate) sample_count: u32,
+    pub(crate) depth_compare: Option<CompareFunction>,
+    pub(crate) depth_write: bool,
+    pub(crate) stencil: Option<StencilState>,
+    pub(crate) blend: Option<BlendState>,
+    pub(crate) blend_constant: [f32; 4],
+    pub(crate) alpha_to_coverage: bool,
+    pub(crate) color_write_mask: ColorWriteMask,
+    pub(crate) color_attachments: Vec<ColorAttachmentState>,
+    pub(crate) rasterizer: RasterizerState,
+    pub(crate) bindings: Vec<Binding>,
+}
+
+impl Pipeline {
+    pub(crate) fn new(id: u32, shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        Ok(Pipeline {
+            id,
+            vertex_name: shader.vertex_name.clone(),
+            fragment_name: shader.fragment_name.clone(),
+            topology: desc.topology,
+            sample_count: desc.sample_count,
+            depth_compare: desc.depth_compare,
+            depth_write: desc.depth_write,
+            stencil: desc.stencil,
+            blend: desc.blend,
+            blend_constant: desc.blend_constant,
+            alpha_to_coverage: desc.alpha_to_coverage,
+            color_write_mask: desc.color_write_mask,
+            color_attachments: Vec::from(desc.color_attachments),
+            rasterizer: desc.rasterizer,
+            bindings: Vec::from(desc.bindings),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn vertex_name(&self) -> &str {
+        &self.vertex_name
+    }
+
+    pub fn fragment_name(&self) -> &str {
+        &self.fragment_name
+    }
+
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn depth_compare(&self) -> Option<CompareFunction> {
+        self.depth_compare
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.depth_write
+    }
+
+    pub fn stencil(&self) -> Option<&StencilState> {
+        self.stencil.as_ref()
+    }
+
+    pub fn blend(&self) -> Option<&BlendState> {
+        self.blend.as_ref()
+    }
+
+    pub fn blend_constant(&self) -> [f32; 4] {
+        self.blend_constant
+    }
+
+    pub fn alpha_to_coverage(&self) -> bool {
+        self.alpha_to_coverage
+    }
+
+    pub fn color_write_mask(&self) -> ColorWriteMask {
+        self.color_write_mask
+    }
+
+    pub fn color_attachments(&self) -> &[ColorAttachmentState] {
+        &self.color_attachments
+    }
+
+    pub fn rasterizer(&self) -> &RasterizerState {
+        &self.rasterizer
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+}
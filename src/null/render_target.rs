@@ -0,0 +1,85 @@
+use crate::null::err::Result;
+use crate::null::Texture;
+use crate::shared::image::check_sample_count;
+use crate::{Attachments, TextureFormat};
+
+pub struct RenderTarget {
+    pub(crate) id: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) sample_count: u32,
+    pub(crate) color_textures: Vec<Texture>,
+    pub(crate) depth_texture: Option<Texture>,
+}
+
+impl RenderTarget {
+    /// `texture_ids` hands out the ids of the attachments.
+    pub(crate) fn new<F>(
+        id: u32,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+        mut texture_ids: F,
+    ) -> Result<RenderTarget>
+    where
+        F: FnMut() -> u32,
+    {
+        check_sample_count(sample_count)?;
+
+        let color_textures = color_formats
+            .iter()
+            .map(|format| Texture::with_format(texture_ids(), *format))
+            .collect::<Result<Vec<Texture>>>()?;
+        let depth_texture = match depth_format {
+            Some(format) => Some(Texture::with_format(texture_ids(), format)?),
+            None => None,
+        };
+
+        Ok(RenderTarget {
+            id,
+            width,
+            height,
+            sample_count,
+            color_textures,
+            depth_texture,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.color_textures
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+}
+
+impl Attachments for RenderTarget {
+    type Texture = Texture;
+
+    fn color_textures(&self) -> &[Texture] {
+        RenderTarget::color_textures(self)
+    }
+
+    fn depth_texture(&self) -> Option<&Texture> {
+        RenderTarget::depth_texture(self)
+    }
+}
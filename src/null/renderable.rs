@@ -0,0 +1,37 @@
+use crate::null::err::Result;
+use crate::null::internal::Buffer;
+use crate::null::{IndexBuffer, VertexBuffer};
+use std::rc::Rc;
+
+pub struct Renderable {
+    pub(crate) id: u32,
+    pub(crate) vertex_buffers: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) index_buffer: Option<(u32, Rc<Vec<u32>>)>,
+}
+
+impl Renderable {
+    pub(crate) fn new(id: u32, vertex_buffers: &[(u32, &VertexBuffer)]) -> Result<Renderable> {
+        Ok(Renderable {
+            id,
+            vertex_buffers: vertex_buffers
+                .iter()
+                .map(|(i, vertex_buffer)| (*i, vertex_buffer.buffer.clone()))
+                .collect(),
+            index_buffer: None,
+        })
+    }
+
+    pub(crate) fn with_index(
+        id: u32,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        let mut renderable = Renderable::new(id, vertex_buffers)?;
+        renderable.index_buffer = Some((index_buffer.id, Rc::clone(&index_buffer.indices)));
+        Ok(renderable)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
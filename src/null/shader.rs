@@ -0,0 +1,31 @@
+use crate::null::err::Result;
+
+pub struct Library {
+    pub(crate) library_path: String,
+}
+
+impl Library {
+    pub(crate) fn new(library_path: &str) -> Result<Library> {
+        Ok(Library {
+            library_path: library_path.into(),
+        })
+    }
+
+    pub fn library_path(&self) -> &str {
+        &self.library_path
+    }
+}
+
+pub struct Shader {
+    pub(crate) vertex_name: String,
+    pub(crate) fragment_name: String,
+}
+
+impl Shader {
+    pub(crate) fn new(vertex_name: &str, fragment_name: &str) -> Result<Shader> {
+        Ok(Shader {
+            vertex_name: vertex_name.into(),
+            fragment_name: fragment_name.into(),
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use crate::null::*;
+use crate::{Operations, PassDesc, PipelineDesc, SamplerDesc, TextureFormat};
+
+fn bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect()
+}
+
+// `set_clear_color` is deprecated but still recorded.
+#[allow(deprecated)]
+#[test]
+fn records_a_frame() {
+    let mut context = Context::new().unwrap();
+    let library = context.create_library("shaders").unwrap();
+    let shader = context
+        .create_shader(&library, "vertex", "fragment")
+        .unwrap();
+    let pipeline = context
+        .create_pipeline(&shader, &PipelineDesc::default())
+        .unwrap();
+    let mut uniform_buffer = context
+        .create_uniform_buffer_with_value(&[1.0f32, 2.0])
+        .unwrap();
+    let texture = context
+        .create_texture_from_pixels(
+            1,
+            1,
+            TextureFormat::Rgba8,
+            &[255; 4],
+            &SamplerDesc::default(),
+        )
+        .unwrap();
+    let vertex_buffer = context
+        .create_vertex_buffer_with_data(&[0.5f32, -0.5])
+        .unwrap();
+    let renderable = context.create_renderable(&[(0, &vertex_buffer)]).unwrap();
+
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.set_clear_color(Some((0.0, 0.0, 1.0, 1.0)));
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear([0.0, 0.0, 1.0, 1.0])],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.use_uniform(&uniform_buffer, 0, 1);
+        frame.use_texture(&texture, 2, 3);
+        frame.draw(&renderable, 3, 2);
+        frame.end_pass();
+    }
+    // The recorded frame keeps the contents the buffer had when it was
+    // used.
+    context
+        .update_uniform_buffer(&mut uniform_buffer, &[3.0f32, 4.0])
+        .unwrap();
+
+    assert_eq!(
+        context.take_frames(),
+        vec![vec![
+            Command::SetClearColor(Some((0.0, 0.0, 1.0, 1.0))),
+            Command::BeginPass {
+                color: vec![Operations::clear([0.0, 0.0, 1.0, 1.0])],
+                depth: Operations::default(),
+                stencil: Operations::default(),
+            },
+            Command::UsePipeline {
+                pipeline: pipeline.id(),
+            },
+            Command::UseUniform {
+                uniform_buffer: uniform_buffer.id(),
+                location: 0,
+                binding: 1,
+                data: bytes(&[1.0, 2.0]),
+            },
+            Command::UseTexture {
+                texture: texture.id(),
+                location: 2,
+                binding: 3,
+            },
+            Command::Draw {
+                renderable: renderable.id(),
+                vertex_buffers: vec![(0, bytes(&[0.5, -0.5]))],
+                vertex_count: 3,
+                instance_count: 2,
+            },
+            Command::EndPass,
+        ]]
+    );
+    assert!(context.take_frames().is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+fn frames_are_recorded_when_dropped() {
+    let mut context = Context::new().unwrap();
+    let first = context.next_frame().unwrap();
+    {
+        let mut second = context.next_frame().unwrap();
+        second.set_clear_color(None);
+        assert_eq!(second.commands(), &[Command::SetClearColor(None)]);
+    }
+    drop(first);
+
+    assert_eq!(
+        context.take_frames(),
+        vec![vec![Command::SetClearColor(None)], Vec::new()]
+    );
+}
@@ -0,0 +1,132 @@
+use crate::null::err::Result;
+use crate::shared::image::{check_mip_levels, check_pixel_data, check_update_kind};
+use crate::{Error, SamplerDesc, TextureFormat, TextureKind, TextureRegion};
+use image::RgbaImage;
+
+/// A texture that only remembers where it would have been loaded from, the
+/// image file is never opened.
+pub struct Texture {
+    pub(crate) id: u32,
+    pub(crate) image_paths: Vec<String>,
+    pub(crate) kind: TextureKind,
+    pub(crate) format: TextureFormat,
+    pub(crate) sampler: SamplerDesc,
+}
+
+impl Texture {
+    pub(crate) fn new(id: u32, image_path: &str, sampler: &SamplerDesc) -> Result<Texture> {
+        Texture::with_image_paths(id, TextureKind::D2, &[image_path], sampler)
+    }
+
+    /// A `Rgba8` texture loaded from one image file, or from one file per
+    /// cube face.
+    pub(crate) fn with_image_paths(
+        id: u32,
+        kind: TextureKind,
+        image_paths: &[&str],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Ok(Texture {
+            id,
+            image_paths: image_paths.iter().map(|path| path.to_string()).collect(),
+            kind,
+            format: TextureFormat::Rgba8,
+            sampler: *sampler,
+        })
+    }
+
+    /// Checks the size of the pixel data, which is then dropped.
+    pub(crate) fn with_pixels(
+        id: u32,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_mip_levels(id, kind, width, height, format, &[data], sampler)
+    }
+
+    /// Checks the size of every level, which are then dropped.
+    pub(crate) fn with_mip_levels(
+        id: u32,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, levels)?;
+
+        Ok(Texture {
+            id,
+            image_paths: Vec::new(),
+            kind,
+            format,
+            sampler: *sampler,
+        })
+    }
+
+    pub(crate) fn with_format(id: u32, format: TextureFormat) -> Result<Texture> {
+        Ok(Texture {
+            id,
+            image_paths: Vec::new(),
+            kind: TextureKind::D2,
+            format,
+            sampler: SamplerDesc::default(),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The image file the texture was created from, `None` for textures
+    /// created from memory, cube maps created from six files and the
+    /// attachments of a render target.
+    pub fn image_path(&self) -> Option<&str> {
+        match self.image_paths.as_slice() {
+            [image_path] => Some(image_path),
+            _ => None,
+        }
+    }
+
+    /// Every image file the texture was created from, one per face for cube
+    /// maps created from six files.
+    pub fn image_paths(&self) -> &[String] {
+        &self.image_paths
+    }
+
+    pub fn kind(&self) -> TextureKind {
+        self.kind
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn sampler(&self) -> &SamplerDesc {
+        &self.sampler
+    }
+
+    /// Null textures do not know their size, so only the length of the
+    /// data is checked.
+    pub(crate) fn update(
+        &mut self,
+        region: TextureRegion,
+        _mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        check_update_kind(self.kind)?;
+        check_pixel_data(region.width, region.height, self.format, data)
+    }
+
+    /// Null textures have no contents to read back.
+    pub fn read_to_image(&self) -> Result<RgbaImage> {
+        Err(Error::Unsupported(
+            "reading textures on the null backend".into(),
+        ))
+    }
+}
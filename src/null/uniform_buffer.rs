@@ -0,0 +1,60 @@
+use crate::null::err::Result;
+use crate::null::internal::{as_bytes, Buffer};
+use crate::Error;
+use std::mem::size_of;
+use std::rc::Rc;
+
+pub struct UniformBuffer {
+    pub(crate) id: u32,
+    pub(crate) buffer: Rc<Buffer>,
+    /// The number of bytes that can be stored in this buffer.
+    pub(crate) capacity: usize,
+}
+
+impl UniformBuffer {
+    pub(crate) fn with_capacity(id: u32, capacity: usize) -> Result<UniformBuffer> {
+        Ok(UniformBuffer {
+            id,
+            buffer: Rc::from(Buffer::new(vec![0; capacity])),
+            capacity,
+        })
+    }
+
+    pub(crate) fn with_data<T>(id: u32, data: &T) -> Result<UniformBuffer>
+    where
+        T: Sized,
+    {
+        let contents = as_bytes(std::slice::from_ref(data)).to_vec();
+
+        Ok(UniformBuffer {
+            id,
+            buffer: Rc::from(Buffer::new(contents)),
+            capacity: size_of::<T>(),
+        })
+    }
+
+    pub(crate) fn update<T>(&mut self, data: &T) -> Result<()>
+    where
+        T: Sized,
+    {
+        let length = size_of::<T>();
+        if length > self.capacity {
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
+        }
+
+        *self.buffer.0.borrow_mut() = as_bytes(std::slice::from_ref(data)).to_vec();
+
+        Ok(())
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
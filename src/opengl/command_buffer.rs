@@ -1,68 +1,233 @@
 use crate::opengl::err::Result;
 use crate::opengl::internal::*;
 use crate::opengl::*;
-use crate::Binding;
+use crate::{Binding, Frame, IndexFormat, LoadAction, Operations, PassDesc, Rect, TextureKind};
 use std::rc::Rc;
 
 pub struct CommandBuffer {
-    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
     pub(crate) pipeline_index: u32,
+    pub(crate) primitive_mode: u32,
     /// A reference to the shader program is needed to be able to set the
     /// uniform block binding.
     pub(crate) shader_program: Rc<Program>,
     pub(crate) bindings: Vec<Binding>,
+    /// The viewport of the default framebuffer, restored when drawing goes
+    /// back to the screen.
+    pub(crate) screen_viewport: [i32; 4],
+    /// The viewport covering the whole framebuffer that is drawn into.
+    pub(crate) target_viewport: [i32; 4],
+    pub(crate) color_attachment_count: usize,
+    pub(crate) scissor_test: bool,
+    /// The multisampled attachments drawn into and the framebuffer they are
+    /// resolved into when the pass ends.
+    pub(crate) resolve: Option<(Rc<Multisample>, Rc<Framebuffer>)>,
+    pub(crate) in_pass: bool,
+    /// See `set_clear_color`.
+    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
 }
 
 impl CommandBuffer {
     pub(crate) fn new() -> Result<CommandBuffer> {
+        let mut screen_viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, screen_viewport.as_mut_ptr());
+        }
+
         Ok(CommandBuffer {
-            clear_color: None,
             pipeline_index: 0,
+            primitive_mode: gl::TRIANGLE_STRIP,
             shader_program: Rc::from(Program(0)),
             bindings: Vec::new(),
+            screen_viewport,
+            target_viewport: screen_viewport,
+            color_attachment_count: 1,
+            scissor_test: false,
+            resolve: None,
+            in_pass: false,
+            clear_color: None,
         })
     }
 
+    pub fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        self.end_pass();
+
+        unsafe {
+            match render_target {
+                Some(render_target) => {
+                    match &render_target.multisample {
+                        Some(multisample) => {
+                            gl::BindFramebuffer(gl::FRAMEBUFFER, multisample.framebuffer.0);
+                            self.resolve = Some((
+                                Rc::clone(multisample),
+                                Rc::clone(&render_target.framebuffer),
+                            ));
+                        }
+                        None => {
+                            gl::BindFramebuffer(gl::FRAMEBUFFER, render_target.framebuffer.0);
+                            self.resolve = None;
+                        }
+                    }
+                    self.target_viewport = [
+                        0,
+                        0,
+                        render_target.width as i32,
+                        render_target.height as i32,
+                    ];
+                    self.color_attachment_count = render_target.color_textures.len();
+                }
+                None => {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    self.resolve = None;
+                    self.target_viewport = self.screen_viewport;
+                    self.color_attachment_count = 1;
+                }
+            }
+        }
+
+        self.set_viewport(None);
+        self.set_scissor(None);
+    }
+
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        let [x, y, width, height] = match viewport {
+            Some(viewport) => self.to_gl_rect(viewport),
+            None => self.target_viewport,
+        };
+        unsafe {
+            gl::Viewport(x, y, width, height);
+        }
+    }
+
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        unsafe {
+            match scissor {
+                Some(scissor) => {
+                    let [x, y, width, height] = self.to_gl_rect(scissor);
+                    gl::Scissor(x, y, width, height);
+                    gl::Enable(gl::SCISSOR_TEST);
+                }
+                None => gl::Disable(gl::SCISSOR_TEST),
+            }
+        }
+
+        self.scissor_test = scissor.is_some();
+    }
+
+    /// Converts a rectangle with the origin in the top left corner to the
+    /// bottom left origin of OpenGL.
+    fn to_gl_rect(&self, rect: Rect) -> [i32; 4] {
+        let [x, y, _width, height] = self.target_viewport;
+        [
+            x + rect.x as i32,
+            y + height - rect.y as i32 - rect.height as i32,
+            rect.width as i32,
+            rect.height as i32,
+        ]
+    }
+
+    /// Deprecated, see `Frame::set_clear_color`.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
     pub fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
         self.clear_color = clear_color;
     }
 
-    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
-        if let Some((red, green, blue, alpha)) = self.clear_color {
-            unsafe {
-                gl::ClearColor(red, green, blue, alpha);
-                gl::Clear(gl::COLOR_BUFFER_BIT);
+    /// Clears the attachments that the pass clears. OpenGL has no use for
+    /// the other actions.
+    pub fn begin_pass(&mut self, desc: &PassDesc) {
+        self.end_pass();
+
+        unsafe {
+            // The write masks of the previous pipeline apply to clears, and
+            // so does the scissor test.
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            for i in 0..self.color_attachment_count {
+                if let LoadAction::Clear(color) = desc.color_operations(i).load {
+                    gl::ClearBufferfv(gl::COLOR, i as i32, color.as_ptr());
+                }
+            }
+            if let LoadAction::Clear(depth) = desc.depth.load {
+                gl::DepthMask(gl::TRUE);
+                gl::ClearBufferfv(gl::DEPTH, 0, &depth);
+            }
+            if let LoadAction::Clear(stencil) = desc.stencil.load {
+                gl::StencilMask(0xff);
+                gl::ClearBufferiv(gl::STENCIL, 0, &(stencil as i32));
+            }
+            if self.scissor_test {
+                gl::Enable(gl::SCISSOR_TEST);
             }
         }
 
-        unsafe {
-            if pipeline.depth_test {
-                gl::Enable(gl::DEPTH_TEST);
-            } else {
-                gl::Disable(gl::DEPTH_TEST);
+        self.in_pass = true;
+    }
+
+    /// Resolves the attachments of a multisampled render target. OpenGL has
+    /// no passes, the attachments are always stored.
+    pub fn end_pass(&mut self) {
+        if !self.in_pass {
+            return;
+        }
+        self.in_pass = false;
+
+        if let Some((multisample, framebuffer)) = &self.resolve {
+            unsafe {
+                gl::Disable(gl::SCISSOR_TEST);
+                multisample.resolve(framebuffer);
+                if self.scissor_test {
+                    gl::Enable(gl::SCISSOR_TEST);
+                }
             }
+        }
+    }
 
-            gl::DepthMask(if pipeline.depth_write {
-                gl::TRUE
-            } else {
-                gl::FALSE
+    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        if let Some((red, green, blue, alpha)) = self.clear_color {
+            let color =
+                vec![Operations::clear([red, green, blue, alpha]); self.color_attachment_count];
+            self.begin_pass(&PassDesc {
+                color: &color,
+                ..Default::default()
             });
+        } else if !self.in_pass {
+            self.begin_pass(&PassDesc::default());
+        }
 
-            if pipeline.alpha_blend {
-                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-                gl::Enable(gl::BLEND);
-            } else {
-                gl::Disable(gl::BLEND);
-            }
+        unsafe {
+            set_depth_stencil_state(
+                pipeline.depth_compare,
+                pipeline.depth_write,
+                pipeline.stencil.as_ref(),
+            );
+            let attachments: Vec<_> = (0..self.color_attachment_count)
+                .map(|i| pipeline.color_attachment(i))
+                .collect();
+            set_blend_state(
+                &attachments,
+                pipeline.blend_constant,
+                pipeline.alpha_to_coverage,
+            );
+            set_rasterizer_state(&pipeline.rasterizer);
 
             gl::UseProgram(pipeline.shader_program.0);
         }
 
         self.pipeline_index = pipeline.index;
+        self.primitive_mode = pipeline.primitive_mode;
         self.shader_program = Rc::clone(&pipeline.shader_program);
         self.bindings = pipeline.bindings.clone();
     }
 
+    pub fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        self.end_pass();
+
+        unsafe {
+            gl::UseProgram(compute_pipeline.shader_program.0);
+        }
+
+        self.shader_program = Rc::clone(&compute_pipeline.shader_program);
+    }
+
     pub fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
         unsafe {
             gl::BindBufferBase(gl::UNIFORM_BUFFER, location, uniform_buffer.buffer.0);
@@ -70,15 +235,115 @@ impl CommandBuffer {
         }
     }
 
-    pub fn use_texture(&mut self, texture: &Texture, location: i32) {
+    pub fn use_texture(&mut self, texture: &Texture, location: i32, binding: u32) {
+        unsafe {
+            gl::Uniform1i(location, binding as i32);
+            gl::ActiveTexture(gl::TEXTURE0 + binding);
+            gl::BindTexture(texture_target(texture.kind), texture.texture);
+        }
+    }
+
+    /// Binds to the shader storage block binding `location`.
+    pub fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, location, vertex_buffer.buffer.0);
+        }
+    }
+
+    /// Binds to the shader storage block binding `location`.
+    pub fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, location, storage_buffer.buffer.0);
+        }
+    }
+
+    /// Binds to the image unit `location`, every layer of arrays, 3D
+    /// textures and cube maps can be accessed.
+    ///
+    /// Panics before OpenGL 4.2, which has no image units. Compute
+    /// pipelines, which need 4.3, cannot be created there either.
+    pub fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        assert!(
+            gl::BindImageTexture::is_loaded(),
+            "binding storage texture: image units need OpenGL 4.2"
+        );
+
         unsafe {
-            gl::Uniform1i(location, 0);
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, texture.texture);
+            gl::BindImageTexture(
+                location,
+                texture.texture,
+                0,
+                if texture.kind == TextureKind::D2 {
+                    gl::FALSE
+                } else {
+                    gl::TRUE
+                },
+                0,
+                gl::READ_WRITE,
+                tex_image_format(texture.format).internal_format as u32,
+            );
         }
     }
 
     pub fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        self.bind_vertex_array(renderable);
+
+        unsafe {
+            gl::DrawArraysInstanced(
+                self.primitive_mode,
+                0,
+                vertex_count as i32,
+                instance_count as i32,
+            );
+        }
+    }
+
+    pub fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        let format = match &renderable.index_buffer {
+            Some((_buffer, format)) => *format,
+            None => return,
+        };
+
+        self.bind_vertex_array(renderable);
+
+        unsafe {
+            gl::DrawElementsInstancedBaseVertex(
+                self.primitive_mode,
+                index_count as i32,
+                match format {
+                    IndexFormat::UInt16 => gl::UNSIGNED_SHORT,
+                    IndexFormat::UInt32 => gl::UNSIGNED_INT,
+                },
+                (index_offset * format.size()) as *const _,
+                instance_count as i32,
+                base_vertex,
+            );
+        }
+    }
+
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /// Waits for every kind of access to the memory written by shaders.
+    pub fn memory_barrier(&mut self) {
+        unsafe {
+            gl::MemoryBarrier(gl::ALL_BARRIER_BITS);
+        }
+    }
+
+    /// Binds the vertex array object of the renderable for the current
+    /// pipeline, creating it on first use.
+    fn bind_vertex_array(&self, renderable: &Renderable) {
         let mut vertex_arrays = renderable.vertex_arrays.take();
         match vertex_arrays
             .iter()
@@ -100,45 +365,140 @@ impl CommandBuffer {
                         continue;
                     }
 
-                    gl::VertexAttribPointer(
-                        binding.attribute,
-                        4,
-                        gl::FLOAT,
-                        gl::FALSE,
-                        binding.stride as i32,
-                        binding.offset as usize as *const _,
-                    );
-                    gl::VertexAttribDivisor(
-                        binding.attribute,
-                        if binding.instanced { 1 } else { 0 },
-                    );
+                    let format = vertex_attrib_format(binding.format);
+                    if format.integer {
+                        gl::VertexAttribIPointer(
+                            binding.attribute,
+                            format.size,
+                            format.data_type,
+                            binding.stride as i32,
+                            binding.offset as *const _,
+                        );
+                    } else {
+                        gl::VertexAttribPointer(
+                            binding.attribute,
+                            format.size,
+                            format.data_type,
+                            if format.normalized {
+                                gl::TRUE
+                            } else {
+                                gl::FALSE
+                            },
+                            binding.stride as i32,
+                            binding.offset as *const _,
+                        );
+                    }
+                    gl::VertexAttribDivisor(binding.attribute, binding.instance_step_rate);
                     gl::EnableVertexAttribArray(binding.attribute);
                 }
 
+                if let Some((buffer, _format)) = &renderable.index_buffer {
+                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer.0);
+                }
+
                 vertex_arrays.push((self.pipeline_index, VertexArray(vertex_array)));
             },
             Some((_pipeline_index, vertex_array)) => unsafe { gl::BindVertexArray(vertex_array.0) },
         }
 
         renderable.vertex_arrays.set(vertex_arrays);
+    }
+}
 
-        unsafe {
-            gl::DrawArraysInstanced(
-                gl::TRIANGLE_STRIP,
-                0,
-                vertex_count as i32,
-                instance_count as i32,
-            );
-        }
+impl Frame for CommandBuffer {
+    type Device = Context;
+    type TextureLocation = i32;
+
+    #[allow(deprecated)]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        CommandBuffer::set_clear_color(self, clear_color)
+    }
+
+    fn begin_pass(&mut self, desc: &PassDesc) {
+        CommandBuffer::begin_pass(self, desc)
+    }
+
+    fn end_pass(&mut self) {
+        CommandBuffer::end_pass(self)
     }
 
-    // pub fn draw_indexed(&self, renderable: &Renderable, index_count: usize, instance_count: usize) {
+    fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        CommandBuffer::use_pipeline(self, pipeline)
+    }
+
+    fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        CommandBuffer::use_compute_pipeline(self, compute_pipeline)
+    }
+
+    fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        CommandBuffer::use_uniform(self, uniform_buffer, location, binding)
+    }
+
+    fn use_texture(&mut self, texture: &Texture, location: i32, binding: u32) {
+        CommandBuffer::use_texture(self, texture, location, binding)
+    }
+
+    fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        CommandBuffer::use_storage_vertex_buffer(self, vertex_buffer, location)
+    }
 
-    // }
+    fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        CommandBuffer::use_storage_buffer(self, storage_buffer, location)
+    }
+
+    fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        CommandBuffer::use_storage_texture(self, texture, location)
+    }
+
+    fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        CommandBuffer::draw(self, renderable, vertex_count, instance_count)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        CommandBuffer::draw_indexed(
+            self,
+            renderable,
+            index_count,
+            instance_count,
+            index_offset,
+            base_vertex,
+        )
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        CommandBuffer::dispatch(self, x, y, z)
+    }
+
+    fn memory_barrier(&mut self) {
+        CommandBuffer::memory_barrier(self)
+    }
+
+    fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        CommandBuffer::set_render_target(self, render_target)
+    }
+
+    fn set_viewport(&mut self, viewport: Option<Rect>) {
+        CommandBuffer::set_viewport(self, viewport)
+    }
+
+    fn set_scissor(&mut self, scissor: Option<Rect>) {
+        CommandBuffer::set_scissor(self, scissor)
+    }
 }
 
 impl Drop for CommandBuffer {
     fn drop(&mut self) {
+        // The next frame reads the viewport of the screen back from the
+        // context.
+        self.set_render_target(None);
+
         unsafe {
             gl::Finish();
         }
@@ -0,0 +1,45 @@
+use crate::opengl::err::Result;
+use crate::opengl::internal::Program;
+use crate::opengl::shader::{compile_shader, link_program};
+use crate::{Error, ShaderStage};
+use gl;
+use std::rc::Rc;
+
+pub struct ComputePipeline {
+    pub(crate) shader_program: Rc<Program>,
+}
+
+impl ComputePipeline {
+    pub(crate) fn new(compute_source: &str, workgroup_size: [u32; 3]) -> Result<ComputePipeline> {
+        if !gl::DispatchCompute::is_loaded() {
+            return Err(Error::Unsupported(
+                "compute pipelines before OpenGL 4.3".into(),
+            ));
+        }
+
+        let compute_shader = compile_shader(ShaderStage::Compute, compute_source)?;
+        let program = link_program(&[compute_shader])?;
+
+        // The workgroup size is declared by the shader, it can only be
+        // checked against the one the other backends are given.
+        let mut declared = [0; 3];
+        unsafe {
+            gl::GetProgramiv(
+                program.0,
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                declared.as_mut_ptr(),
+            );
+        }
+        let declared = [declared[0] as u32, declared[1] as u32, declared[2] as u32];
+        if declared != workgroup_size {
+            return Err(Error::WorkgroupSize {
+                declared,
+                expected: workgroup_size,
+            });
+        }
+
+        Ok(ComputePipeline {
+            shader_program: Rc::from(program),
+        })
+    }
+}
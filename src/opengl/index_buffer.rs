@@ -0,0 +1,38 @@
+use crate::opengl::err::Result;
+use crate::opengl::internal::Buffer;
+use crate::{Index, IndexFormat};
+use gl;
+use std::mem::size_of_val;
+use std::rc::Rc;
+
+pub struct IndexBuffer {
+    pub(crate) buffer: Rc<Buffer>,
+    pub(crate) format: IndexFormat,
+}
+
+impl IndexBuffer {
+    pub(crate) fn with_data<T>(indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        unsafe {
+            let mut buffer = 0;
+            gl::GenBuffers(1, &mut buffer);
+            // Binding to ELEMENT_ARRAY_BUFFER would change the index buffer of
+            // whichever vertex array object is currently bound.
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                size_of_val(indices) as isize,
+                indices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            Ok(IndexBuffer {
+                buffer: Rc::from(Buffer(buffer)),
+                format: T::FORMAT,
+            })
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{BlendFactor, BlendOperation, ColorAttachmentState};
+use gl;
+
+/// Sets the blend equations, constant and write masks of the color
+/// attachments, and alpha to coverage, used by the following draws. The
+/// indexed functions of OpenGL 4.0 are only used if the attachments differ.
+pub(crate) unsafe fn set_blend_state(
+    attachments: &[ColorAttachmentState],
+    blend_constant: [f32; 4],
+    alpha_to_coverage: bool,
+) {
+    let [red, green, blue, alpha] = blend_constant;
+    gl::BlendColor(red, green, blue, alpha);
+    if alpha_to_coverage {
+        gl::Enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+    } else {
+        gl::Disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+    }
+
+    match attachments.split_first() {
+        Some((first, rest)) if rest.iter().all(|state| state == first) => {
+            set_attachment_state(None, first)
+        }
+        _ => {
+            for (i, state) in attachments.iter().enumerate() {
+                set_attachment_state(Some(i as u32), state);
+            }
+        }
+    }
+}
+
+/// Sets the state of the attachment `index`, or of all of them. `None`
+/// disables blending.
+unsafe fn set_attachment_state(index: Option<u32>, state: &ColorAttachmentState) {
+    match (&state.blend, index) {
+        (Some(blend), Some(index)) => {
+            gl::BlendFuncSeparatei(
+                index,
+                blend_factor(blend.color.src_factor),
+                blend_factor(blend.color.dst_factor),
+                blend_factor(blend.alpha.src_factor),
+                blend_factor(blend.alpha.dst_factor),
+            );
+            gl::BlendEquationSeparatei(
+                index,
+                blend_operation(blend.color.operation),
+                blend_operation(blend.alpha.operation),
+            );
+            gl::Enablei(gl::BLEND, index);
+        }
+        (Some(blend), None) => {
+            gl::BlendFuncSeparate(
+                blend_factor(blend.color.src_factor),
+                blend_factor(blend.color.dst_factor),
+                blend_factor(blend.alpha.src_factor),
+                blend_factor(blend.alpha.dst_factor),
+            );
+            gl::BlendEquationSeparate(
+                blend_operation(blend.color.operation),
+                blend_operation(blend.alpha.operation),
+            );
+            gl::Enable(gl::BLEND);
+        }
+        (None, Some(index)) => gl::Disablei(gl::BLEND, index),
+        (None, None) => gl::Disable(gl::BLEND),
+    }
+
+    let mask = state.write_mask;
+    match index {
+        Some(index) => gl::ColorMaski(
+            index,
+            mask.red as u8,
+            mask.green as u8,
+            mask.blue as u8,
+            mask.alpha as u8,
+        ),
+        None => gl::ColorMask(
+            mask.red as u8,
+            mask.green as u8,
+            mask.blue as u8,
+            mask.alpha as u8,
+        ),
+    }
+}
+
+fn blend_factor(factor: BlendFactor) -> u32 {
+    match factor {
+        BlendFactor::Zero => gl::ZERO,
+        BlendFactor::One => gl::ONE,
+        BlendFactor::SrcColor => gl::SRC_COLOR,
+        BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+        BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+        BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+        BlendFactor::DstColor => gl::DST_COLOR,
+        BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+        BlendFactor::DstAlpha => gl::DST_ALPHA,
+        BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+        BlendFactor::SrcAlphaSaturated => gl::SRC_ALPHA_SATURATE,
+        BlendFactor::Constant => gl::CONSTANT_COLOR,
+        BlendFactor::OneMinusConstant => gl::ONE_MINUS_CONSTANT_COLOR,
+        BlendFactor::ConstantAlpha => gl::CONSTANT_ALPHA,
+        BlendFactor::OneMinusConstantAlpha => gl::ONE_MINUS_CONSTANT_ALPHA,
+    }
+}
+
+fn blend_operation(operation: BlendOperation) -> u32 {
+    match operation {
+        BlendOperation::Add => gl::FUNC_ADD,
+        BlendOperation::Subtract => gl::FUNC_SUBTRACT,
+        BlendOperation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+        BlendOperation::Min => gl::MIN,
+        BlendOperation::Max => gl::MAX,
+    }
+}
@@ -0,0 +1,55 @@
+use crate::opengl::internal::compare_function;
+use crate::{CompareFunction, StencilOperation, StencilState};
+use gl;
+
+/// Sets the depth and stencil tests used by the following draws, `None`
+/// disables them.
+pub(crate) unsafe fn set_depth_stencil_state(
+    depth_compare: Option<CompareFunction>,
+    depth_write: bool,
+    stencil: Option<&StencilState>,
+) {
+    match depth_compare {
+        Some(function) => {
+            gl::DepthFunc(compare_function(function));
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        None => gl::Disable(gl::DEPTH_TEST),
+    }
+    gl::DepthMask(depth_write as u8);
+
+    match stencil {
+        Some(stencil) => {
+            for (face, state) in [(gl::FRONT, &stencil.front), (gl::BACK, &stencil.back)] {
+                gl::StencilFuncSeparate(
+                    face,
+                    compare_function(state.compare),
+                    stencil.reference as i32,
+                    stencil.read_mask as u32,
+                );
+                gl::StencilOpSeparate(
+                    face,
+                    stencil_operation(state.fail_op),
+                    stencil_operation(state.depth_fail_op),
+                    stencil_operation(state.pass_op),
+                );
+            }
+            gl::StencilMask(stencil.write_mask as u32);
+            gl::Enable(gl::STENCIL_TEST);
+        }
+        None => gl::Disable(gl::STENCIL_TEST),
+    }
+}
+
+fn stencil_operation(operation: StencilOperation) -> u32 {
+    match operation {
+        StencilOperation::Keep => gl::KEEP,
+        StencilOperation::Zero => gl::ZERO,
+        StencilOperation::Replace => gl::REPLACE,
+        StencilOperation::IncrementClamp => gl::INCR,
+        StencilOperation::DecrementClamp => gl::DECR,
+        StencilOperation::Invert => gl::INVERT,
+        StencilOperation::IncrementWrap => gl::INCR_WRAP,
+        StencilOperation::DecrementWrap => gl::DECR_WRAP,
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{TextureFormat, TextureKind, VertexFormat};
+use gl;
+
+pub(crate) struct VertexAttribFormat {
+    pub(crate) size: i32,
+    pub(crate) data_type: u32,
+    pub(crate) normalized: bool,
+    /// Integer attributes have to be specified with `VertexAttribIPointer`,
+    /// otherwise the shader would see them converted to floats.
+    pub(crate) integer: bool,
+}
+
+pub(crate) fn vertex_attrib_format(format: VertexFormat) -> VertexAttribFormat {
+    let data_type = match format {
+        VertexFormat::Float
+        | VertexFormat::Float2
+        | VertexFormat::Float3
+        | VertexFormat::Float4 => gl::FLOAT,
+        VertexFormat::Half2 | VertexFormat::Half4 => gl::HALF_FLOAT,
+        VertexFormat::UChar4 | VertexFormat::UChar4Normalized => gl::UNSIGNED_BYTE,
+        VertexFormat::Char4 | VertexFormat::Char4Normalized => gl::BYTE,
+        VertexFormat::UShort2
+        | VertexFormat::UShort4
+        | VertexFormat::UShort2Normalized
+        | VertexFormat::UShort4Normalized => gl::UNSIGNED_SHORT,
+        VertexFormat::Short2
+        | VertexFormat::Short4
+        | VertexFormat::Short2Normalized
+        | VertexFormat::Short4Normalized => gl::SHORT,
+        VertexFormat::Int | VertexFormat::Int2 | VertexFormat::Int3 | VertexFormat::Int4 => gl::INT,
+        VertexFormat::UInt | VertexFormat::UInt2 | VertexFormat::UInt3 | VertexFormat::UInt4 => {
+            gl::UNSIGNED_INT
+        }
+    };
+
+    VertexAttribFormat {
+        size: format.components() as i32,
+        data_type,
+        normalized: !format.is_integer() && data_type != gl::FLOAT && data_type != gl::HALF_FLOAT,
+        integer: format.is_integer(),
+    }
+}
+
+pub(crate) struct TexImageFormat {
+    pub(crate) internal_format: i32,
+    pub(crate) format: u32,
+    pub(crate) data_type: u32,
+}
+
+pub(crate) fn tex_image_format(format: TextureFormat) -> TexImageFormat {
+    let (internal_format, format, data_type) = match format {
+        TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+        TextureFormat::Rg8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+        TextureFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        TextureFormat::Rgba8Srgb => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        TextureFormat::R16F => (gl::R16F, gl::RED, gl::HALF_FLOAT),
+        TextureFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+        TextureFormat::Rgba32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        TextureFormat::Depth24Stencil8 => (
+            gl::DEPTH24_STENCIL8,
+            gl::DEPTH_STENCIL,
+            gl::UNSIGNED_INT_24_8,
+        ),
+        TextureFormat::Depth32F => (gl::DEPTH_COMPONENT32F, gl::DEPTH_COMPONENT, gl::FLOAT),
+    };
+
+    TexImageFormat {
+        internal_format: internal_format as i32,
+        format,
+        data_type,
+    }
+}
+
+/// The target a texture of the kind is bound to.
+pub(crate) fn texture_target(kind: TextureKind) -> u32 {
+    match kind {
+        TextureKind::D2 => gl::TEXTURE_2D,
+        TextureKind::D2Array { .. } => gl::TEXTURE_2D_ARRAY,
+        TextureKind::D3 { .. } => gl::TEXTURE_3D,
+        TextureKind::Cube => gl::TEXTURE_CUBE_MAP,
+    }
+}
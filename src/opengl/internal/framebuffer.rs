@@ -0,0 +1,108 @@
+use crate::opengl::err::Result;
+use crate::Error;
+use gl;
+
+pub(crate) struct Framebuffer(pub(crate) u32);
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.0);
+        }
+    }
+}
+
+pub(crate) struct Renderbuffer(pub(crate) u32);
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.0);
+        }
+    }
+}
+
+/// The multisampled attachments of a render target, which passes draw into
+/// instead of its textures.
+pub(crate) struct Multisample {
+    pub(crate) framebuffer: Framebuffer,
+    /// Kept alive as long as the framebuffer.
+    pub(crate) _renderbuffers: Vec<Renderbuffer>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) color_attachment_count: u32,
+    pub(crate) depth: bool,
+}
+
+impl Multisample {
+    /// Blits the samples of the color and depth attachments into the
+    /// textures of `destination`, leaving the multisampled framebuffer bound.
+    /// The scissor test applies to blits, so it has to be disabled.
+    pub(crate) unsafe fn resolve(&self, destination: &Framebuffer) {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer.0);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, destination.0);
+        let (width, height) = (self.width as i32, self.height as i32);
+
+        // Colors are blitted from the read buffer into every draw buffer, so
+        // the attachments are resolved one at a time.
+        for i in 0..self.color_attachment_count {
+            let attachment = gl::COLOR_ATTACHMENT0 + i;
+            let mut draw_buffers = vec![gl::NONE; i as usize + 1];
+            draw_buffers[i as usize] = attachment;
+            gl::ReadBuffer(attachment);
+            gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+        // The draw buffers belong to the destination, which is drawn into
+        // with all of them when it is not multisampled.
+        if self.color_attachment_count > 1 {
+            let draw_buffers: Vec<u32> = (0..self.color_attachment_count)
+                .map(|i| gl::COLOR_ATTACHMENT0 + i)
+                .collect();
+            gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+        }
+        if self.depth {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer.0);
+    }
+}
+
+/// Checks that the bound framebuffer can be drawn into.
+pub(crate) unsafe fn check_framebuffer_status() -> Result<()> {
+    let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+    if status != gl::FRAMEBUFFER_COMPLETE {
+        return Err(Error::Backend(
+            format!(
+                "creating render target: incomplete framebuffer ({:#x})",
+                status
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
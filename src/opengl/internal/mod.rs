@@ -1,5 +1,17 @@
+mod blend;
 mod buffer;
+mod depth_stencil;
+mod format;
+mod framebuffer;
 mod program;
+mod rasterizer;
+mod sampler;
 
+pub(crate) use blend::*;
 pub(crate) use buffer::*;
+pub(crate) use depth_stencil::*;
+pub(crate) use format::*;
+pub(crate) use framebuffer::*;
 pub(crate) use program::*;
+pub(crate) use rasterizer::*;
+pub(crate) use sampler::*;
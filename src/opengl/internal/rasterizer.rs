@@ -0,0 +1,46 @@
+use crate::{CullMode, FrontFace, PolygonMode, RasterizerState};
+use gl;
+
+/// Sets the face culling, polygon mode, depth bias and depth clamping used
+/// by the following draws.
+pub(crate) unsafe fn set_rasterizer_state(rasterizer: &RasterizerState) {
+    match rasterizer.cull_mode {
+        CullMode::None => gl::Disable(gl::CULL_FACE),
+        CullMode::Front => {
+            gl::CullFace(gl::FRONT);
+            gl::Enable(gl::CULL_FACE);
+        }
+        CullMode::Back => {
+            gl::CullFace(gl::BACK);
+            gl::Enable(gl::CULL_FACE);
+        }
+    }
+
+    gl::FrontFace(match rasterizer.front_face {
+        FrontFace::CounterClockwise => gl::CCW,
+        FrontFace::Clockwise => gl::CW,
+    });
+
+    gl::PolygonMode(
+        gl::FRONT_AND_BACK,
+        match rasterizer.polygon_mode {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+        },
+    );
+
+    if rasterizer.depth_bias != 0.0 || rasterizer.depth_bias_slope_scale != 0.0 {
+        gl::PolygonOffset(rasterizer.depth_bias_slope_scale, rasterizer.depth_bias);
+        gl::Enable(gl::POLYGON_OFFSET_FILL);
+        gl::Enable(gl::POLYGON_OFFSET_LINE);
+    } else {
+        gl::Disable(gl::POLYGON_OFFSET_FILL);
+        gl::Disable(gl::POLYGON_OFFSET_LINE);
+    }
+
+    if rasterizer.depth_clamp {
+        gl::Enable(gl::DEPTH_CLAMP);
+    } else {
+        gl::Disable(gl::DEPTH_CLAMP);
+    }
+}
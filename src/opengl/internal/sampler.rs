@@ -0,0 +1,117 @@
+use crate::{AddressMode, BorderColor, CompareFunction, FilterMode, SamplerDesc};
+use gl;
+
+/// From `EXT_texture_filter_anisotropic`, which is core in OpenGL 4.6 and
+/// missing from the generated bindings.
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// Applies the sampler to the texture bound to `target`, which has
+/// `mip_level_count` levels. Mip filters on a single level texture sample
+/// that level.
+pub(crate) unsafe fn set_sampler_parameters(
+    target: u32,
+    sampler: &SamplerDesc,
+    mip_level_count: u32,
+) {
+    gl::TexParameteri(
+        target,
+        gl::TEXTURE_WRAP_S,
+        address_mode(sampler.address_mode_u),
+    );
+    gl::TexParameteri(
+        target,
+        gl::TEXTURE_WRAP_T,
+        address_mode(sampler.address_mode_v),
+    );
+    gl::TexParameteri(
+        target,
+        gl::TEXTURE_WRAP_R,
+        address_mode(sampler.address_mode_w),
+    );
+    let border_color = match sampler.border_color {
+        BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+        BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+        BorderColor::OpaqueWhite => [1.0, 1.0, 1.0, 1.0],
+    };
+    gl::TexParameterfv(target, gl::TEXTURE_BORDER_COLOR, border_color.as_ptr());
+
+    gl::TexParameteri(
+        target,
+        gl::TEXTURE_MAG_FILTER,
+        match sampler.mag_filter {
+            FilterMode::Nearest => gl::NEAREST,
+            FilterMode::Linear => gl::LINEAR,
+        } as i32,
+    );
+    gl::TexParameteri(
+        target,
+        gl::TEXTURE_MIN_FILTER,
+        min_filter(sampler.min_filter, sampler.mip_filter) as i32,
+    );
+    gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, mip_level_count as i32 - 1);
+    gl::TexParameterf(target, gl::TEXTURE_MIN_LOD, sampler.lod_min_clamp);
+    gl::TexParameterf(target, gl::TEXTURE_MAX_LOD, sampler.lod_max_clamp);
+
+    if sampler.max_anisotropy > 1 {
+        // Left at zero when the extension is missing.
+        let mut max_anisotropy = 0.0;
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+        if max_anisotropy >= 1.0 {
+            gl::TexParameterf(
+                target,
+                TEXTURE_MAX_ANISOTROPY,
+                (sampler.max_anisotropy as f32).min(max_anisotropy),
+            );
+        }
+    }
+
+    match sampler.compare {
+        Some(compare) => {
+            gl::TexParameteri(
+                target,
+                gl::TEXTURE_COMPARE_MODE,
+                gl::COMPARE_REF_TO_TEXTURE as i32,
+            );
+            gl::TexParameteri(
+                target,
+                gl::TEXTURE_COMPARE_FUNC,
+                compare_function(compare) as i32,
+            );
+        }
+        None => gl::TexParameteri(target, gl::TEXTURE_COMPARE_MODE, gl::NONE as i32),
+    }
+}
+
+fn address_mode(mode: AddressMode) -> i32 {
+    (match mode {
+        AddressMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+        AddressMode::Repeat => gl::REPEAT,
+        AddressMode::MirrorRepeat => gl::MIRRORED_REPEAT,
+        AddressMode::ClampToBorder => gl::CLAMP_TO_BORDER,
+    }) as i32
+}
+
+fn min_filter(min_filter: FilterMode, mip_filter: Option<FilterMode>) -> u32 {
+    match (min_filter, mip_filter) {
+        (FilterMode::Nearest, None) => gl::NEAREST,
+        (FilterMode::Linear, None) => gl::LINEAR,
+        (FilterMode::Nearest, Some(FilterMode::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (FilterMode::Linear, Some(FilterMode::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (FilterMode::Nearest, Some(FilterMode::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (FilterMode::Linear, Some(FilterMode::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+pub(crate) fn compare_function(function: CompareFunction) -> u32 {
+    match function {
+        CompareFunction::Never => gl::NEVER,
+        CompareFunction::Less => gl::LESS,
+        CompareFunction::Equal => gl::EQUAL,
+        CompareFunction::LessEqual => gl::LEQUAL,
+        CompareFunction::Greater => gl::GREATER,
+        CompareFunction::NotEqual => gl::NOTEQUAL,
+        CompareFunction::GreaterEqual => gl::GEQUAL,
+        CompareFunction::Always => gl::ALWAYS,
+    }
+}
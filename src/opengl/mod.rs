@@ -1,21 +1,27 @@
 mod command_buffer;
+mod compute_pipeline;
 mod context;
 mod err;
+mod index_buffer;
 mod internal;
 mod pipeline;
+mod render_target;
 mod renderable;
 mod shader;
+mod storage_buffer;
 mod texture;
 mod uniform_buffer;
 mod vertex_buffer;
-// mod index_buffer;
 
 pub use command_buffer::*;
+pub use compute_pipeline::*;
 pub use context::*;
+pub use index_buffer::*;
 pub use pipeline::*;
+pub use render_target::*;
 pub use renderable::*;
 pub use shader::*;
+pub use storage_buffer::*;
 pub use texture::*;
 pub use uniform_buffer::*;
 pub use vertex_buffer::*;
-// pub use index_buffer::*;
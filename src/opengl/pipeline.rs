@@ -1,7 +1,10 @@
 use crate::opengl::err::Result;
 use crate::opengl::internal::Program;
 use crate::opengl::Shader;
-use crate::shared::{Binding, PipelineDesc};
+use crate::shared::{
+    Binding, BlendState, ColorAttachmentState, ColorWriteMask, CompareFunction, PipelineDesc,
+    PrimitiveTopology, RasterizerState, StencilState,
+};
 use std::rc::Rc;
 
 pub struct Pipeline {
@@ -9,9 +12,16 @@ pub struct Pipeline {
     /// corresponding vertex array object.
     pub(crate) index: u32,
     pub(crate) shader_program: Rc<Program>,
-    pub(crate) depth_test: bool,
+    pub(crate) primitive_mode: u32,
+    pub(crate) depth_compare: Option<CompareFunction>,
     pub(crate) depth_write: bool,
-    pub(crate) alpha_blend: bool,
+    pub(crate) stencil: Option<StencilState>,
+    pub(crate) blend: Option<BlendState>,
+    pub(crate) blend_constant: [f32; 4],
+    pub(crate) alpha_to_coverage: bool,
+    pub(crate) color_write_mask: ColorWriteMask,
+    pub(crate) color_attachments: Vec<ColorAttachmentState>,
+    pub(crate) rasterizer: RasterizerState,
     pub(crate) bindings: Vec<Binding>,
 }
 
@@ -29,10 +39,35 @@ impl Pipeline {
         Ok(Pipeline {
             index,
             shader_program: shader.program.clone(),
-            depth_test: desc.depth_test,
+            primitive_mode: match desc.topology {
+                PrimitiveTopology::Points => gl::POINTS,
+                PrimitiveTopology::Lines => gl::LINES,
+                PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+                PrimitiveTopology::Triangles => gl::TRIANGLES,
+                PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            },
+            depth_compare: desc.depth_compare,
             depth_write: desc.depth_write,
-            alpha_blend: desc.alpha_blend,
+            stencil: desc.stencil,
+            blend: desc.blend,
+            blend_constant: desc.blend_constant,
+            alpha_to_coverage: desc.alpha_to_coverage,
+            color_write_mask: desc.color_write_mask,
+            color_attachments: Vec::from(desc.color_attachments),
+            rasterizer: desc.rasterizer,
             bindings: Vec::from(desc.bindings),
         })
     }
+
+    /// The state of the color attachment `index`, see
+    /// `PipelineDesc::color_attachment`.
+    pub(crate) fn color_attachment(&self, index: usize) -> ColorAttachmentState {
+        self.color_attachments
+            .get(index)
+            .copied()
+            .unwrap_or(ColorAttachmentState {
+                blend: self.blend,
+                write_mask: self.color_write_mask,
+            })
+    }
 }
@@ -0,0 +1,192 @@
+use crate::opengl::err::Result;
+use crate::opengl::internal::{
+    check_framebuffer_status, tex_image_format, Framebuffer, Multisample, Renderbuffer,
+};
+use crate::opengl::Texture;
+use crate::shared::image::check_sample_count;
+use crate::{Attachments, TextureFormat};
+use gl;
+use std::rc::Rc;
+
+pub struct RenderTarget {
+    /// The framebuffer of the textures.
+    pub(crate) framebuffer: Rc<Framebuffer>,
+    /// Drawn into instead of `framebuffer` with more than one sample, and
+    /// resolved into it when a pass ends.
+    pub(crate) multisample: Option<Rc<Multisample>>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) sample_count: u32,
+    pub(crate) color_textures: Vec<Texture>,
+    pub(crate) depth_texture: Option<Texture>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        check_sample_count(sample_count)?;
+
+        unsafe {
+            let mut framebuffer = 0;
+            gl::GenFramebuffers(1, &mut framebuffer);
+            let framebuffer = Framebuffer(framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.0);
+
+            let mut color_textures = Vec::with_capacity(color_formats.len());
+            let mut draw_buffers = Vec::with_capacity(color_formats.len());
+            for (i, format) in color_formats.iter().enumerate() {
+                let texture = Texture::with_format(width, height, *format)?;
+                let attachment = gl::COLOR_ATTACHMENT0 + i as u32;
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::TEXTURE_2D,
+                    texture.texture,
+                    0,
+                );
+                color_textures.push(texture);
+                draw_buffers.push(attachment);
+            }
+            gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+
+            let depth_texture = match depth_format {
+                Some(format) => {
+                    let texture = Texture::with_format(width, height, format)?;
+                    gl::FramebufferTexture2D(
+                        gl::FRAMEBUFFER,
+                        depth_attachment(format),
+                        gl::TEXTURE_2D,
+                        texture.texture,
+                        0,
+                    );
+                    Some(texture)
+                }
+                None => None,
+            };
+
+            let status = check_framebuffer_status();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            status?;
+
+            let multisample = if sample_count > 1 {
+                Some(Rc::new(new_multisample(
+                    width,
+                    height,
+                    color_formats,
+                    depth_format,
+                    sample_count,
+                )?))
+            } else {
+                None
+            };
+
+            Ok(RenderTarget {
+                framebuffer: Rc::new(framebuffer),
+                multisample,
+                width,
+                height,
+                sample_count,
+                color_textures,
+                depth_texture,
+            })
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.color_textures
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+}
+
+impl Attachments for RenderTarget {
+    type Texture = Texture;
+
+    fn color_textures(&self) -> &[Texture] {
+        RenderTarget::color_textures(self)
+    }
+
+    fn depth_texture(&self) -> Option<&Texture> {
+        RenderTarget::depth_texture(self)
+    }
+}
+
+/// Creates a framebuffer of multisampled renderbuffers in the formats.
+unsafe fn new_multisample(
+    width: u32,
+    height: u32,
+    color_formats: &[TextureFormat],
+    depth_format: Option<TextureFormat>,
+    sample_count: u32,
+) -> Result<Multisample> {
+    let mut framebuffer = 0;
+    gl::GenFramebuffers(1, &mut framebuffer);
+    let framebuffer = Framebuffer(framebuffer);
+    gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.0);
+
+    let mut renderbuffers = Vec::with_capacity(color_formats.len() + 1);
+    let mut draw_buffers = Vec::with_capacity(color_formats.len());
+    let mut attach = |attachment, format| {
+        let mut renderbuffer = 0;
+        gl::GenRenderbuffers(1, &mut renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        gl::RenderbufferStorageMultisample(
+            gl::RENDERBUFFER,
+            sample_count as i32,
+            tex_image_format(format).internal_format as u32,
+            width as i32,
+            height as i32,
+        );
+        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, renderbuffer);
+        renderbuffers.push(Renderbuffer(renderbuffer));
+    };
+    for (i, format) in color_formats.iter().enumerate() {
+        let attachment = gl::COLOR_ATTACHMENT0 + i as u32;
+        attach(attachment, *format);
+        draw_buffers.push(attachment);
+    }
+    if let Some(format) = depth_format {
+        attach(depth_attachment(format), format);
+    }
+    gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+
+    let status = check_framebuffer_status();
+    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    status?;
+
+    Ok(Multisample {
+        framebuffer,
+        _renderbuffers: renderbuffers,
+        width,
+        height,
+        color_attachment_count: color_formats.len() as u32,
+        depth: depth_format.is_some(),
+    })
+}
+
+fn depth_attachment(format: TextureFormat) -> u32 {
+    if format.has_stencil() {
+        gl::DEPTH_STENCIL_ATTACHMENT
+    } else {
+        gl::DEPTH_ATTACHMENT
+    }
+}
@@ -1,12 +1,14 @@
 use crate::opengl::err::Result;
 use crate::opengl::internal::Buffer;
-use crate::opengl::VertexBuffer;
+use crate::opengl::{IndexBuffer, VertexBuffer};
+use crate::IndexFormat;
 use std::cell::Cell;
 use std::rc::Rc;
 
 pub struct Renderable {
     pub(crate) vertex_arrays: Cell<Vec<(u32, VertexArray)>>,
     pub(crate) vertex_buffers: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) index_buffer: Option<(Rc<Buffer>, IndexFormat)>,
 }
 
 impl Renderable {
@@ -17,8 +19,18 @@ impl Renderable {
                 .iter()
                 .map(|(i, vertex_buffer)| (*i, vertex_buffer.buffer.clone()))
                 .collect(),
+            index_buffer: None,
         })
     }
+
+    pub(crate) fn with_index(
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        let mut renderable = Renderable::new(vertex_buffers)?;
+        renderable.index_buffer = Some((Rc::clone(&index_buffer.buffer), index_buffer.format));
+        Ok(renderable)
+    }
 }
 
 pub(crate) struct VertexArray(pub(crate) u32);
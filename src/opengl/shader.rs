@@ -1,5 +1,6 @@
 use crate::opengl::err::Result;
 use crate::opengl::internal::Program;
+use crate::{Error, ShaderStage};
 use gl;
 use std::ffi::CString;
 use std::path::PathBuf;
@@ -25,50 +26,12 @@ pub struct Shader {
 
 impl Shader {
     pub(crate) fn new(vertex_source: &str, fragment_source: &str) -> Result<Shader> {
-        let vertex_shader = compile_shader(gl::VERTEX_SHADER, vertex_source)?;
-        let fragment_shader = compile_shader(gl::FRAGMENT_SHADER, fragment_source)?;
-
-        unsafe {
-            let program = gl::CreateProgram();
-            gl::AttachShader(program, vertex_shader);
-            gl::AttachShader(program, fragment_shader);
-            gl::LinkProgram(program);
-
-            let mut link_status = 0;
-            gl::GetProgramiv(program, gl::LINK_STATUS, &mut link_status);
-            if link_status == 0 {
-                let mut info_log_length = 0;
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut info_log_length);
-
-                // Info log length includes the null terminator, so 1 means that
-                // the info log is an empty string.
-                let message = if info_log_length > 1 {
-                    let mut info_log_buffer = Vec::with_capacity(info_log_length as usize);
-                    info_log_buffer.set_len(info_log_length as usize);
-                    gl::GetProgramInfoLog(
-                        program,
-                        info_log_length,
-                        null_mut(),
-                        info_log_buffer.as_mut_ptr() as *mut _,
-                    );
-                    let info_log = match str::from_utf8(&info_log_buffer) {
-                        Ok(info_log) => info_log,
-                        Err(_) => "<empty log message>",
-                    };
-
-                    format!("linking shader program: {}", info_log)
-                } else {
-                    format!("linking shader program: <empty log message>")
-                };
-
-                gl::DeleteProgram(program);
-                return Err(message);
-            }
-
-            Ok(Shader {
-                program: Rc::from(Program(program)),
-            })
-        }
+        let vertex_shader = compile_shader(ShaderStage::Vertex, vertex_source)?;
+        let fragment_shader = compile_shader(ShaderStage::Fragment, fragment_source)?;
+
+        Ok(Shader {
+            program: Rc::from(link_program(&[vertex_shader, fragment_shader])?),
+        })
     }
 
     /// This is an OPENGL ONLY API, and is only needed for a subset of OpenGL
@@ -78,7 +41,7 @@ impl Shader {
             let uniform_name_cstr = match CString::new(uniform_name) {
                 Ok(uniform_name) => uniform_name,
                 Err(..) => {
-                    return Err("converting uniform name to C string".into());
+                    return Err(Error::MissingUniform(uniform_name.into()));
                 }
             };
 
@@ -88,7 +51,7 @@ impl Shader {
         if location != gl::INVALID_INDEX {
             Ok(location)
         } else {
-            Err(format!("shader uniform \"{}\" not found", uniform_name))
+            Err(Error::MissingUniform(uniform_name.into()))
         }
     }
 
@@ -99,7 +62,7 @@ impl Shader {
             let texture_name_cstr = match CString::new(texture_name) {
                 Ok(texture_name) => texture_name,
                 Err(..) => {
-                    return Err("converting texture name to C string".into());
+                    return Err(Error::MissingTexture(texture_name.into()));
                 }
             };
 
@@ -109,12 +72,59 @@ impl Shader {
         if location >= 0 {
             Ok(location)
         } else {
-            Err(format!("shader texture \"{}\" not found", texture_name))
+            Err(Error::MissingTexture(texture_name.into()))
         }
     }
 }
 
-fn compile_shader(shader_type: u32, shader_source: &str) -> Result<u32> {
+pub(crate) fn link_program(shaders: &[u32]) -> Result<Program> {
+    unsafe {
+        let program = gl::CreateProgram();
+        for shader in shaders {
+            gl::AttachShader(program, *shader);
+        }
+        gl::LinkProgram(program);
+
+        let mut link_status = 0;
+        gl::GetProgramiv(program, gl::LINK_STATUS, &mut link_status);
+        if link_status == 0 {
+            let mut info_log_length = 0;
+            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut info_log_length);
+
+            // Info log length includes the null terminator, so 1 means that
+            // the info log is an empty string.
+            let log = if info_log_length > 1 {
+                let mut info_log_buffer = Vec::with_capacity(info_log_length as usize);
+                info_log_buffer.set_len(info_log_length as usize);
+                gl::GetProgramInfoLog(
+                    program,
+                    info_log_length,
+                    null_mut(),
+                    info_log_buffer.as_mut_ptr() as *mut _,
+                );
+                match str::from_utf8(&info_log_buffer) {
+                    Ok(info_log) => info_log.into(),
+                    Err(_) => "<empty log message>".into(),
+                }
+            } else {
+                "<empty log message>".into()
+            };
+
+            gl::DeleteProgram(program);
+            return Err(Error::ProgramLink { log });
+        }
+
+        Ok(Program(program))
+    }
+}
+
+pub(crate) fn compile_shader(stage: ShaderStage, shader_source: &str) -> Result<u32> {
+    let shader_type = match stage {
+        ShaderStage::Vertex => gl::VERTEX_SHADER,
+        ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+        ShaderStage::Compute => gl::COMPUTE_SHADER,
+    };
+
     unsafe {
         let shader = gl::CreateShader(shader_type);
         gl::ShaderSource(
@@ -132,16 +142,9 @@ fn compile_shader(shader_type: u32, shader_source: &str) -> Result<u32> {
             let mut info_log_length = 0;
             gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut info_log_length);
 
-            let shader_type = match shader_type {
-                gl::VERTEX_SHADER => "vertex",
-                gl::FRAGMENT_SHADER => "fragment",
-                gl::COMPUTE_SHADER => "compute",
-                _ => "unknown",
-            };
-
             // Info log length includes the null terminator, so 1 means that the info log is an
             // empty string.
-            let message = if info_log_length > 1 {
+            let log = if info_log_length > 1 {
                 let mut info_log_buffer = Vec::with_capacity(info_log_length as usize);
                 info_log_buffer.set_len(info_log_length as usize);
                 gl::GetShaderInfoLog(
@@ -150,17 +153,16 @@ fn compile_shader(shader_type: u32, shader_source: &str) -> Result<u32> {
                     null_mut(),
                     info_log_buffer.as_mut_ptr() as *mut _,
                 );
-                let info_log = match str::from_utf8(&info_log_buffer) {
-                    Ok(info_log) => info_log,
-                    Err(_) => "<empty log message>",
-                };
-                format!("compiling {} shader: {}", shader_type, info_log)
+                match str::from_utf8(&info_log_buffer) {
+                    Ok(info_log) => info_log.into(),
+                    Err(_) => "<empty log message>".into(),
+                }
             } else {
-                format!("compiling {} shader: <empty log message>", shader_type)
+                "<empty log message>".into()
             };
             gl::DeleteShader(shader);
 
-            return Err(message);
+            return Err(Error::ShaderCompile { stage, log });
         }
 
         Ok(shader)
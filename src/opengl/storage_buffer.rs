@@ -0,0 +1,94 @@
+use crate::opengl::err::Result;
+use crate::opengl::internal::Buffer;
+use crate::Error;
+use gl;
+use std::mem::size_of_val;
+use std::ptr::null;
+
+pub struct StorageBuffer {
+    pub(crate) buffer: Buffer,
+    /// The number of bytes that can be stored in this buffer.
+    pub(crate) capacity: usize,
+}
+
+impl StorageBuffer {
+    pub(crate) fn with_capacity(capacity: usize) -> Result<StorageBuffer> {
+        StorageBuffer::new(capacity, null())
+    }
+
+    pub(crate) fn with_data<T>(data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        StorageBuffer::new(size_of_val(data), data.as_ptr() as *const _)
+    }
+
+    fn new(capacity: usize, data: *const std::ffi::c_void) -> Result<StorageBuffer> {
+        if !gl::ShaderStorageBlockBinding::is_loaded() {
+            return Err(Error::Unsupported(
+                "storage buffers before OpenGL 4.3".into(),
+            ));
+        }
+
+        unsafe {
+            let mut buffer = 0;
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                capacity as isize,
+                data,
+                gl::DYNAMIC_COPY,
+            );
+
+            Ok(StorageBuffer {
+                buffer: Buffer(buffer),
+                capacity,
+            })
+        }
+    }
+
+    /// Replaces the start of the buffer, the rest keeps its contents.
+    pub(crate) fn update<T>(&mut self, data: &[T]) -> Result<()>
+    where
+        T: Sized,
+    {
+        unsafe {
+            let length = size_of_val(data);
+            if length > self.capacity {
+                return Err(Error::CapacityOverflow {
+                    capacity: self.capacity,
+                    length,
+                });
+            }
+
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.buffer.0);
+            gl::BufferSubData(
+                gl::SHADER_STORAGE_BUFFER,
+                0,
+                length as isize,
+                data.as_ptr() as *const _,
+            );
+
+            Ok(())
+        }
+    }
+
+    /// Shader writes are only guaranteed to be seen by reads through the
+    /// buffer API after a barrier.
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        let mut contents = vec![0u8; self.capacity];
+        unsafe {
+            gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.buffer.0);
+            gl::GetBufferSubData(
+                gl::SHADER_STORAGE_BUFFER,
+                0,
+                self.capacity as isize,
+                contents.as_mut_ptr() as *mut _,
+            );
+        }
+
+        Ok(contents)
+    }
+}
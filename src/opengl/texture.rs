@@ -1,76 +1,299 @@
 use crate::opengl::err::Result;
-use crate::shared::image::{is_power_of_2, load_rgba_image};
-use crate::TextureFilter;
+use crate::opengl::internal::{set_sampler_parameters, tex_image_format, texture_target};
+use crate::shared::image::{
+    check_mip_levels, check_region, check_update_kind, generate_mip_levels, kind_mip_level_count,
+    layer_count, load_rgba_image, mip_level_size, rgba_image_from_depth,
+};
+use crate::{SamplerDesc, TextureFormat, TextureKind, TextureRegion};
 use gl;
+use image::RgbaImage;
+use std::borrow::Cow;
+use std::iter;
+use std::ptr::null;
 
 pub struct Texture {
     pub(crate) texture: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) kind: TextureKind,
+    pub(crate) format: TextureFormat,
+    pub(crate) mip_level_count: u32,
+    /// Whether the rows are stored bottom up, as they are when the texture
+    /// is rendered into.
+    pub(crate) flip_y: bool,
 }
 
 impl Texture {
-    pub(crate) fn new(image_path: &str, filter: TextureFilter) -> Result<Texture> {
+    pub(crate) fn new(image_path: &str, sampler: &SamplerDesc) -> Result<Texture> {
+        let img = load_rgba_image(image_path)?;
+
+        Texture::with_pixels(
+            TextureKind::D2,
+            img.width(),
+            img.height(),
+            TextureFormat::Rgba8,
+            &img,
+            sampler,
+        )
+    }
+
+    /// Creates a texture from tightly packed texels, with a full mip chain
+    /// if the sampler filters between mip levels. sRGB chains are built on
+    /// the CPU, as drivers are free to filter them in gamma space.
+    pub(crate) fn with_pixels(
+        kind: TextureKind,
+        w: u32,
+        h: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, w, h, format, &[data])?;
+        if sampler.mip_filter.is_none() || format.is_depth() {
+            return Texture::with_mip_levels(kind, w, h, format, &[data], sampler);
+        }
+
+        if format == TextureFormat::Rgba8Srgb {
+            let generated = generate_mip_levels(kind, w, h, format, data)?;
+            let levels: Vec<&[u8]> = iter::once(data)
+                .chain(generated.iter().map(|level| level.as_slice()))
+                .collect();
+
+            return Texture::with_mip_levels(kind, w, h, format, &levels, sampler);
+        }
+
+        let mut texture = Texture::with_mip_levels(kind, w, h, format, &[data], sampler)?;
+        texture.mip_level_count = kind_mip_level_count(kind, w, h);
+        unsafe {
+            // Mipmaps are only generated up to the max level.
+            let target = texture_target(kind);
+            set_sampler_parameters(target, sampler, texture.mip_level_count);
+            gl::GenerateMipmap(target);
+        }
+
+        Ok(texture)
+    }
+
+    /// Every level holds the layers, slices or cube faces of the texture one
+    /// after the other.
+    pub(crate) fn with_mip_levels(
+        kind: TextureKind,
+        w: u32,
+        h: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, w, h, format, levels)?;
+
         unsafe {
+            let target = texture_target(kind);
             let mut texture = 0;
             gl::GenTextures(1, &mut texture);
-            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::BindTexture(target, texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            let tex_image_format = tex_image_format(format);
+            for (mip_level, data) in levels.iter().enumerate() {
+                let (width, height) = mip_level_size(w, h, mip_level as u32);
+                let layers = layer_count(kind, mip_level as u32);
+                match kind {
+                    TextureKind::D2 => gl::TexImage2D(
+                        target,
+                        mip_level as i32,
+                        tex_image_format.internal_format,
+                        width as i32,
+                        height as i32,
+                        0,
+                        tex_image_format.format,
+                        tex_image_format.data_type,
+                        data.as_ptr() as *const _,
+                    ),
+                    TextureKind::D2Array { .. } | TextureKind::D3 { .. } => gl::TexImage3D(
+                        target,
+                        mip_level as i32,
+                        tex_image_format.internal_format,
+                        width as i32,
+                        height as i32,
+                        layers as i32,
+                        0,
+                        tex_image_format.format,
+                        tex_image_format.data_type,
+                        data.as_ptr() as *const _,
+                    ),
+                    TextureKind::Cube => {
+                        let face_size = data.len() / layers as usize;
+                        for (face, data) in data.chunks_exact(face_size).enumerate() {
+                            gl::TexImage2D(
+                                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                                mip_level as i32,
+                                tex_image_format.internal_format,
+                                width as i32,
+                                height as i32,
+                                0,
+                                tex_image_format.format,
+                                tex_image_format.data_type,
+                                data.as_ptr() as *const _,
+                            );
+                        }
+                    }
+                }
+            }
+            set_sampler_parameters(target, sampler, levels.len() as u32);
 
-            let img = load_rgba_image(image_path)?;
-            let w = img.width();
-            let h = img.height();
+            Ok(Texture {
+                texture,
+                width: w,
+                height: h,
+                kind,
+                format,
+                mip_level_count: levels.len() as u32,
+                flip_y: false,
+            })
+        }
+    }
 
+    /// Creates a texture with undefined contents, to be rendered into.
+    pub(crate) fn with_format(width: u32, height: u32, format: TextureFormat) -> Result<Texture> {
+        unsafe {
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+
+            let tex_image_format = tex_image_format(format);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGBA as i32,
-                w as i32,
-                h as i32,
+                tex_image_format.internal_format,
+                width as i32,
+                height as i32,
                 0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                img.into_raw().as_ptr() as *const _,
+                tex_image_format.format,
+                tex_image_format.data_type,
+                null(),
             );
 
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            set_sampler_parameters(gl::TEXTURE_2D, &SamplerDesc::default(), 1);
 
-            match filter {
-                TextureFilter::Nearest => {
-                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-                }
-                TextureFilter::Linear => {
-                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-                }
-                TextureFilter::MipMap => {
-                    if is_power_of_2(w) && is_power_of_2(h) {
-                        gl::GenerateMipmap(gl::TEXTURE_2D);
-                        gl::TexParameteri(
-                            gl::TEXTURE_2D,
-                            gl::TEXTURE_MIN_FILTER,
-                            gl::LINEAR_MIPMAP_LINEAR as i32,
-                        );
-                        gl::TexParameteri(
-                            gl::TEXTURE_2D,
-                            gl::TEXTURE_MAG_FILTER,
-                            gl::LINEAR_MIPMAP_LINEAR as i32,
-                        );
-                    } else {
-                        gl::TexParameteri(
-                            gl::TEXTURE_2D,
-                            gl::TEXTURE_MIN_FILTER,
-                            gl::LINEAR as i32,
-                        );
-                        gl::TexParameteri(
-                            gl::TEXTURE_2D,
-                            gl::TEXTURE_MAG_FILTER,
-                            gl::LINEAR as i32,
-                        );
-                    }
-                }
+            Ok(Texture {
+                texture,
+                width,
+                height,
+                kind: TextureKind::D2,
+                format,
+                mip_level_count: 1,
+                flip_y: true,
+            })
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        check_update_kind(self.kind)?;
+        check_region(
+            self.width,
+            self.height,
+            self.mip_level_count,
+            self.format,
+            region,
+            mip_level,
+            data,
+        )?;
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
+        }
+
+        // Rendered textures are stored bottom up, so the region and its rows
+        // are flipped to keep `y` counting from the top.
+        let (y, data) = if self.flip_y {
+            let row_size = region.width as usize * self.format.texel_size();
+            let mip_height = (self.height >> mip_level).max(1);
+            (
+                mip_height - region.y - region.height,
+                Cow::Owned(
+                    data.chunks_exact(row_size)
+                        .rev()
+                        .flatten()
+                        .copied()
+                        .collect(),
+                ),
+            )
+        } else {
+            (region.y, Cow::Borrowed(data))
+        };
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            let tex_image_format = tex_image_format(self.format);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                mip_level as i32,
+                region.x as i32,
+                y as i32,
+                region.width as i32,
+                region.height as i32,
+                tex_image_format.format,
+                tex_image_format.data_type,
+                data.as_ptr() as *const _,
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads back the texture as an 8 bit RGBA image with the first row at
+    /// the top. Float formats are clamped to `[0, 1]` and depth formats are
+    /// read as grayscale. Only the first layer, slice or face is read.
+    pub fn read_to_image(&self) -> Result<RgbaImage> {
+        unsafe {
+            // Image stores are only guaranteed to be seen by reads through
+            // the texture API after a barrier.
+            if gl::MemoryBarrier::is_loaded() {
+                gl::MemoryBarrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
             }
+            gl::BindTexture(texture_target(self.kind), self.texture);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            // Arrays and 3D textures can only be read as a whole, cube faces
+            // are read one at a time.
+            let (target, layers) = match self.kind {
+                TextureKind::Cube => (gl::TEXTURE_CUBE_MAP_POSITIVE_X, 1),
+                kind => (texture_target(kind), layer_count(kind, 0)),
+            };
+            let texel_count = self.width as usize * self.height as usize;
 
-            Ok(Texture { texture })
+            let mut img = if self.format.is_depth() {
+                let mut depths = vec![0.0f32; texel_count * layers as usize];
+                gl::GetTexImage(
+                    target,
+                    0,
+                    gl::DEPTH_COMPONENT,
+                    gl::FLOAT,
+                    depths.as_mut_ptr() as *mut _,
+                );
+                rgba_image_from_depth(self.width, self.height, &depths[..texel_count])
+            } else {
+                let mut pixels = vec![0u8; texel_count * 4 * layers as usize];
+                gl::GetTexImage(
+                    target,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_mut_ptr() as *mut _,
+                );
+                pixels.truncate(texel_count * 4);
+                RgbaImage::from_raw(self.width, self.height, pixels).unwrap()
+            };
+
+            if self.flip_y {
+                image::imageops::flip_vertical_in_place(&mut img);
+            }
+            Ok(img)
         }
     }
 }
@@ -1,5 +1,6 @@
 use crate::opengl::err::Result;
 use crate::opengl::internal::Buffer;
+use crate::Error;
 use gl;
 use std::mem::size_of;
 use std::ptr::null;
@@ -62,9 +63,10 @@ impl VertexBuffer {
         unsafe {
             let length = data.len() * size_of::<T>();
             if length > self.capacity {
-                return Err(
-                    "updating vertex buffer: new data is longer than buffer capacity".into(),
-                );
+                return Err(Error::CapacityOverflow {
+                    capacity: self.capacity,
+                    length,
+                });
             }
 
             gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer.0);
@@ -0,0 +1,400 @@
+use crate::{
+    Index, PassDesc, PipelineDesc, Rect, Result, SamplerDesc, TextureFormat, TextureRegion,
+};
+use image::{DynamicImage, RgbaImage};
+
+/// The resource factory of a backend.
+///
+/// Every backend's `Context` implements this trait, so application code can
+/// be written once and be generic over the backend it renders with.
+pub trait Device {
+    type Frame: Frame<Device = Self>;
+    type Library;
+    type Shader;
+    type Pipeline;
+    type ComputePipeline;
+    type Texture;
+    type UniformBuffer;
+    type VertexBuffer;
+    type StorageBuffer;
+    type IndexBuffer;
+    type Renderable;
+    type RenderTarget: Attachments<Texture = Self::Texture>;
+
+    fn next_frame(&mut self) -> Result<Self::Frame>;
+
+    fn create_library(&mut self, library_path: &str) -> Result<Self::Library>;
+
+    fn create_shader(
+        &mut self,
+        library: &Self::Library,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) -> Result<Self::Shader>;
+
+    fn create_pipeline(
+        &mut self,
+        shader: &Self::Shader,
+        desc: &PipelineDesc,
+    ) -> Result<Self::Pipeline>;
+
+    /// Creates a pipeline running the compute function of the library in
+    /// workgroups of `workgroup_size` invocations. OpenGL shaders declare the
+    /// size with `local_size_x`, `local_size_y` and `local_size_z`, and have
+    /// to agree with it, or `Error::WorkgroupSize` is returned. Needs OpenGL
+    /// 4.3, WebGL 2 has no compute shaders.
+    fn create_compute_pipeline(
+        &mut self,
+        library: &Self::Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<Self::ComputePipeline>;
+
+    fn create_texture(&mut self, image_path: &str) -> Result<Self::Texture>;
+
+    fn create_texture_with_sampler(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a texture from a decoded image, converted to `format`. Single
+    /// and two channel formats take the luma, and the luma and alpha, of the
+    /// image.
+    fn create_texture_from_image(
+        &mut self,
+        image: &DynamicImage,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a texture from tightly packed rows of texels in `format`,
+    /// starting with the top row.
+    fn create_texture_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates an `Rgba8` texture from an encoded image, such as the
+    /// contents of a PNG file.
+    fn create_texture_from_memory(
+        &mut self,
+        bytes: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a texture from pre-built mip levels, each holding tightly
+    /// packed rows of texels in `format` starting with the top row.
+    /// `levels[0]` is `width` by `height` texels and every following level
+    /// halves the size of the one before, down to 1 texel. The chain may stop
+    /// before reaching 1 by 1, no more levels are generated.
+    fn create_texture_from_mip_levels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a 2D texture array with one layer per slice of `layers`, each
+    /// holding `width` by `height` texels in `format`.
+    fn create_texture_array_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a 3D texture from `depth` slices of `width` by `height`
+    /// texels, stored back to back starting with the front slice.
+    fn create_texture_3d_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates an `Rgba8` cube map from six square images of the same size,
+    /// in the order +X, -X, +Y, -Y, +Z, -Z.
+    fn create_cube_texture(
+        &mut self,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates an `Rgba8` cube map from a single image laid out as a
+    /// horizontal cross, 4 faces wide and 3 faces high:
+    ///
+    /// ```text
+    ///       +Y
+    ///    -X +Z +X -Z
+    ///       -Y
+    /// ```
+    fn create_cube_texture_from_cross(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Creates a cube map from six faces of `size` by `size` texels in
+    /// `format`, in the order +X, -X, +Y, -Y, +Z, -Z.
+    fn create_cube_texture_from_pixels(
+        &mut self,
+        size: u32,
+        format: TextureFormat,
+        faces: &[&[u8]; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Self::Texture>;
+
+    /// Replaces the texels of a region of a mip level with tightly packed
+    /// rows of texels in the texture's format, starting with the top row.
+    /// Other mip levels are left as they are. Only 2D textures can be
+    /// updated.
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::Texture,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()>;
+
+    fn create_uniform_buffer_with_capacity(
+        &mut self,
+        capacity: usize,
+    ) -> Result<Self::UniformBuffer>;
+
+    fn create_uniform_buffer_with_value<T>(&mut self, value: &T) -> Result<Self::UniformBuffer>
+    where
+        T: Sized;
+
+    fn update_uniform_buffer<T>(
+        &mut self,
+        uniform_buffer: &mut Self::UniformBuffer,
+        data: &T,
+    ) -> Result<()>
+    where
+        T: Sized;
+
+    fn create_vertex_buffer_with_capacity(&mut self, capacity: usize)
+        -> Result<Self::VertexBuffer>;
+
+    fn create_vertex_buffer_with_data<T>(&mut self, data: &[T]) -> Result<Self::VertexBuffer>
+    where
+        T: Sized;
+
+    fn update_vertex_buffer<T>(
+        &mut self,
+        vertex_buffer: &mut Self::VertexBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized;
+
+    /// Creates a buffer that shaders can both read and write, without the
+    /// size limits of uniform buffers. Needs OpenGL 4.3, WebGL 2 has no
+    /// storage buffers.
+    fn create_storage_buffer_with_capacity(
+        &mut self,
+        capacity: usize,
+    ) -> Result<Self::StorageBuffer>;
+
+    fn create_storage_buffer_with_data<T>(&mut self, data: &[T]) -> Result<Self::StorageBuffer>
+    where
+        T: Sized;
+
+    fn update_storage_buffer<T>(
+        &mut self,
+        storage_buffer: &mut Self::StorageBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized;
+
+    /// Reads back the contents of the storage buffer, as written by the
+    /// shaders of the submitted frames. Waits for them to finish.
+    fn read_storage_buffer(&mut self, storage_buffer: &Self::StorageBuffer) -> Result<Vec<u8>>;
+
+    fn create_index_buffer<T>(&mut self, indices: &[T]) -> Result<Self::IndexBuffer>
+    where
+        T: Index;
+
+    fn create_renderable(
+        &mut self,
+        vertex_buffers: &[(u32, &Self::VertexBuffer)],
+    ) -> Result<Self::Renderable>;
+
+    fn create_renderable_with_index(
+        &mut self,
+        vertex_buffers: &[(u32, &Self::VertexBuffer)],
+        index_buffer: &Self::IndexBuffer,
+    ) -> Result<Self::Renderable>;
+
+    /// Creates an offscreen framebuffer with a color attachment for every
+    /// format in `color_formats`, and a depth attachment if `depth_format`
+    /// is given.
+    ///
+    /// With a `sample_count` above 1 passes draw into multisampled
+    /// attachments, which are resolved into the textures of the render
+    /// target when the pass ends. Only color and depth are resolved, and
+    /// the software backend takes up to 16 samples.
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<Self::RenderTarget>;
+
+    /// Reads back what has been drawn to the screen, with the first row at
+    /// the top. Waits for the submitted frames to finish.
+    fn read_pixels(&mut self) -> Result<RgbaImage>;
+
+    /// Reads back the first mip level of the texture, see
+    /// `Texture::read_to_image`. Only the first layer of arrays, the front
+    /// slice of 3D textures and the +X face of cube maps is read.
+    fn read_texture(&mut self, texture: &Self::Texture) -> Result<RgbaImage>;
+}
+
+/// The textures a render target draws into, which can be bound with
+/// `Frame::use_texture` once the render target is no longer drawn to.
+pub trait Attachments {
+    type Texture;
+
+    fn color_textures(&self) -> &[Self::Texture];
+
+    fn depth_texture(&self) -> Option<&Self::Texture>;
+}
+
+/// The commands that can be recorded while rendering a single frame.
+///
+/// Every backend's `CommandBuffer` implements this trait. The frame is
+/// submitted when it is dropped.
+pub trait Frame {
+    type Device: Device<Frame = Self>;
+    /// The way a shader's texture is referred to: a uniform location on the
+    /// OpenGL based backends, an argument index on Metal.
+    type TextureLocation;
+
+    /// Makes every following `use_pipeline` start a new pass that clears
+    /// the color attachments to `clear_color`, the way drawing was cleared
+    /// before there were passes. `None` stops clearing.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>);
+
+    /// Makes the following passes draw into the render target, or back into
+    /// the screen when `None` is given. Ends the current pass, and resets the
+    /// viewport and the scissor rectangle.
+    fn set_render_target(&mut self, render_target: Option<&<Self::Device as Device>::RenderTarget>);
+
+    /// Starts a render pass drawing into the current render target, ending
+    /// the previous pass. The attachments are loaded, cleared or left
+    /// undefined as `desc` says, clears ignore the scissor rectangle. A
+    /// pipeline has to be used again before drawing, while the buffers and
+    /// textures that were bound stay bound.
+    fn begin_pass(&mut self, desc: &PassDesc);
+
+    /// Ends the current render pass, storing the attachments as its
+    /// `PassDesc` says and resolving multisampled ones. Using a pipeline
+    /// outside of a pass starts one that loads and stores every attachment.
+    /// The frame ends its last pass when it is dropped.
+    fn end_pass(&mut self);
+
+    /// Maps clip space to the rectangle for the following draws, which are
+    /// also clipped to it. `None` covers the whole screen or render target.
+    fn set_viewport(&mut self, viewport: Option<Rect>);
+
+    /// Discards the fragments of the following draws that lie outside of
+    /// the rectangle, `None` disables the scissor test. Clears are not
+    /// affected.
+    fn set_scissor(&mut self, scissor: Option<Rect>);
+
+    /// Uses the pipeline for the following draws of the current pass, or of
+    /// a new pass if there is none.
+    fn use_pipeline(&mut self, pipeline: &<Self::Device as Device>::Pipeline);
+
+    /// Ends the current pass and uses the compute pipeline for the following
+    /// dispatches. A pipeline has to be used again before drawing.
+    fn use_compute_pipeline(&mut self, pipeline: &<Self::Device as Device>::ComputePipeline);
+
+    /// Binds the vertex buffer as the storage buffer at `location` of the
+    /// following dispatches, which can both read and write it.
+    fn use_storage_vertex_buffer(
+        &mut self,
+        vertex_buffer: &<Self::Device as Device>::VertexBuffer,
+        location: u32,
+    );
+
+    /// Binds the storage buffer at `location` of the following draws and
+    /// dispatches, which can both read and write it.
+    fn use_storage_buffer(
+        &mut self,
+        storage_buffer: &<Self::Device as Device>::StorageBuffer,
+        location: u32,
+    );
+
+    /// Binds the first mip level of the texture as the storage image at
+    /// `location` of the following dispatches, which can load and store its
+    /// texels. Depth and sRGB textures cannot be bound, and the texture must
+    /// not be sampled by the same dispatches.
+    fn use_storage_texture(&mut self, texture: &<Self::Device as Device>::Texture, location: u32);
+
+    /// Runs `x * y * z` workgroups of the current compute pipeline.
+    fn dispatch(&mut self, x: u32, y: u32, z: u32);
+
+    /// Makes the writes of the previous dispatches visible to the following
+    /// dispatches and draws.
+    fn memory_barrier(&mut self);
+
+    fn use_uniform(
+        &mut self,
+        uniform_buffer: &<Self::Device as Device>::UniformBuffer,
+        location: u32,
+        binding: u32,
+    );
+
+    /// Binds the texture to the texture unit `binding` and points the
+    /// sampler uniform at `location` to it, so several textures can be
+    /// sampled at once by binding them to different units. Metal binds to
+    /// the texture index `location`, and the software backend to the
+    /// location shaders sample with, both ignore `binding`.
+    ///
+    /// Drawing into a render target while one of its textures is bound is
+    /// undefined on the GPU, the software backend panics.
+    fn use_texture(
+        &mut self,
+        texture: &<Self::Device as Device>::Texture,
+        location: Self::TextureLocation,
+        binding: u32,
+    );
+
+    fn draw(
+        &mut self,
+        renderable: &<Self::Device as Device>::Renderable,
+        vertex_count: usize,
+        instance_count: usize,
+    );
+
+    /// Draws `index_count` indices of the renderable's index buffer, starting
+    /// at the index `index_offset`. `base_vertex` is added to every index
+    /// before the vertex is fetched. Does nothing if the renderable was
+    /// created without an index buffer.
+    fn draw_indexed(
+        &mut self,
+        renderable: &<Self::Device as Device>::Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    );
+}
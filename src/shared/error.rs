@@ -0,0 +1,169 @@
+use crate::TextureRegion;
+use std::error;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Compute => write!(f, "compute"),
+        }
+    }
+}
+
+/// The error type returned by every backend.
+#[derive(Debug)]
+pub enum Error {
+    /// A shader failed to compile, with the log of the shader compiler.
+    ShaderCompile { stage: ShaderStage, log: String },
+    /// The shader stages could not be linked into a program or pipeline.
+    ProgramLink { log: String },
+    /// A shader function with the given name does not exist in the library.
+    MissingFunction(String),
+    /// The shader has no uniform block with the given name.
+    MissingUniform(String),
+    /// The shader has no texture with the given name.
+    MissingTexture(String),
+    /// The workgroup size a compute shader declares is not the one its
+    /// pipeline was created with.
+    WorkgroupSize {
+        declared: [u32; 3],
+        expected: [u32; 3],
+    },
+    /// An image could not be read or decoded.
+    ImageDecode(image::ImageError),
+    /// More data was written into a buffer than it was created to hold.
+    CapacityOverflow { capacity: usize, length: usize },
+    /// The pixel data does not have the size its dimensions and format need.
+    PixelDataSize { expected: usize, length: usize },
+    /// A texture was given more mip levels than its full chain has, or none.
+    MipLevelCount { count: usize, max: u32 },
+    /// A cube map face is not square, or not the size of the other faces.
+    CubeFaceSize { width: u32, height: u32 },
+    /// An image is not a horizontal cross of cube map faces, which is 4
+    /// faces wide and 3 faces high.
+    CubeCrossSize { width: u32, height: u32 },
+    /// The region is not inside the mip level of the texture, which is
+    /// `width` by `height` texels, or `0` by `0` if the level does not exist.
+    RegionOutOfBounds {
+        region: TextureRegion,
+        mip_level: u32,
+        width: u32,
+        height: u32,
+    },
+    /// The graphics context or device could not be created.
+    ContextCreation(String),
+    /// A shader or resource file could not be read.
+    Io(std::io::Error),
+    /// The operation is not available on this backend or format.
+    Unsupported(String),
+    /// An error reported by the underlying graphics API.
+    Backend(Box<dyn error::Error + Send + Sync>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ShaderCompile { stage, log } => write!(f, "compiling {} shader: {}", stage, log),
+            Error::ProgramLink { log } => write!(f, "linking shader program: {}", log),
+            Error::MissingFunction(name) => write!(f, "shader function \"{}\" not found", name),
+            Error::MissingUniform(name) => write!(f, "shader uniform \"{}\" not found", name),
+            Error::MissingTexture(name) => write!(f, "shader texture \"{}\" not found", name),
+            Error::WorkgroupSize { declared, expected } => write!(
+                f,
+                "compute shader declares a workgroup size of {:?}, expected {:?}",
+                declared, expected
+            ),
+            Error::ImageDecode(e) => write!(f, "loading image: {}", e),
+            Error::CapacityOverflow { capacity, length } => write!(
+                f,
+                "updating buffer: new data is longer than buffer capacity ({} > {} bytes)",
+                length, capacity
+            ),
+            Error::PixelDataSize { expected, length } => write!(
+                f,
+                "texture pixel data is {} bytes, expected {} bytes",
+                length, expected
+            ),
+            Error::MipLevelCount { count, max } => {
+                write!(f, "texture has {} mip levels, expected 1 to {}", count, max)
+            }
+            Error::CubeFaceSize { width, height } => write!(
+                f,
+                "cube map face is {}x{}, faces must be square and the same size",
+                width, height
+            ),
+            Error::CubeCrossSize { width, height } => write!(
+                f,
+                "{}x{} image is not a 4:3 cross of cube map faces",
+                width, height
+            ),
+            Error::RegionOutOfBounds {
+                region,
+                mip_level,
+                width,
+                height,
+            } => write!(
+                f,
+                "updating texture: {}x{} region at ({}, {}) is outside of mip level {} ({}x{})",
+                region.width, region.height, region.x, region.y, mip_level, width, height
+            ),
+            Error::ContextCreation(message) => write!(f, "creating context: {}", message),
+            Error::Io(e) => write!(f, "reading file: {}", e),
+            Error::Unsupported(operation) => write!(f, "{} is not supported", operation),
+            Error::Backend(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::ImageDecode(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Backend(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Error {
+        Error::ImageDecode(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "use-webgl")]
+impl From<wasm_bindgen::JsValue> for Error {
+    fn from(value: wasm_bindgen::JsValue) -> Error {
+        let message = match value.as_string() {
+            Some(message) => message,
+            None => format!("{:?}", value),
+        };
+
+        Error::Backend(message.into())
+    }
+}
+
+#[cfg(feature = "use-webgl")]
+impl From<Error> for wasm_bindgen::JsValue {
+    fn from(e: Error) -> wasm_bindgen::JsValue {
+        js_sys::Error::new(&e.to_string()).into()
+    }
+}
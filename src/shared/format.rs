@@ -0,0 +1,275 @@
+/// The layout of a single vertex attribute in a vertex buffer.
+///
+/// `Normalized` formats are read by the shader as floats, mapping the range
+/// of the integer type to `[0, 1]` (unsigned) or `[-1, 1]` (signed). The
+/// other integer formats are read by the shader as integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VertexFormat {
+    Float,
+    Float2,
+    Float3,
+    #[default]
+    Float4,
+    Half2,
+    Half4,
+    UChar4,
+    Char4,
+    UChar4Normalized,
+    Char4Normalized,
+    UShort2,
+    UShort4,
+    Short2,
+    Short4,
+    UShort2Normalized,
+    UShort4Normalized,
+    Short2Normalized,
+    Short4Normalized,
+    Int,
+    Int2,
+    Int3,
+    Int4,
+    UInt,
+    UInt2,
+    UInt3,
+    UInt4,
+}
+
+impl VertexFormat {
+    /// The number of components of the attribute.
+    pub fn components(self) -> usize {
+        match self {
+            VertexFormat::Float | VertexFormat::Int | VertexFormat::UInt => 1,
+            VertexFormat::Float2
+            | VertexFormat::Half2
+            | VertexFormat::UShort2
+            | VertexFormat::Short2
+            | VertexFormat::UShort2Normalized
+            | VertexFormat::Short2Normalized
+            | VertexFormat::Int2
+            | VertexFormat::UInt2 => 2,
+            VertexFormat::Float3 | VertexFormat::Int3 | VertexFormat::UInt3 => 3,
+            VertexFormat::Float4
+            | VertexFormat::Half4
+            | VertexFormat::UChar4
+            | VertexFormat::Char4
+            | VertexFormat::UChar4Normalized
+            | VertexFormat::Char4Normalized
+            | VertexFormat::UShort4
+            | VertexFormat::Short4
+            | VertexFormat::UShort4Normalized
+            | VertexFormat::Short4Normalized
+            | VertexFormat::Int4
+            | VertexFormat::UInt4 => 4,
+        }
+    }
+
+    /// The size in bytes of a single component.
+    pub fn component_size(self) -> usize {
+        match self {
+            VertexFormat::UChar4
+            | VertexFormat::Char4
+            | VertexFormat::UChar4Normalized
+            | VertexFormat::Char4Normalized => 1,
+            VertexFormat::Half2
+            | VertexFormat::Half4
+            | VertexFormat::UShort2
+            | VertexFormat::UShort4
+            | VertexFormat::Short2
+            | VertexFormat::Short4
+            | VertexFormat::UShort2Normalized
+            | VertexFormat::UShort4Normalized
+            | VertexFormat::Short2Normalized
+            | VertexFormat::Short4Normalized => 2,
+            VertexFormat::Float
+            | VertexFormat::Float2
+            | VertexFormat::Float3
+            | VertexFormat::Float4
+            | VertexFormat::Int
+            | VertexFormat::Int2
+            | VertexFormat::Int3
+            | VertexFormat::Int4
+            | VertexFormat::UInt
+            | VertexFormat::UInt2
+            | VertexFormat::UInt3
+            | VertexFormat::UInt4 => 4,
+        }
+    }
+
+    /// The size in bytes of the whole attribute.
+    pub fn size(self) -> usize {
+        self.components() * self.component_size()
+    }
+
+    /// Whether the attribute is read by the shader as integers rather than
+    /// floats.
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            VertexFormat::UChar4
+                | VertexFormat::Char4
+                | VertexFormat::UShort2
+                | VertexFormat::UShort4
+                | VertexFormat::Short2
+                | VertexFormat::Short4
+                | VertexFormat::Int
+                | VertexFormat::Int2
+                | VertexFormat::Int3
+                | VertexFormat::Int4
+                | VertexFormat::UInt
+                | VertexFormat::UInt2
+                | VertexFormat::UInt3
+                | VertexFormat::UInt4
+        )
+    }
+}
+
+/// Converts IEEE 754 half precision bits to a float.
+#[allow(dead_code)]
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2.0f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
+
+/// Converts a float to IEEE 754 half precision bits, rounding to nearest.
+#[allow(dead_code)]
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity and NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal, shift in the implicit leading bit.
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let round = (mantissa >> (shift - 1)) & 1;
+        sign | (half + round) as u16
+    } else {
+        let half = ((exponent as u32) << 10) | (mantissa >> 13);
+        let round = (mantissa >> 12) & 1;
+        sign | (half + round) as u16
+    }
+}
+
+/// The type of the indices stored in an index buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    UInt16,
+    UInt32,
+}
+
+impl IndexFormat {
+    /// The size in bytes of a single index.
+    pub fn size(self) -> usize {
+        match self {
+            IndexFormat::UInt16 => 2,
+            IndexFormat::UInt32 => 4,
+        }
+    }
+}
+
+/// An integer type that can be stored in an index buffer.
+pub trait Index: Copy + Into<u32> {
+    const FORMAT: IndexFormat;
+}
+
+impl Index for u16 {
+    const FORMAT: IndexFormat = IndexFormat::UInt16;
+}
+
+impl Index for u32 {
+    const FORMAT: IndexFormat = IndexFormat::UInt32;
+}
+
+/// The format of the texels of a texture or render target attachment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    /// 8 bit RGBA with the colors stored in sRGB, they are converted to
+    /// linear when sampled and back to sRGB when rendered to.
+    Rgba8Srgb,
+    R16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+    Depth24Stencil8,
+    Depth32F,
+}
+
+impl TextureFormat {
+    /// Whether the format holds depth (and possibly stencil) values rather
+    /// than colors.
+    pub fn is_depth(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F
+        )
+    }
+
+    /// Whether the format has a stencil component.
+    pub fn has_stencil(self) -> bool {
+        self == TextureFormat::Depth24Stencil8
+    }
+
+    /// Whether the color components are stored as floats.
+    pub fn is_float(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::R16F
+                | TextureFormat::Rgba16F
+                | TextureFormat::R32F
+                | TextureFormat::Rgba32F
+        )
+    }
+
+    /// The number of color components, depth formats have one.
+    pub fn components(self) -> usize {
+        match self {
+            TextureFormat::R8 | TextureFormat::R16F | TextureFormat::R32F => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgba8
+            | TextureFormat::Rgba8Srgb
+            | TextureFormat::Rgba16F
+            | TextureFormat::Rgba32F => 4,
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F => 1,
+        }
+    }
+
+    /// The size in bytes of a single texel. `Depth24Stencil8` texels are
+    /// packed into a `u32` with the depth in the upper 24 bits.
+    pub fn texel_size(self) -> usize {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgba8 | TextureFormat::Rgba8Srgb => 4,
+            TextureFormat::R16F => 2,
+            TextureFormat::Rgba16F => 8,
+            TextureFormat::R32F => 4,
+            TextureFormat::Rgba32F => 16,
+            TextureFormat::Depth24Stencil8 => 4,
+            TextureFormat::Depth32F => 4,
+        }
+    }
+}
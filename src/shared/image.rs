@@ -1,30 +1,617 @@
-use image;
-use image::RgbaImage;
+use crate::shared::{
+    f16_to_f32, f32_to_f16, Error, Result, TextureFormat, TextureKind, TextureRegion,
+};
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use std::convert::TryInto;
 use std::path::Path;
 
 pub(crate) type BgraImage = image::ImageBuffer<image::Bgra<u8>, Vec<u8>>;
 
 #[allow(dead_code)]
-pub(crate) fn load_rgba_image<P>(file_name: P) -> Result<RgbaImage, String>
+pub(crate) fn load_rgba_image<P>(file_name: P) -> Result<RgbaImage>
 where
     P: AsRef<Path>,
 {
-    let img = image::open(file_name).map_err(|e| format!("loading image: {}", e))?;
+    let img = image::open(file_name)?;
 
-    Ok(img.to_rgba())
+    Ok(img.to_rgba8())
 }
 
 #[allow(dead_code)]
-pub(crate) fn load_bgra_image<P>(file_name: P) -> Result<BgraImage, String>
+pub(crate) fn load_bgra_image<P>(file_name: P) -> Result<BgraImage>
 where
     P: AsRef<Path>,
 {
-    let img = image::open(file_name).map_err(|e| format!("loading image: {}", e))?;
+    let img = image::open(file_name)?;
 
-    Ok(img.to_bgra())
+    Ok(img.to_bgra8())
 }
 
 #[allow(dead_code)]
-pub(crate) fn is_power_of_2(value: u32) -> bool {
-    value == (1 << (31 - value.leading_zeros()))
+pub(crate) fn decode_image(bytes: &[u8]) -> Result<DynamicImage> {
+    Ok(image::load_from_memory(bytes)?)
+}
+
+/// Converts a decoded image to tightly packed texels of the format. Single
+/// and two channel formats take the luma, and the luma and alpha, of the
+/// image.
+#[allow(dead_code)]
+pub(crate) fn image_to_pixels(image: &DynamicImage, format: TextureFormat) -> Result<Vec<u8>> {
+    let pixels = match format {
+        TextureFormat::R8 => image.to_luma8().into_raw(),
+        TextureFormat::Rg8 => image.to_luma_alpha8().into_raw(),
+        TextureFormat::Rgba8 | TextureFormat::Rgba8Srgb => image.to_rgba8().into_raw(),
+        TextureFormat::R16F => luma_f32(image)
+            .iter()
+            .flat_map(|value| f32_to_f16(*value).to_ne_bytes())
+            .collect(),
+        TextureFormat::Rgba16F => rgba_f32(image)
+            .iter()
+            .flat_map(|value| f32_to_f16(*value).to_ne_bytes())
+            .collect(),
+        TextureFormat::R32F => luma_f32(image)
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect(),
+        TextureFormat::Rgba32F => rgba_f32(image)
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect(),
+        TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F => {
+            return Err(Error::Unsupported(
+                "creating depth textures from images".into(),
+            ))
+        }
+    };
+
+    Ok(pixels)
+}
+
+// `image` widens 8 bit channels to 16 bits by shifting them, which does not
+// map 255 to 65535, so 8 bit images are normalized from their own values.
+fn is_16_bit(image: &DynamicImage) -> bool {
+    matches!(
+        image,
+        DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_)
+    )
+}
+
+fn luma_f32(image: &DynamicImage) -> Vec<f32> {
+    if is_16_bit(image) {
+        image
+            .to_luma16()
+            .iter()
+            .map(|value| *value as f32 / 65535.0)
+            .collect()
+    } else {
+        image
+            .to_luma8()
+            .iter()
+            .map(|value| *value as f32 / 255.0)
+            .collect()
+    }
+}
+
+fn rgba_f32(image: &DynamicImage) -> Vec<f32> {
+    if is_16_bit(image) {
+        image
+            .to_rgba16()
+            .iter()
+            .map(|value| *value as f32 / 65535.0)
+            .collect()
+    } else {
+        image
+            .to_rgba8()
+            .iter()
+            .map(|value| *value as f32 / 255.0)
+            .collect()
+    }
+}
+
+/// Checks that tightly packed pixel data covers the whole texture.
+#[allow(dead_code)]
+pub(crate) fn check_pixel_data(
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    data: &[u8],
+) -> Result<()> {
+    let expected = width as usize * height as usize * format.texel_size();
+    if data.len() != expected {
+        return Err(Error::PixelDataSize {
+            expected,
+            length: data.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that every layer, slice or cube face is a whole `width` by
+/// `height` image, and joins them into the pixel data of the texture.
+#[allow(dead_code)]
+pub(crate) fn join_layers(
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    layers: &[&[u8]],
+) -> Result<Vec<u8>> {
+    for layer in layers {
+        check_pixel_data(width, height, format, layer)?;
+    }
+
+    Ok(layers.concat())
+}
+
+/// Checks that the region is inside the mip level of a `width` by `height`
+/// texture with `mip_level_count` levels, and that the pixel data holds
+/// exactly its tightly packed rows.
+#[allow(dead_code)]
+pub(crate) fn check_region(
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+    format: TextureFormat,
+    region: TextureRegion,
+    mip_level: u32,
+    data: &[u8],
+) -> Result<()> {
+    let (width, height) = if mip_level < mip_level_count {
+        mip_level_size(width, height, mip_level)
+    } else {
+        (0, 0)
+    };
+    if region.x as u64 + region.width as u64 > width as u64
+        || region.y as u64 + region.height as u64 > height as u64
+        || mip_level >= mip_level_count
+    {
+        return Err(Error::RegionOutOfBounds {
+            region,
+            mip_level,
+            width,
+            height,
+        });
+    }
+
+    check_pixel_data(region.width, region.height, format, data)
+}
+
+/// Regions only address 2D textures, which have a single layer.
+#[allow(dead_code)]
+pub(crate) fn check_update_kind(kind: TextureKind) -> Result<()> {
+    if kind != TextureKind::D2 {
+        return Err(Error::Unsupported(
+            "updating array, 3D and cube textures".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Render targets take a power of two samples per pixel.
+#[allow(dead_code)]
+pub(crate) fn check_sample_count(sample_count: u32) -> Result<()> {
+    if !sample_count.is_power_of_two() {
+        return Err(Error::Unsupported(format!(
+            "render targets with {} samples per pixel",
+            sample_count
+        )));
+    }
+
+    Ok(())
+}
+
+/// The number of mip levels of a full chain down to 1 by 1.
+#[allow(dead_code)]
+pub(crate) fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// The size of a mip level, each level halves the size of the one before
+/// down to 1 texel.
+#[allow(dead_code)]
+pub(crate) fn mip_level_size(width: u32, height: u32, mip_level: u32) -> (u32, u32) {
+    ((width >> mip_level).max(1), (height >> mip_level).max(1))
+}
+
+/// The number of mip levels of a full chain of a texture of the kind, 3D
+/// textures also halve their depth.
+#[allow(dead_code)]
+pub(crate) fn kind_mip_level_count(kind: TextureKind, width: u32, height: u32) -> u32 {
+    match kind {
+        TextureKind::D3 { depth } => mip_level_count(width.max(depth), height),
+        _ => mip_level_count(width, height),
+    }
+}
+
+/// The number of 2D images in a mip level of a texture of the kind, which
+/// are its array layers, 3D slices or cube faces.
+#[allow(dead_code)]
+pub(crate) fn layer_count(kind: TextureKind, mip_level: u32) -> u32 {
+    match kind {
+        TextureKind::D2 => 1,
+        TextureKind::D2Array { layers } => layers,
+        TextureKind::D3 { depth } => (depth >> mip_level).max(1),
+        TextureKind::Cube => 6,
+    }
+}
+
+/// Checks that there are between one level and a full chain of mip levels,
+/// each holding exactly the tightly packed texels of all of its layers, one
+/// layer after the other.
+#[allow(dead_code)]
+pub(crate) fn check_mip_levels(
+    kind: TextureKind,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    levels: &[&[u8]],
+) -> Result<()> {
+    let max = kind_mip_level_count(kind, width, height);
+    if levels.is_empty() || levels.len() > max as usize {
+        return Err(Error::MipLevelCount {
+            count: levels.len(),
+            max,
+        });
+    }
+    if kind == TextureKind::Cube && width != height {
+        return Err(Error::CubeFaceSize { width, height });
+    }
+
+    for (mip_level, data) in levels.iter().enumerate() {
+        let (width, height) = mip_level_size(width, height, mip_level as u32);
+        let layers = layer_count(kind, mip_level as u32);
+        check_pixel_data(width, height * layers, format, data)?;
+    }
+    Ok(())
+}
+
+/// Builds the full mip chain of a texture on the CPU, for formats the GPU
+/// can not generate mipmaps for. Each level is filtered down from the one
+/// before with a box filter like the GPU's, sRGB colors in linear space.
+/// Array layers and cube faces are filtered on their own, 3D textures
+/// across their slices as well. Returns the tightly packed texels of every level after
+/// the first.
+#[allow(dead_code)]
+pub(crate) fn generate_mip_levels(
+    kind: TextureKind,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    data: &[u8],
+) -> Result<Vec<Vec<u8>>> {
+    if format.is_depth() {
+        return Err(Error::Unsupported(
+            "generating mip levels of depth textures".into(),
+        ));
+    }
+
+    let layer_size = width as usize * height as usize * format.texel_size();
+    let mut layers: Vec<Rgba32FImage> = (0..layer_count(kind, 0) as usize)
+        .map(|layer| {
+            let data = &data[layer * layer_size..(layer + 1) * layer_size];
+            Rgba32FImage::from_raw(width, height, decode_texels(format, data)).unwrap()
+        })
+        .collect();
+
+    let mut levels = Vec::new();
+    for mip_level in 1..kind_mip_level_count(kind, width, height) {
+        let (width, height) = mip_level_size(width, height, mip_level);
+        layers = downsample(kind, &layers, width, height, layer_count(kind, mip_level));
+
+        levels.push(
+            layers
+                .iter()
+                .flat_map(|layer| encode_texels(format, layer))
+                .collect(),
+        );
+    }
+    Ok(levels)
+}
+
+/// Loads the six faces of a cube map as RGBA, checking that they are
+/// square and all the same size. Returns the size of a face and the faces
+/// one after the other.
+#[allow(dead_code)]
+pub(crate) fn load_cube_faces(image_paths: &[&str; 6]) -> Result<(u32, Vec<u8>)> {
+    let mut size = 0;
+    let mut faces = Vec::new();
+    for (face, image_path) in image_paths.iter().enumerate() {
+        let img = load_rgba_image(image_path)?;
+        if img.width() != img.height() || (face > 0 && img.width() != size) {
+            return Err(Error::CubeFaceSize {
+                width: img.width(),
+                height: img.height(),
+            });
+        }
+
+        size = img.width();
+        faces.extend_from_slice(&img);
+    }
+
+    Ok((size, faces))
+}
+
+/// Cuts the faces of a cube map out of a horizontal cross, which has +Y
+/// above and -Y below +Z on a row of -X, +Z, +X and -Z. Returns the size of a
+/// face and the faces one after the other.
+#[allow(dead_code)]
+pub(crate) fn cube_faces_from_cross(img: &RgbaImage) -> Result<(u32, Vec<u8>)> {
+    let size = img.width() / 4;
+    if size == 0 || img.width() != size * 4 || img.height() != size * 3 {
+        return Err(Error::CubeCrossSize {
+            width: img.width(),
+            height: img.height(),
+        });
+    }
+
+    let mut faces = Vec::new();
+    for (column, row) in CUBE_CROSS_FACES.iter() {
+        let face = image::imageops::crop_imm(img, column * size, row * size, size, size);
+        faces.extend_from_slice(&face.to_image());
+    }
+    Ok((size, faces))
+}
+
+/// The column and row of every face in a horizontal cross, in the order
+/// +X, -X, +Y, -Y, +Z, -Z.
+#[allow(dead_code)]
+pub(crate) const CUBE_CROSS_FACES: [(u32, u32); 6] =
+    [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+
+type Rgba32FImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+/// Halves the layers of a mip level by averaging every 2×2 block of texels,
+/// and every 2×2×2 block across the slices of 3D textures, the box filter
+/// `glGenerateMipmap` and Metal's `generateMipmaps` use. Sizes of 1 are
+/// kept, and odd sizes leave out their last column, row or slice.
+fn downsample(
+    kind: TextureKind,
+    layers: &[Rgba32FImage],
+    width: u32,
+    height: u32,
+    layer_count: u32,
+) -> Vec<Rgba32FImage> {
+    let (source_width, source_height) = layers[0].dimensions();
+    let block = |coord: u32, size: u32| (2 * coord..2 * coord + 2).filter(move |c| *c < size);
+
+    (0..layer_count)
+        .map(|layer| {
+            let sources: Vec<&Rgba32FImage> = match kind {
+                TextureKind::D3 { .. } => block(layer, layers.len() as u32)
+                    .map(|slice| &layers[slice as usize])
+                    .collect(),
+                _ => vec![&layers[layer as usize]],
+            };
+
+            Rgba32FImage::from_fn(width, height, |x, y| {
+                let mut sum = [0.0; 4];
+                let mut count = 0.0;
+                for source in &sources {
+                    for sy in block(y, source_height) {
+                        for sx in block(x, source_width) {
+                            let texel = source.get_pixel(sx, sy);
+                            for (sum, component) in sum.iter_mut().zip(texel.0.iter()) {
+                                *sum += component;
+                            }
+                            count += 1.0;
+                        }
+                    }
+                }
+                Rgba(sum.map(|sum| sum / count))
+            })
+        })
+        .collect()
+}
+
+/// Decodes tightly packed texels to 4 floats each. Missing components read
+/// as `(0, 0, 0, 1)`, sRGB colors are converted to linear and depth formats
+/// keep the depth in the first component.
+#[allow(dead_code)]
+pub(crate) fn decode_texels(format: TextureFormat, data: &[u8]) -> Vec<f32> {
+    let mut texels = Vec::with_capacity(data.len() / format.texel_size() * 4);
+    for texel in data.chunks_exact(format.texel_size()) {
+        let mut value = [0.0, 0.0, 0.0, 1.0];
+        match format {
+            TextureFormat::R8 | TextureFormat::Rg8 | TextureFormat::Rgba8 => {
+                for (component, byte) in value.iter_mut().zip(texel) {
+                    *component = *byte as f32 / 255.0;
+                }
+            }
+            TextureFormat::Rgba8Srgb => {
+                for (i, (component, byte)) in value.iter_mut().zip(texel).enumerate() {
+                    *component = if i < 3 {
+                        srgb_to_linear(*byte as f32 / 255.0)
+                    } else {
+                        *byte as f32 / 255.0
+                    };
+                }
+            }
+            TextureFormat::R16F | TextureFormat::Rgba16F => {
+                for (component, bytes) in value.iter_mut().zip(texel.chunks_exact(2)) {
+                    *component = f16_to_f32(u16::from_ne_bytes(bytes.try_into().unwrap()));
+                }
+            }
+            TextureFormat::R32F | TextureFormat::Rgba32F | TextureFormat::Depth32F => {
+                for (component, bytes) in value.iter_mut().zip(texel.chunks_exact(4)) {
+                    *component = f32::from_ne_bytes(bytes.try_into().unwrap());
+                }
+            }
+            TextureFormat::Depth24Stencil8 => {
+                let packed = u32::from_ne_bytes(texel.try_into().unwrap());
+                value[0] = (packed >> 8) as f32 / 0xff_ffff as f32;
+            }
+        }
+        texels.extend_from_slice(&value);
+    }
+    texels
+}
+
+/// The inverse of `decode_texels`, the stencil of `Depth24Stencil8` is 0.
+#[allow(dead_code)]
+pub(crate) fn encode_texels(format: TextureFormat, texels: &[f32]) -> Vec<u8> {
+    let unorm8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let mut data = Vec::with_capacity(texels.len() / 4 * format.texel_size());
+    for texel in texels.chunks_exact(4) {
+        let texel = &texel[..format.components()];
+        match format {
+            TextureFormat::R8 | TextureFormat::Rg8 | TextureFormat::Rgba8 => {
+                data.extend(texel.iter().map(|component| unorm8(*component)));
+            }
+            TextureFormat::Rgba8Srgb => {
+                data.extend(
+                    texel[..3]
+                        .iter()
+                        .map(|component| unorm8(linear_to_srgb(*component))),
+                );
+                data.push(unorm8(texel[3]));
+            }
+            TextureFormat::R16F | TextureFormat::Rgba16F => {
+                for component in texel {
+                    data.extend_from_slice(&f32_to_f16(*component).to_ne_bytes());
+                }
+            }
+            TextureFormat::R32F | TextureFormat::Rgba32F | TextureFormat::Depth32F => {
+                for component in texel {
+                    data.extend_from_slice(&component.to_ne_bytes());
+                }
+            }
+            TextureFormat::Depth24Stencil8 => {
+                let depth = (texel[0].clamp(0.0, 1.0) * 0xff_ffff as f32).round() as u32;
+                data.extend_from_slice(&(depth << 8).to_ne_bytes());
+            }
+        }
+    }
+    data
+}
+
+#[allow(dead_code)]
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[allow(dead_code)]
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts float texels with `components` components, clamped to `[0, 1]`,
+/// to an 8 bit RGBA image. Missing components read as `(0, 0, 0, 1)`.
+#[allow(dead_code)]
+pub(crate) fn rgba_image_from_f32(
+    width: u32,
+    height: u32,
+    components: usize,
+    texels: &[f32],
+) -> RgbaImage {
+    let mut img = RgbaImage::new(width, height);
+    for (pixel, texel) in img.pixels_mut().zip(texels.chunks_exact(components)) {
+        let mut value = [0.0, 0.0, 0.0, 1.0];
+        value[..components].copy_from_slice(texel);
+        for (channel, component) in pixel.0.iter_mut().zip(&value) {
+            *channel = (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    img
+}
+
+/// Converts depth values to a grayscale image, near is black.
+#[allow(dead_code)]
+pub(crate) fn rgba_image_from_depth(width: u32, height: u32, depths: &[f32]) -> RgbaImage {
+    let texels: Vec<f32> = depths
+        .iter()
+        .flat_map(|depth| vec![*depth, *depth, *depth, 1.0])
+        .collect();
+
+    rgba_image_from_f32(width, height, 4, &texels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_are_checked_one_by_one() {
+        // The total is right for two 1x1 layers, but the first layer holds
+        // both texels.
+        let layers: [&[u8]; 2] = [&[1, 2, 3, 4, 5, 6, 7, 8], &[]];
+        let result = join_layers(1, 1, TextureFormat::Rgba8, &layers);
+
+        assert!(matches!(
+            result,
+            Err(Error::PixelDataSize {
+                expected: 4,
+                length: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn mip_chain_of_an_odd_size() {
+        let data: Vec<u8> = [10, 20, 30, 255].repeat(5 * 3);
+        let levels =
+            generate_mip_levels(TextureKind::D2, 5, 3, TextureFormat::Rgba8, &data).unwrap();
+
+        // 5 by 3, 2 by 1 and 1 by 1.
+        assert_eq!(
+            levels,
+            vec![[10, 20, 30, 255].repeat(2), vec![10, 20, 30, 255]]
+        );
+    }
+
+    #[test]
+    fn mip_chain_keeps_negative_floats() {
+        let data: Vec<u8> = [-1.0f32, 0.5, 0.0, 1.0]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<u8>>()
+            .repeat(3 * 3);
+        let levels =
+            generate_mip_levels(TextureKind::D2, 3, 3, TextureFormat::Rgba32F, &data).unwrap();
+
+        assert_eq!(levels.len(), 1);
+        assert_eq!(
+            decode_texels(TextureFormat::Rgba32F, &levels[0]),
+            [-1.0, 0.5, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn mip_chain_of_a_3d_srgb_texture() {
+        // Two black slices followed by two white ones.
+        let black = [0, 0, 0, 255].repeat(2 * 2);
+        let white = [255; 4].repeat(2 * 2);
+        let data = [black.clone(), black, white.clone(), white].concat();
+        let levels = generate_mip_levels(
+            TextureKind::D3 { depth: 4 },
+            2,
+            2,
+            TextureFormat::Rgba8Srgb,
+            &data,
+        )
+        .unwrap();
+
+        // 1 by 1 by 2, then 1 by 1 by 1. Each slice of the first level
+        // averages two slices of the same color, the last level averages
+        // black and white in linear space.
+        assert_eq!(
+            levels,
+            vec![
+                vec![0, 0, 0, 255, 255, 255, 255, 255],
+                vec![188, 188, 188, 255],
+            ]
+        );
+    }
 }
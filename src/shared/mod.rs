@@ -1,25 +1,583 @@
+mod device;
+mod error;
+mod format;
 pub(crate) mod image;
 
-#[derive(Clone, Copy)]
-pub enum TextureFilter {
+pub use device::*;
+pub use error::*;
+pub use format::*;
+
+/// How texture coordinates outside of `[0, 1]` are mapped to texels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressMode {
+    #[default]
+    ClampToEdge,
+    Repeat,
+    MirrorRepeat,
+    /// Reads `SamplerDesc::border_color` outside of the texture. Not
+    /// supported by WebGL.
+    ClampToBorder,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    #[default]
     Nearest,
     Linear,
-    MipMap,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BorderColor {
+    #[default]
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+/// Passes when `reference <op> stored value` holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+/// How a texture is sampled. The default samples the nearest texel of the
+/// first mip level, clamping coordinates to the edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerDesc {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub address_mode_w: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    /// How mip levels are blended, `None` only samples the first level. If
+    /// this is set, the full mip chain is generated when a texture is created
+    /// from a single image, except for depth textures.
+    pub mip_filter: Option<FilterMode>,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    /// `1` disables anisotropic filtering. Clamped to what the GPU supports.
+    pub max_anisotropy: u16,
+    /// Turns the sampler into a comparison sampler for depth textures,
+    /// which returns the fraction of texels passing the comparison.
+    pub compare: Option<CompareFunction>,
+    pub border_color: BorderColor,
+}
+
+impl SamplerDesc {
+    /// Bilinear filtering of the first mip level.
+    pub fn linear() -> SamplerDesc {
+        SamplerDesc {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..SamplerDesc::default()
+        }
+    }
+
+    /// Bilinear filtering blended between mip levels.
+    pub fn trilinear() -> SamplerDesc {
+        SamplerDesc {
+            mip_filter: Some(FilterMode::Linear),
+            ..SamplerDesc::linear()
+        }
+    }
+}
+
+impl Default for SamplerDesc {
+    fn default() -> SamplerDesc {
+        SamplerDesc {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mip_filter: None,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            max_anisotropy: 1,
+            compare: None,
+            border_color: BorderColor::TransparentBlack,
+        }
+    }
+}
+
+/// How the vertices of a draw are assembled into primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    Points,
+    Lines,
+    LineStrip,
+    Triangles,
+    #[default]
+    TriangleStrip,
+}
+
+/// The shape of a texture, which decides how shaders sample it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureKind {
+    #[default]
+    D2,
+    /// `layers` 2D images of the same size, sampled by layer index.
+    D2Array { layers: u32 },
+    /// A volume of `depth` slices, sampled with 3 coordinates.
+    D3 { depth: u32 },
+    /// Six square faces in the order +X, -X, +Y, -Y, +Z, -Z, sampled with a
+    /// direction.
+    Cube,
+}
+
+/// A rectangle of texels in a texture, `(x, y)` is the top left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A rectangle of pixels in the screen or a render target, `(x, y)` is the
+/// top left corner on every backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What the source, the fragment color, and the destination, the color in
+/// the attachment, are multiplied with before they are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstColor,
+    OneMinusDstColor,
+    DstAlpha,
+    OneMinusDstAlpha,
+    /// `min(src_alpha, 1 - dst_alpha)` for color, `1` for alpha. Only
+    /// valid as a source factor.
+    SrcAlphaSaturated,
+    /// The blend constant of the pipeline.
+    Constant,
+    OneMinusConstant,
+    /// The alpha of the blend constant.
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+}
+
+/// How the weighted source and destination are combined. `Min` and `Max`
+/// ignore the factors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendOperation {
+    Add,
+    /// `source - destination`.
+    Subtract,
+    /// `destination - source`.
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+/// The blend equation of the color or alpha channel:
+/// `operation(source * src_factor, destination * dst_factor)`. The default
+/// writes the source as it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlendComponent {
+    pub src_factor: BlendFactor,
+    pub dst_factor: BlendFactor,
+    pub operation: BlendOperation,
+}
+
+impl Default for BlendComponent {
+    fn default() -> BlendComponent {
+        BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
+        }
+    }
+}
+
+/// How fragments are blended into the color attachments, with separate
+/// equations for the color and the alpha channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlendState {
+    pub color: BlendComponent,
+    pub alpha: BlendComponent,
+}
+
+impl BlendState {
+    /// Blends colors that are not premultiplied by their alpha over the
+    /// destination.
+    pub fn alpha() -> BlendState {
+        BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+        }
+    }
+
+    /// Blends colors that are premultiplied by their alpha over the
+    /// destination.
+    pub fn premultiplied_alpha() -> BlendState {
+        BlendState::uniform(
+            BlendFactor::One,
+            BlendFactor::OneMinusSrcAlpha,
+            BlendOperation::Add,
+        )
+    }
+
+    /// Adds the source to the destination.
+    pub fn additive() -> BlendState {
+        BlendState::uniform(BlendFactor::One, BlendFactor::One, BlendOperation::Add)
+    }
+
+    /// Multiplies the destination with the source.
+    pub fn multiply() -> BlendState {
+        BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::DstColor,
+                dst_factor: BlendFactor::Zero,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent {
+                src_factor: BlendFactor::DstAlpha,
+                dst_factor: BlendFactor::Zero,
+                operation: BlendOperation::Add,
+            },
+        }
+    }
+
+    /// Keeps the smaller of the source and the destination.
+    pub fn min() -> BlendState {
+        BlendState::uniform(BlendFactor::One, BlendFactor::One, BlendOperation::Min)
+    }
+
+    /// Keeps the larger of the source and the destination.
+    pub fn max() -> BlendState {
+        BlendState::uniform(BlendFactor::One, BlendFactor::One, BlendOperation::Max)
+    }
+
+    /// The same equation for color and alpha.
+    fn uniform(
+        src_factor: BlendFactor,
+        dst_factor: BlendFactor,
+        operation: BlendOperation,
+    ) -> BlendState {
+        let component = BlendComponent {
+            src_factor,
+            dst_factor,
+            operation,
+        };
+        BlendState {
+            color: component,
+            alpha: component,
+        }
+    }
+}
+
+/// The channels of the color attachments that are written to. The default
+/// writes all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorWriteMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl Default for ColorWriteMask {
+    fn default() -> ColorWriteMask {
+        ColorWriteMask {
+            red: true,
+            green: true,
+            blue: true,
+            alpha: true,
+        }
+    }
+}
+
+/// How fragments are written into one color attachment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColorAttachmentState {
+    /// `None` overwrites the attachment.
+    pub blend: Option<BlendState>,
+    pub write_mask: ColorWriteMask,
+}
+
+/// Which triangles are discarded before they are rasterized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CullMode {
+    #[default]
+    None,
+    Front,
+    Back,
+}
+
+/// The winding of the triangles that face the viewer, as seen on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrontFace {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+/// How triangles are rasterized. `Line` only draws their edges and is not
+/// supported on WebGL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    Line,
+}
+
+/// How the triangles of a pipeline are rasterized. The default draws both
+/// sides of every triangle, filled and without a depth bias.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RasterizerState {
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    /// Added to the depth of every fragment of a triangle, in units of the
+    /// smallest difference the depth format can tell apart.
+    pub depth_bias: f32,
+    /// Scales the steepest depth slope of a triangle, which is then added to
+    /// the depth of its fragments along with `depth_bias`.
+    pub depth_bias_slope_scale: f32,
+    /// Clamps the depth of fragments to the depth range instead of clipping
+    /// triangles against the near and far planes. Not supported on WebGL.
+    pub depth_clamp: bool,
+}
+
+/// What happens to the stencil value of a pixel, depending on the outcome
+/// of the stencil and depth tests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StencilOperation {
+    #[default]
+    Keep,
+    Zero,
+    /// Writes the reference value.
+    Replace,
+    IncrementClamp,
+    DecrementClamp,
+    Invert,
+    IncrementWrap,
+    DecrementWrap,
+}
+
+/// The stencil test and operations of one side of the triangles. The
+/// default always passes and keeps the stencil value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StencilFaceState {
+    pub compare: CompareFunction,
+    /// Applied when the stencil test fails.
+    pub fail_op: StencilOperation,
+    /// Applied when the stencil test passes but the depth test fails.
+    pub depth_fail_op: StencilOperation,
+    /// Applied when both tests pass.
+    pub pass_op: StencilOperation,
+}
+
+impl Default for StencilFaceState {
+    fn default() -> StencilFaceState {
+        StencilFaceState {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        }
+    }
+}
+
+/// The stencil test of a pipeline, which needs a render target with a
+/// `Depth24Stencil8` depth attachment. Points and lines use the front face
+/// state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+    /// The value the stencil is compared with, and written by
+    /// `StencilOperation::Replace`.
+    pub reference: u8,
+    /// Masks both the reference and the stencil value before they are
+    /// compared.
+    pub read_mask: u8,
+    /// The bits of the stencil value that the operations change.
+    pub write_mask: u8,
+}
+
+impl Default for StencilState {
+    fn default() -> StencilState {
+        StencilState {
+            front: StencilFaceState::default(),
+            back: StencilFaceState::default(),
+            reference: 0,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct PipelineDesc<'a> {
-    pub depth_test: bool,
+    pub topology: PrimitiveTopology,
+    /// The formats of the color attachments of the render target the
+    /// pipeline draws into. Leave empty to draw to the screen. Only Metal
+    /// needs to know them up front.
+    pub color_formats: &'a [TextureFormat],
+    /// The format of the depth attachment of the render target the pipeline
+    /// draws into, see `color_formats`.
+    pub depth_format: Option<TextureFormat>,
+    /// The sample count of the render target the pipeline draws into, see
+    /// `color_formats`.
+    pub sample_count: u32,
+    /// Passes when `fragment depth <op> stored depth` holds. `None` disables
+    /// the depth test, and with it writing the depth.
+    pub depth_compare: Option<CompareFunction>,
     pub depth_write: bool,
-    pub alpha_blend: bool,
+    /// `None` disables the stencil test.
+    pub stencil: Option<StencilState>,
+    /// How fragments are blended into the color attachments, `None`
+    /// overwrites them.
+    pub blend: Option<BlendState>,
+    /// The color that `BlendFactor::Constant` and friends stand for.
+    pub blend_constant: [f32; 4],
+    /// Covers the samples of a pixel in proportion to the alpha of the
+    /// first color output. Only has an effect when multisampling.
+    pub alpha_to_coverage: bool,
+    pub color_write_mask: ColorWriteMask,
+    /// The blend state and write mask of each color attachment, for passes
+    /// drawing into several at once. Attachments past the end use `blend`
+    /// and `color_write_mask`. Different states need OpenGL 4.0, WebGL
+    /// rejects them.
+    pub color_attachments: &'a [ColorAttachmentState],
+    pub rasterizer: RasterizerState,
     pub bindings: &'a [Binding],
 }
 
-#[derive(Clone, Copy)]
+impl<'a> Default for PipelineDesc<'a> {
+    fn default() -> PipelineDesc<'a> {
+        PipelineDesc {
+            topology: PrimitiveTopology::default(),
+            color_formats: &[],
+            depth_format: None,
+            sample_count: 1,
+            depth_compare: None,
+            depth_write: false,
+            stencil: None,
+            blend: None,
+            blend_constant: [0.0; 4],
+            alpha_to_coverage: false,
+            color_write_mask: ColorWriteMask::default(),
+            color_attachments: &[],
+            rasterizer: RasterizerState::default(),
+            bindings: &[],
+        }
+    }
+}
+
+impl PipelineDesc<'_> {
+    /// The state of the color attachment `index`.
+    pub fn color_attachment(&self, index: usize) -> ColorAttachmentState {
+        self.color_attachments
+            .get(index)
+            .copied()
+            .unwrap_or(ColorAttachmentState {
+                blend: self.blend,
+                write_mask: self.color_write_mask,
+            })
+    }
+}
+
+#[derive(Default, Clone, Copy)]
 pub struct Binding {
     pub attribute: u32,
     pub buffer: u32,
     pub offset: usize,
     pub stride: usize,
-    pub instanced: bool,
+    pub format: VertexFormat,
+    /// `0` advances the attribute for every vertex, any other value advances
+    /// it once every `instance_step_rate` instances.
+    pub instance_step_rate: u32,
+}
+
+/// What happens to an attachment at the start of a render pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoadAction<T> {
+    /// Keeps what the attachment holds.
+    #[default]
+    Load,
+    /// Clears the attachment to the value.
+    Clear(T),
+    /// Leaves the contents undefined, which saves reading them on tiled
+    /// GPUs. The OpenGL based backends and the software backend keep them.
+    DontCare,
+}
+
+/// What happens to an attachment at the end of a render pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StoreAction {
+    #[default]
+    Store,
+    /// Leaves the contents undefined after the pass, for attachments that
+    /// are only needed while drawing. The OpenGL based backends and the
+    /// software backend keep them. Multisampled attachments are resolved
+    /// either way.
+    DontCare,
+}
+
+/// The load and store actions of one attachment. The default loads and
+/// stores it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Operations<T> {
+    pub load: LoadAction<T>,
+    pub store: StoreAction,
+}
+
+impl<T> Operations<T> {
+    /// Clears the attachment to `value` and stores it.
+    pub fn clear(value: T) -> Operations<T> {
+        Operations {
+            load: LoadAction::Clear(value),
+            store: StoreAction::Store,
+        }
+    }
+}
+
+/// How the attachments are treated by a render pass, see
+/// `Frame::begin_pass`. The default loads and stores every attachment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PassDesc<'a> {
+    /// One entry per color attachment of the render target, the first one
+    /// is used for the screen. Attachments without an entry are loaded and
+    /// stored.
+    pub color: &'a [Operations<[f32; 4]>],
+    pub depth: Operations<f32>,
+    /// Only used with `Depth24Stencil8` depth attachments.
+    pub stencil: Operations<u8>,
+}
+
+impl PassDesc<'_> {
+    /// The operations of the color attachment `index`.
+    pub fn color_operations(&self, index: usize) -> Operations<[f32; 4]> {
+        self.color.get(index).copied().unwrap_or_default()
+    }
 }
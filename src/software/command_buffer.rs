@@ -0,0 +1,469 @@
+use crate::shared::f16_to_f32;
+use crate::software::err::Result;
+use crate::software::internal::*;
+use crate::software::*;
+use crate::{Frame, Operations, PassDesc, PrimitiveTopology, Rect, SamplerDesc, VertexFormat};
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::rc::Rc;
+
+pub struct CommandBuffer {
+    pub(crate) screen: Framebuffer,
+    pub(crate) framebuffer: Framebuffer,
+    pub(crate) viewport: Option<Rect>,
+    pub(crate) scissor: Option<Rect>,
+    pub(crate) pipeline: Option<Pipeline>,
+    pub(crate) uniforms: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) textures: Vec<(u32, Rc<RefCell<Surface>>, SamplerDesc)>,
+    pub(crate) compute_pipeline: Option<ComputePipeline>,
+    /// Storage buffers, and vertex buffers bound as storage buffers.
+    pub(crate) storage: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) storage_textures: Vec<(u32, Rc<RefCell<Surface>>)>,
+    pub(crate) in_pass: bool,
+    /// See `set_clear_color`.
+    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
+}
+
+impl CommandBuffer {
+    pub(crate) fn new(screen: &Framebuffer) -> Result<CommandBuffer> {
+        Ok(CommandBuffer {
+            screen: screen.clone(),
+            framebuffer: screen.clone(),
+            viewport: None,
+            scissor: None,
+            pipeline: None,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            compute_pipeline: None,
+            storage: Vec::new(),
+            storage_textures: Vec::new(),
+            in_pass: false,
+            clear_color: None,
+        })
+    }
+
+    pub fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        self.end_pass();
+
+        self.framebuffer = match render_target {
+            Some(render_target) => render_target.framebuffer.clone(),
+            None => self.screen.clone(),
+        };
+        self.viewport = None;
+        self.scissor = None;
+    }
+
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        self.viewport = viewport;
+    }
+
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        self.scissor = scissor;
+    }
+
+    /// Deprecated, see `Frame::set_clear_color`.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
+    pub fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        self.clear_color = clear_color;
+    }
+
+    /// Clears the attachments, the other load actions keep them.
+    pub fn begin_pass(&mut self, desc: &PassDesc) {
+        self.end_pass();
+
+        self.framebuffer.clear(desc);
+        self.in_pass = true;
+    }
+
+    /// Resolves the attachments of a multisampled render target, the
+    /// attachments are always stored.
+    pub fn end_pass(&mut self) {
+        if !self.in_pass {
+            return;
+        }
+        self.in_pass = false;
+
+        self.framebuffer.resolve();
+    }
+
+    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        if let Some((red, green, blue, alpha)) = self.clear_color {
+            let color =
+                vec![Operations::clear([red, green, blue, alpha]); self.framebuffer.color.len()];
+            self.begin_pass(&PassDesc {
+                color: &color,
+                ..Default::default()
+            });
+        } else if !self.in_pass {
+            self.begin_pass(&PassDesc::default());
+        }
+
+        self.pipeline = Some(pipeline.clone());
+    }
+
+    pub fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        self.end_pass();
+
+        self.compute_pipeline = Some(compute_pipeline.clone());
+    }
+
+    pub fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, _binding: u32) {
+        self.uniforms.retain(|(i, _buffer)| *i != location);
+        self.uniforms
+            .push((location, Rc::clone(&uniform_buffer.buffer)));
+    }
+
+    pub fn use_texture(&mut self, texture: &Texture, location: u32, _binding: u32) {
+        self.textures
+            .retain(|(i, _surface, _sampler)| *i != location);
+        self.textures
+            .push((location, Rc::clone(&texture.surface), texture.sampler));
+    }
+
+    pub fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        self.storage.retain(|(i, _buffer)| *i != location);
+        self.storage
+            .push((location, Rc::clone(&vertex_buffer.buffer)));
+    }
+
+    pub fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        self.storage.retain(|(i, _buffer)| *i != location);
+        self.storage
+            .push((location, Rc::clone(&storage_buffer.buffer)));
+    }
+
+    /// Only the first mip level is bound, the others are left as they are.
+    pub fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        self.storage_textures.retain(|(i, _surface)| *i != location);
+        self.storage_textures
+            .push((location, Rc::clone(&texture.surface)));
+    }
+
+    /// Panics if one of the bound textures is an attachment of the render
+    /// target, which the GPU would read and write at the same time.
+    pub fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        let vertex_ids: Vec<usize> = (0..vertex_count).collect();
+        self.draw_vertices(renderable, &vertex_ids, instance_count);
+    }
+
+    /// Panics like `draw`.
+    pub fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        let indices = match &renderable.index_buffer {
+            Some(indices) => indices,
+            None => return,
+        };
+
+        let vertex_ids: Vec<usize> = indices
+            .iter()
+            .skip(index_offset)
+            .take(index_count)
+            .map(|index| (*index as i64 + base_vertex as i64).max(0) as usize)
+            .collect();
+        self.draw_vertices(renderable, &vertex_ids, instance_count);
+    }
+
+    /// Runs every invocation right away, one after the other.
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        let pipeline = match &self.compute_pipeline {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        let resources = self.resources();
+        let size = pipeline.workgroup_size;
+        for workgroup_id in grid([x, y, z]) {
+            for local_id in grid(size) {
+                let global_id = [
+                    workgroup_id[0] * size[0] + local_id[0],
+                    workgroup_id[1] * size[1] + local_id[1],
+                    workgroup_id[2] * size[2] + local_id[2],
+                ];
+                pipeline.compute_function.run(&ComputeInput {
+                    global_id,
+                    workgroup_id,
+                    local_id,
+                    resources: &resources,
+                });
+            }
+        }
+    }
+
+    /// Dispatches write into the buffers right away, so their writes are
+    /// always visible.
+    pub fn memory_barrier(&mut self) {}
+
+    /// Borrows the bound resources for the shaders of a draw or a dispatch.
+    fn resources(&self) -> Resources<'_> {
+        Resources {
+            uniforms: self
+                .uniforms
+                .iter()
+                .map(|(location, buffer)| (*location, buffer.0.borrow()))
+                .collect(),
+            textures: self
+                .textures
+                .iter()
+                .map(|(location, surface, sampler)| (*location, surface.borrow(), *sampler))
+                .collect(),
+            storage: self
+                .storage
+                .iter()
+                .map(|(location, buffer)| (*location, &buffer.0))
+                .collect(),
+            storage_textures: self
+                .storage_textures
+                .iter()
+                .map(|(location, surface)| (*location, &**surface))
+                .collect(),
+        }
+    }
+
+    /// Shades the vertices with the given ids for every instance and
+    /// rasterizes them in order.
+    fn draw_vertices(
+        &mut self,
+        renderable: &Renderable,
+        vertex_ids: &[usize],
+        instance_count: usize,
+    ) {
+        let pipeline = match &self.pipeline {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        let resources = self.resources();
+        let vertex_buffers: Vec<_> = renderable
+            .vertex_buffers
+            .iter()
+            .map(|(i, buffer)| (*i, buffer.0.borrow()))
+            .collect();
+
+        let mut target = self.framebuffer.lock(self.viewport, self.scissor);
+        let mut attributes = Vec::with_capacity(pipeline.bindings.len());
+        for instance_id in 0..instance_count {
+            let vertices: Vec<VertexOutput> = vertex_ids
+                .iter()
+                .map(|&vertex_id| {
+                    attributes.clear();
+                    for binding in &pipeline.bindings {
+                        let data = match vertex_buffers
+                            .iter()
+                            .find(|(buffer_index, _data)| binding.buffer == *buffer_index)
+                        {
+                            Some((_buffer_index, data)) => data,
+                            None => continue,
+                        };
+
+                        let index = if binding.instance_step_rate == 0 {
+                            vertex_id
+                        } else {
+                            instance_id / binding.instance_step_rate as usize
+                        };
+                        if let Some(value) = read_attribute(
+                            data,
+                            binding.offset + index * binding.stride,
+                            binding.format,
+                        ) {
+                            attributes.push((binding.attribute, value));
+                        }
+                    }
+
+                    pipeline.vertex_function.shade(&VertexInput {
+                        vertex_id,
+                        instance_id,
+                        resources: &resources,
+                        attributes: &attributes,
+                    })
+                })
+                .collect();
+
+            match pipeline.topology {
+                PrimitiveTopology::Points => {
+                    for point in &vertices {
+                        draw_point(&mut target, pipeline, &resources, point);
+                    }
+                }
+                PrimitiveTopology::Lines => {
+                    for line in vertices.chunks_exact(2) {
+                        draw_line(&mut target, pipeline, &resources, [&line[0], &line[1]]);
+                    }
+                }
+                PrimitiveTopology::LineStrip => {
+                    for line in vertices.windows(2) {
+                        draw_line(&mut target, pipeline, &resources, [&line[0], &line[1]]);
+                    }
+                }
+                PrimitiveTopology::Triangles => {
+                    for triangle in vertices.chunks_exact(3) {
+                        let triangle = [&triangle[0], &triangle[1], &triangle[2]];
+                        draw_triangle(&mut target, pipeline, &resources, triangle);
+                    }
+                }
+                PrimitiveTopology::TriangleStrip => {
+                    // Every other triangle of a strip has its first two
+                    // vertices swapped so all of them keep the winding of the
+                    // first one.
+                    for i in 0..vertices.len().saturating_sub(2) {
+                        let triangle = if i % 2 == 0 {
+                            [&vertices[i], &vertices[i + 1], &vertices[i + 2]]
+                        } else {
+                            [&vertices[i + 1], &vertices[i], &vertices[i + 2]]
+                        };
+                        draw_triangle(&mut target, pipeline, &resources, triangle);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every position in a grid of the given size, `x` varying fastest.
+fn grid([width, height, depth]: [u32; 3]) -> impl Iterator<Item = [u32; 3]> {
+    (0..depth).flat_map(move |z| (0..height).flat_map(move |y| (0..width).map(move |x| [x, y, z])))
+}
+
+/// Reads a vertex attribute and widens it to four floats. Integer formats
+/// are converted to their float value, missing components read as
+/// `(0, 0, 0, 1)`.
+fn read_attribute(data: &[u8], offset: usize, format: VertexFormat) -> Option<[f32; 4]> {
+    let size = format.component_size();
+    let bytes = data.get(offset..offset + format.size())?;
+    let mut value = [0.0, 0.0, 0.0, 1.0];
+    for (i, component) in value.iter_mut().take(format.components()).enumerate() {
+        let bytes = &bytes[i * size..(i + 1) * size];
+        *component = match format {
+            VertexFormat::Float
+            | VertexFormat::Float2
+            | VertexFormat::Float3
+            | VertexFormat::Float4 => f32::from_ne_bytes(bytes.try_into().unwrap()),
+            VertexFormat::Half2 | VertexFormat::Half4 => {
+                f16_to_f32(u16::from_ne_bytes(bytes.try_into().unwrap()))
+            }
+            VertexFormat::UChar4 => bytes[0] as f32,
+            VertexFormat::Char4 => bytes[0] as i8 as f32,
+            VertexFormat::UChar4Normalized => bytes[0] as f32 / 255.0,
+            VertexFormat::Char4Normalized => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+            VertexFormat::UShort2 | VertexFormat::UShort4 => {
+                u16::from_ne_bytes(bytes.try_into().unwrap()) as f32
+            }
+            VertexFormat::Short2 | VertexFormat::Short4 => {
+                i16::from_ne_bytes(bytes.try_into().unwrap()) as f32
+            }
+            VertexFormat::UShort2Normalized | VertexFormat::UShort4Normalized => {
+                u16::from_ne_bytes(bytes.try_into().unwrap()) as f32 / 65535.0
+            }
+            VertexFormat::Short2Normalized | VertexFormat::Short4Normalized => {
+                (i16::from_ne_bytes(bytes.try_into().unwrap()) as f32 / 32767.0).max(-1.0)
+            }
+            VertexFormat::Int | VertexFormat::Int2 | VertexFormat::Int3 | VertexFormat::Int4 => {
+                i32::from_ne_bytes(bytes.try_into().unwrap()) as f32
+            }
+            VertexFormat::UInt
+            | VertexFormat::UInt2
+            | VertexFormat::UInt3
+            | VertexFormat::UInt4 => u32::from_ne_bytes(bytes.try_into().unwrap()) as f32,
+        };
+    }
+    Some(value)
+}
+
+impl Frame for CommandBuffer {
+    type Device = Context;
+    type TextureLocation = u32;
+
+    #[allow(deprecated)]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        CommandBuffer::set_clear_color(self, clear_color)
+    }
+
+    fn begin_pass(&mut self, desc: &PassDesc) {
+        CommandBuffer::begin_pass(self, desc)
+    }
+
+    fn end_pass(&mut self) {
+        CommandBuffer::end_pass(self)
+    }
+
+    fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        CommandBuffer::use_pipeline(self, pipeline)
+    }
+
+    fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        CommandBuffer::use_compute_pipeline(self, compute_pipeline)
+    }
+
+    fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        CommandBuffer::use_uniform(self, uniform_buffer, location, binding)
+    }
+
+    fn use_texture(&mut self, texture: &Texture, location: u32, binding: u32) {
+        CommandBuffer::use_texture(self, texture, location, binding)
+    }
+
+    fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        CommandBuffer::use_storage_vertex_buffer(self, vertex_buffer, location)
+    }
+
+    fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        CommandBuffer::use_storage_buffer(self, storage_buffer, location)
+    }
+
+    fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        CommandBuffer::use_storage_texture(self, texture, location)
+    }
+
+    fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        CommandBuffer::draw(self, renderable, vertex_count, instance_count)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        CommandBuffer::draw_indexed(
+            self,
+            renderable,
+            index_count,
+            instance_count,
+            index_offset,
+            base_vertex,
+        )
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        CommandBuffer::dispatch(self, x, y, z)
+    }
+
+    fn memory_barrier(&mut self) {
+        CommandBuffer::memory_barrier(self)
+    }
+
+    fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        CommandBuffer::set_render_target(self, render_target)
+    }
+
+    fn set_viewport(&mut self, viewport: Option<Rect>) {
+        CommandBuffer::set_viewport(self, viewport)
+    }
+
+    fn set_scissor(&mut self, scissor: Option<Rect>) {
+        CommandBuffer::set_scissor(self, scissor)
+    }
+}
+
+impl Drop for CommandBuffer {
+    fn drop(&mut self) {
+        self.end_pass();
+    }
+}
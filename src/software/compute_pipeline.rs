@@ -0,0 +1,28 @@
+use crate::software::err::Result;
+use crate::software::{ComputeFunction, Library};
+use crate::Error;
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub struct ComputePipeline {
+    pub(crate) compute_function: Rc<dyn ComputeFunction>,
+    pub(crate) workgroup_size: [u32; 3],
+}
+
+impl ComputePipeline {
+    pub(crate) fn new(
+        library: &Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        let compute_function = match library.compute_functions.get(function_name) {
+            Some(function) => Rc::clone(function),
+            None => return Err(Error::MissingFunction(function_name.into())),
+        };
+
+        Ok(ComputePipeline {
+            compute_function,
+            workgroup_size,
+        })
+    }
+}
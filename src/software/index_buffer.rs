@@ -0,0 +1,18 @@
+use crate::software::err::Result;
+use crate::Index;
+use std::rc::Rc;
+
+pub struct IndexBuffer {
+    pub(crate) indices: Rc<Vec<u32>>,
+}
+
+impl IndexBuffer {
+    pub(crate) fn with_data<T>(indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        Ok(IndexBuffer {
+            indices: Rc::new(indices.iter().map(|index| (*index).into()).collect()),
+        })
+    }
+}
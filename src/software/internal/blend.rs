@@ -0,0 +1,90 @@
+use crate::{BlendFactor, BlendOperation, BlendState, ColorWriteMask};
+
+/// Blends a fragment color into the color of the attachment like the GPU
+/// does. Attachments that do not hold floats clamp the source and the
+/// constant to `[0, 1]` first.
+pub(crate) fn blend(
+    blend: &BlendState,
+    constant: [f32; 4],
+    source: [f32; 4],
+    destination: [f32; 4],
+    clamp: bool,
+) -> [f32; 4] {
+    let (source, constant) = if clamp {
+        (clamp_unorm(source), clamp_unorm(constant))
+    } else {
+        (source, constant)
+    };
+
+    let mut result = [0.0; 4];
+    for (channel, value) in result.iter_mut().enumerate() {
+        let component = if channel < 3 {
+            &blend.color
+        } else {
+            &blend.alpha
+        };
+        let factor = |factor| blend_factor(factor, channel, source, destination, constant);
+        let weighted_source = source[channel] * factor(component.src_factor);
+        let weighted_destination = destination[channel] * factor(component.dst_factor);
+
+        *value = match component.operation {
+            BlendOperation::Add => weighted_source + weighted_destination,
+            BlendOperation::Subtract => weighted_source - weighted_destination,
+            BlendOperation::ReverseSubtract => weighted_destination - weighted_source,
+            BlendOperation::Min => source[channel].min(destination[channel]),
+            BlendOperation::Max => source[channel].max(destination[channel]),
+        };
+    }
+    result
+}
+
+/// Keeps the channels of `destination` the mask does not write.
+pub(crate) fn apply_write_mask(
+    mask: ColorWriteMask,
+    color: [f32; 4],
+    destination: [f32; 4],
+) -> [f32; 4] {
+    let writes = [mask.red, mask.green, mask.blue, mask.alpha];
+    let mut result = destination;
+    for channel in 0..4 {
+        if writes[channel] {
+            result[channel] = color[channel];
+        }
+    }
+    result
+}
+
+fn blend_factor(
+    factor: BlendFactor,
+    channel: usize,
+    source: [f32; 4],
+    destination: [f32; 4],
+    constant: [f32; 4],
+) -> f32 {
+    match factor {
+        BlendFactor::Zero => 0.0,
+        BlendFactor::One => 1.0,
+        BlendFactor::SrcColor => source[channel],
+        BlendFactor::OneMinusSrcColor => 1.0 - source[channel],
+        BlendFactor::SrcAlpha => source[3],
+        BlendFactor::OneMinusSrcAlpha => 1.0 - source[3],
+        BlendFactor::DstColor => destination[channel],
+        BlendFactor::OneMinusDstColor => 1.0 - destination[channel],
+        BlendFactor::DstAlpha => destination[3],
+        BlendFactor::OneMinusDstAlpha => 1.0 - destination[3],
+        BlendFactor::SrcAlphaSaturated if channel == 3 => 1.0,
+        BlendFactor::SrcAlphaSaturated => source[3].min(1.0 - destination[3]),
+        BlendFactor::Constant => constant[channel],
+        BlendFactor::OneMinusConstant => 1.0 - constant[channel],
+        BlendFactor::ConstantAlpha => constant[3],
+        BlendFactor::OneMinusConstantAlpha => 1.0 - constant[3],
+    }
+}
+
+fn clamp_unorm(color: [f32; 4]) -> [f32; 4] {
+    let mut result = color;
+    for value in &mut result {
+        *value = value.clamp(0.0, 1.0);
+    }
+    result
+}
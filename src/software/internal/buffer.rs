@@ -0,0 +1,16 @@
+use std::cell::RefCell;
+use std::mem::size_of_val;
+
+pub(crate) struct Buffer(pub(crate) RefCell<Vec<u8>>);
+
+impl Buffer {
+    pub(crate) fn new(contents: Vec<u8>) -> Buffer {
+        Buffer(RefCell::new(contents))
+    }
+}
+
+/// Reinterprets the contents of a slice as raw bytes, the same way the GPU
+/// backends hand the data to the driver.
+pub(crate) fn as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, size_of_val(data)) }
+}
@@ -0,0 +1,9 @@
+mod blend;
+mod buffer;
+mod raster;
+mod surface;
+
+pub(crate) use blend::*;
+pub(crate) use buffer::*;
+pub(crate) use raster::*;
+pub(crate) use surface::*;
@@ -0,0 +1,916 @@
+use crate::software::internal::{apply_write_mask, blend, Surface};
+use crate::software::texture::compare_function;
+use crate::software::{FragmentInput, Pipeline, Resources, VertexOutput};
+use crate::{
+    CullMode, FrontFace, LoadAction, PassDesc, PolygonMode, RasterizerState, Rect,
+    StencilOperation, StencilState, TextureFormat,
+};
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+/// Vertices closer to the eye than this are clipped away, which also
+/// guarantees that the perspective divide never divides by zero.
+const W_EPSILON: f32 = 1.0e-5;
+
+/// The attachments drawn into, either the screen of a `Context` or a
+/// `RenderTarget`. The surfaces are shared with the textures of the render
+/// target.
+#[derive(Clone)]
+pub(crate) struct Framebuffer {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) sample_count: u32,
+    pub(crate) color: Vec<Rc<RefCell<Surface>>>,
+    /// Window space depth of every pixel, between 0 (near) and 1 (far).
+    pub(crate) depth: Option<Rc<RefCell<Surface>>>,
+    /// The attachments drawn into when multisampling, with a layer per
+    /// sample. `resolve` averages them into `color` and `depth`.
+    pub(crate) multisampled_color: Vec<Rc<RefCell<Surface>>>,
+    pub(crate) multisampled_depth: Option<Rc<RefCell<Surface>>>,
+}
+
+impl Framebuffer {
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Framebuffer {
+        let color = |layers| {
+            color_formats
+                .iter()
+                .map(|format| Rc::new(RefCell::new(Surface::new(width, height, layers, *format))))
+                .collect()
+        };
+        let depth = |layers| {
+            depth_format.map(|format| {
+                let mut surface = Surface::new(width, height, layers, format);
+                surface.fill([1.0, 0.0, 0.0, 1.0]);
+                Rc::new(RefCell::new(surface))
+            })
+        };
+
+        let multisampled = sample_count > 1;
+        Framebuffer {
+            width,
+            height,
+            sample_count,
+            color: color(1),
+            depth: depth(1),
+            multisampled_color: if multisampled {
+                color(sample_count)
+            } else {
+                Vec::new()
+            },
+            multisampled_depth: if multisampled {
+                depth(sample_count)
+            } else {
+                None
+            },
+        }
+    }
+
+    /// The color attachments that draws and clears write to.
+    fn drawn_color(&self) -> &[Rc<RefCell<Surface>>] {
+        if self.sample_count > 1 {
+            &self.multisampled_color
+        } else {
+            &self.color
+        }
+    }
+
+    fn drawn_depth(&self) -> Option<&Rc<RefCell<Surface>>> {
+        if self.sample_count > 1 {
+            self.multisampled_depth.as_ref()
+        } else {
+            self.depth.as_ref()
+        }
+    }
+
+    /// Clears the attachments that the pass clears, the others are kept.
+    pub(crate) fn clear(&self, desc: &PassDesc) {
+        for (i, surface) in self.drawn_color().iter().enumerate() {
+            if let LoadAction::Clear(color) = desc.color_operations(i).load {
+                surface.borrow_mut().fill(color);
+            }
+        }
+
+        if let Some(surface) = self.drawn_depth() {
+            let mut surface = surface.borrow_mut();
+            let depth = match desc.depth.load {
+                LoadAction::Clear(depth) => Some(depth.clamp(0.0, 1.0)),
+                _ => None,
+            };
+            let stencil = match desc.stencil.load {
+                LoadAction::Clear(stencil) if surface.format.has_stencil() => Some(stencil as f32),
+                _ => None,
+            };
+            for texel in &mut surface.texels {
+                if let Some(depth) = depth {
+                    texel[0] = depth;
+                }
+                if let Some(stencil) = stencil {
+                    texel[1] = stencil;
+                }
+            }
+        }
+    }
+
+    /// Averages the samples of every color texel into the single sampled
+    /// attachments. Depth and stencil are taken from the first sample, like
+    /// the GPU does by default.
+    pub(crate) fn resolve(&self) {
+        if self.sample_count <= 1 {
+            return;
+        }
+
+        for (multisampled, surface) in self.multisampled_color.iter().zip(&self.color) {
+            let multisampled = multisampled.borrow();
+            let mut surface = surface.borrow_mut();
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let mut sum = [0.0; 4];
+                    for sample in 0..self.sample_count {
+                        let texel = multisampled.layer_texel(x, y, sample);
+                        for (sum, component) in sum.iter_mut().zip(&texel) {
+                            *sum += component;
+                        }
+                    }
+                    surface.set_texel(x, y, sum.map(|sum| sum / self.sample_count as f32));
+                }
+            }
+        }
+
+        if let (Some(multisampled), Some(surface)) = (&self.multisampled_depth, &self.depth) {
+            let multisampled = multisampled.borrow();
+            let mut surface = surface.borrow_mut();
+            let len = surface.texels.len();
+            surface.texels.copy_from_slice(&multisampled.texels[..len]);
+        }
+    }
+
+    /// Borrows the attachments for drawing into the viewport, `None` covering
+    /// all of them. Panics if one of them is also bound as a texture, which
+    /// would be a feedback loop on the GPU.
+    pub(crate) fn lock(&self, viewport: Option<Rect>, scissor: Option<Rect>) -> Target<'_> {
+        let color = self
+            .drawn_color()
+            .iter()
+            .map(|surface| borrow_attachment(surface))
+            .collect();
+        let depth = self.drawn_depth().map(|surface| borrow_attachment(surface));
+
+        let viewport = viewport.unwrap_or(Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        });
+        let mut bounds = [
+            viewport.x.min(self.width),
+            viewport.y.min(self.height),
+            viewport.x.saturating_add(viewport.width).min(self.width),
+            viewport.y.saturating_add(viewport.height).min(self.height),
+        ];
+        if let Some(scissor) = scissor {
+            bounds[0] = bounds[0].max(scissor.x);
+            bounds[1] = bounds[1].max(scissor.y);
+            bounds[2] = bounds[2].min(scissor.x.saturating_add(scissor.width));
+            bounds[3] = bounds[3].min(scissor.y.saturating_add(scissor.height));
+        }
+
+        Target {
+            viewport,
+            bounds,
+            samples: sample_positions(self.sample_count),
+            color,
+            depth,
+        }
+    }
+}
+
+fn borrow_attachment(surface: &RefCell<Surface>) -> RefMut<'_, Surface> {
+    surface.try_borrow_mut().unwrap_or_else(|_| {
+        panic!("drawing renderable: an attachment drawn into is bound as a texture")
+    })
+}
+
+pub(crate) struct Target<'a> {
+    viewport: Rect,
+    /// The pixels that can be drawn to, inside of the attachments, the
+    /// viewport and the scissor rectangle, as `[min_x, min_y, max_x, max_y]`
+    /// with the maximum excluded.
+    bounds: [u32; 4],
+    /// The position of every sample relative to the pixel center, see
+    /// `SAMPLE_POSITIONS`.
+    samples: &'static [[i8; 2]],
+    /// A layer per sample.
+    color: Vec<RefMut<'a, Surface>>,
+    depth: Option<RefMut<'a, Surface>>,
+}
+
+impl Target<'_> {
+    fn contains(&self, x: u32, y: u32) -> bool {
+        let [min_x, min_y, max_x, max_y] = self.bounds;
+        (min_x..max_x).contains(&x) && (min_y..max_y).contains(&y)
+    }
+
+    /// The coverage mask of all the samples of a pixel.
+    fn all_samples(&self) -> u32 {
+        u32::MAX >> (32 - self.samples.len())
+    }
+
+    /// The offset of the sample from the pixel center.
+    fn sample_offset(&self, sample: usize) -> [f32; 2] {
+        let [x, y] = self.samples[sample];
+        [x as f32 / 16.0, y as f32 / 16.0]
+    }
+}
+
+/// The standard sample positions of Direct3D and Metal, in sixteenths of a
+/// pixel.
+const SAMPLE_POSITIONS: [&[[i8; 2]]; 5] = [
+    &[[0, 0]],
+    &[[4, 4], [-4, -4]],
+    &[[-2, -6], [6, -2], [-6, 2], [2, 6]],
+    &[
+        [1, -3],
+        [-1, 3],
+        [5, 1],
+        [-3, -5],
+        [-5, 5],
+        [-7, -1],
+        [3, 7],
+        [7, -7],
+    ],
+    &[
+        [1, 1],
+        [-1, -3],
+        [-3, 2],
+        [4, -1],
+        [-5, -2],
+        [2, 5],
+        [5, 3],
+        [3, -5],
+        [-2, 6],
+        [0, -7],
+        [-4, -6],
+        [-6, 4],
+        [-8, 0],
+        [7, -4],
+        [6, 7],
+        [-7, -8],
+    ],
+];
+
+/// The largest sample count the software backend supports.
+pub(crate) const MAX_SAMPLE_COUNT: u32 = 16;
+
+fn sample_positions(sample_count: u32) -> &'static [[i8; 2]] {
+    SAMPLE_POSITIONS[sample_count.trailing_zeros() as usize]
+}
+
+/// A pixel covered by a primitive, with its attributes interpolated at the
+/// pixel center.
+struct Fragment<'a> {
+    x: u32,
+    y: u32,
+    z: f32,
+    /// How the depth changes along x and y, giving the depth of the samples.
+    z_slope: [f32; 2],
+    /// A bit per sample of the pixel covered by the primitive.
+    coverage: u32,
+    inv_w: f32,
+    varyings: &'a [f32],
+    /// The derivatives of the varyings, empty for points and lines.
+    ddx: &'a [f32],
+    ddy: &'a [f32],
+    front_facing: bool,
+}
+
+struct WindowVertex<'a> {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varyings: &'a [f32],
+}
+
+/// Clips the triangle against the near plane, maps it to window coordinates
+/// (with the origin in the top left corner, like the `image` crate) and
+/// fills the pixels it covers, or draws its edges in the line polygon mode.
+///
+/// Depth follows the OpenGL conventions: clip space `z` is mapped from
+/// `[-1, 1]` to `[0, 1]`, and fragments outside of that range are dropped
+/// unless the pipeline clamps the depth.
+pub(crate) fn draw_triangle(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    triangle: [&VertexOutput; 3],
+) {
+    let rasterizer = &pipeline.rasterizer;
+    let polygon = clip_triangle(triangle, rasterizer.depth_clamp);
+    if polygon.len() < 3 {
+        return;
+    }
+
+    let window: Vec<WindowVertex> = polygon
+        .iter()
+        .map(|vertex| to_window(target, vertex))
+        .collect();
+
+    // The window's y axis points down, so a polygon that is counter
+    // clockwise on screen has a negative area.
+    let area: f32 = (1..window.len() - 1)
+        .map(|i| edge(&window[0], &window[i], window[i + 1].x, window[i + 1].y))
+        .sum();
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    let front_facing = (area < 0.0) == (rasterizer.front_face == FrontFace::CounterClockwise);
+    let culled = match rasterizer.cull_mode {
+        CullMode::None => false,
+        CullMode::Front => front_facing,
+        CullMode::Back => !front_facing,
+    };
+    if culled {
+        return;
+    }
+
+    let depth_offset = depth_offset(target, rasterizer, &window);
+    match rasterizer.polygon_mode {
+        PolygonMode::Fill => {
+            for i in 1..window.len() - 1 {
+                fill_triangle(
+                    target,
+                    pipeline,
+                    resources,
+                    [&window[0], &window[i], &window[i + 1]],
+                    front_facing,
+                    depth_offset,
+                );
+            }
+        }
+        // Edges added by clipping are drawn as well.
+        PolygonMode::Line => {
+            for i in 0..polygon.len() {
+                rasterize_line(
+                    target,
+                    pipeline,
+                    resources,
+                    [&polygon[i], &polygon[(i + 1) % polygon.len()]],
+                    front_facing,
+                    depth_offset,
+                );
+            }
+        }
+    }
+}
+
+/// The polygon offset of a triangle: the steepest slope of its depth scaled
+/// by `depth_bias_slope_scale`, plus `depth_bias` times the smallest depth
+/// difference the depth attachment can hold.
+fn depth_offset(target: &Target, rasterizer: &RasterizerState, window: &[WindowVertex]) -> f32 {
+    if rasterizer.depth_bias == 0.0 && rasterizer.depth_bias_slope_scale == 0.0 {
+        return 0.0;
+    }
+
+    let [a, b, c] = [&window[0], &window[1], &window[2]];
+    let area = edge(a, b, c.x, c.y);
+    let dz_dx = ((b.z - a.z) * (c.y - a.y) - (c.z - a.z) * (b.y - a.y)) / area;
+    let dz_dy = ((c.z - a.z) * (b.x - a.x) - (b.z - a.z) * (c.x - a.x)) / area;
+    let slope = dz_dx.abs().max(dz_dy.abs());
+    let slope = if slope.is_finite() { slope } else { 0.0 };
+
+    // Float depth has the precision of the largest depth of the triangle,
+    // fixed point depth has 24 bits.
+    let resolution = match target.depth.as_ref().map(|depth| depth.format) {
+        Some(TextureFormat::Depth32F) => {
+            let max_z = window
+                .iter()
+                .fold(0.0f32, |max_z, vertex| max_z.max(vertex.z));
+            2.0f32.powf(max_z.max(f32::MIN_POSITIVE).log2().floor() - 23.0)
+        }
+        _ => 1.0 / 16_777_216.0,
+    };
+
+    slope * rasterizer.depth_bias_slope_scale + resolution * rasterizer.depth_bias
+}
+
+/// Draws a single pixel point, dropping it if it lies outside of the depth
+/// range or behind the eye.
+pub(crate) fn draw_point(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    point: &VertexOutput,
+) {
+    let [_x, _y, z, w] = point.position;
+    if w <= W_EPSILON || (z < -w && !pipeline.rasterizer.depth_clamp) {
+        return;
+    }
+
+    let vertex = to_window(target, point);
+    if vertex.x < 0.0 || vertex.y < 0.0 {
+        return;
+    }
+
+    let fragment = Fragment {
+        x: vertex.x as u32,
+        y: vertex.y as u32,
+        z: vertex.z,
+        z_slope: [0.0, 0.0],
+        coverage: target.all_samples(),
+        inv_w: vertex.inv_w,
+        varyings: vertex.varyings,
+        ddx: &[],
+        ddy: &[],
+        front_facing: true,
+    };
+    if target.contains(fragment.x, fragment.y) {
+        shade_fragment(target, pipeline, resources, &fragment, 0.0);
+    }
+}
+
+/// Draws a one pixel wide line, clipped against the near plane. Pixels are
+/// stepped along the major axis and the last pixel is left out, so the
+/// segments of a strip don't overlap.
+pub(crate) fn draw_line(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    line: [&VertexOutput; 2],
+) {
+    rasterize_line(target, pipeline, resources, line, true, 0.0)
+}
+
+fn rasterize_line(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    line: [&VertexOutput; 2],
+    front_facing: bool,
+    depth_offset: f32,
+) {
+    let [mut from, mut to] = [
+        VertexOutput {
+            position: line[0].position,
+            varyings: line[0].varyings.clone(),
+        },
+        VertexOutput {
+            position: line[1].position,
+            varyings: line[1].varyings.clone(),
+        },
+    ];
+    let planes: [fn(&[f32; 4]) -> f32; 2] = [
+        |position| position[3] - W_EPSILON,
+        |position| position[2] + position[3],
+    ];
+    let plane_count = if pipeline.rasterizer.depth_clamp {
+        1
+    } else {
+        2
+    };
+    for distance in &planes[..plane_count] {
+        let from_distance = distance(&from.position);
+        let to_distance = distance(&to.position);
+        if from_distance < 0.0 && to_distance < 0.0 {
+            return;
+        } else if from_distance < 0.0 {
+            from = lerp_vertex(&from, &to, from_distance / (from_distance - to_distance));
+        } else if to_distance < 0.0 {
+            to = lerp_vertex(&to, &from, to_distance / (to_distance - from_distance));
+        }
+    }
+
+    let a = to_window(target, &from);
+    let b = to_window(target, &to);
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let x_major = dx.abs() >= dy.abs();
+    let (start, length) = if x_major { (a.x, dx) } else { (a.y, dy) };
+    if length == 0.0 || !length.is_finite() {
+        return;
+    }
+
+    let varying_count = a.varyings.len().min(b.varyings.len());
+    let mut varyings = vec![0.0; varying_count];
+    let first = (start.min(start + length) - 0.5).ceil() as i64;
+    let last = (start.max(start + length) - 0.5).ceil() as i64;
+    for step in first..last {
+        let t = (step as f32 + 0.5 - start) / length;
+        let (x, y) = if x_major {
+            (step as f32, (a.y + t * dy).floor())
+        } else {
+            ((a.x + t * dx).floor(), step as f32)
+        };
+        if x < 0.0 || y < 0.0 || !target.contains(x as u32, y as u32) {
+            continue;
+        }
+
+        let z = a.z + (b.z - a.z) * t;
+        let p0 = (1.0 - t) * a.inv_w;
+        let p1 = t * b.inv_w;
+        let inv_w = p0 + p1;
+        for (i, varying) in varyings.iter_mut().enumerate() {
+            *varying = (p0 * a.varyings[i] + p1 * b.varyings[i]) / inv_w;
+        }
+
+        let fragment = Fragment {
+            x: x as u32,
+            y: y as u32,
+            z,
+            z_slope: [0.0, 0.0],
+            coverage: target.all_samples(),
+            inv_w,
+            varyings: &varyings,
+            ddx: &[],
+            ddy: &[],
+            front_facing,
+        };
+        shade_fragment(target, pipeline, resources, &fragment, depth_offset);
+    }
+}
+
+/// Maps a clip space vertex to window coordinates, with the origin in the
+/// top left corner.
+fn to_window<'a>(target: &Target, vertex: &'a VertexOutput) -> WindowVertex<'a> {
+    let viewport = target.viewport;
+    let [x, y, z, w] = vertex.position;
+    let inv_w = 1.0 / w;
+    WindowVertex {
+        x: viewport.x as f32 + (x * inv_w * 0.5 + 0.5) * viewport.width as f32,
+        y: viewport.y as f32 + (0.5 - y * inv_w * 0.5) * viewport.height as f32,
+        z: z * inv_w * 0.5 + 0.5,
+        inv_w,
+        varyings: &vertex.varyings,
+    }
+}
+
+/// Sutherland-Hodgman clipping against `w > W_EPSILON` and, unless the
+/// depth is clamped, `z > -w`.
+fn clip_triangle(triangle: [&VertexOutput; 3], depth_clamp: bool) -> Vec<VertexOutput> {
+    let polygon: Vec<VertexOutput> = triangle
+        .iter()
+        .map(|vertex| VertexOutput {
+            position: vertex.position,
+            varyings: vertex.varyings.clone(),
+        })
+        .collect();
+
+    let polygon = clip_polygon(polygon, |position| position[3] - W_EPSILON);
+    if depth_clamp {
+        polygon
+    } else {
+        clip_polygon(polygon, |position| position[2] + position[3])
+    }
+}
+
+fn clip_polygon<F>(polygon: Vec<VertexOutput>, distance: F) -> Vec<VertexOutput>
+where
+    F: Fn(&[f32; 4]) -> f32,
+{
+    if polygon
+        .iter()
+        .all(|vertex| distance(&vertex.position) >= 0.0)
+    {
+        return polygon;
+    }
+
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = distance(&current.position);
+        let next_distance = distance(&next.position);
+
+        if current_distance >= 0.0 {
+            clipped.push(VertexOutput {
+                position: current.position,
+                varyings: current.varyings.clone(),
+            });
+        }
+
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(lerp_vertex(current, next, t));
+        }
+    }
+    clipped
+}
+
+fn lerp_vertex(from: &VertexOutput, to: &VertexOutput, t: f32) -> VertexOutput {
+    let mut position = from.position;
+    for (value, to) in position.iter_mut().zip(&to.position) {
+        *value += (to - *value) * t;
+    }
+
+    VertexOutput {
+        position,
+        varyings: from
+            .varyings
+            .iter()
+            .zip(&to.varyings)
+            .map(|(from, to)| from + (to - from) * t)
+            .collect(),
+    }
+}
+
+fn edge(from: &WindowVertex, to: &WindowVertex, x: f32, y: f32) -> f32 {
+    (to.x - from.x) * (y - from.y) - (to.y - from.y) * (x - from.x)
+}
+
+/// With the winding `fill_triangle` brings triangles to, a pixel center lying exactly on
+/// an edge is only covered if that is a top or a left edge, so pixels on an
+/// edge shared by two triangles are not drawn (and blended) twice.
+fn is_top_left(from: &WindowVertex, to: &WindowVertex) -> bool {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    dy < 0.0 || (dy == 0.0 && dx > 0.0)
+}
+
+fn covers(weight: f32, top_left: bool) -> bool {
+    weight > 0.0 || (weight == 0.0 && top_left)
+}
+
+fn fill_triangle(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    triangle: [&WindowVertex; 3],
+    front_facing: bool,
+    depth_offset: f32,
+) {
+    let [a, mut b, mut c] = triangle;
+
+    let area = edge(a, b, c.x, c.y);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    let area = if area < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+        -area
+    } else {
+        area
+    };
+
+    let [min_x, min_y, max_x, max_y] = target.bounds;
+    let min_x = (a.x.min(b.x).min(c.x).floor().max(0.0) as u32).max(min_x);
+    let min_y = (a.y.min(b.y).min(c.y).floor().max(0.0) as u32).max(min_y);
+    let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(max_x);
+    let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(max_y);
+
+    let top_left = [is_top_left(b, c), is_top_left(c, a), is_top_left(a, b)];
+    let varying_count = a.varyings.len().min(b.varyings.len()).min(c.varyings.len());
+    let mut varyings = vec![0.0; varying_count];
+    let mut quad_origin = vec![0.0; varying_count];
+    let mut ddx = vec![0.0; varying_count];
+    let mut ddy = vec![0.0; varying_count];
+    let z_slope = [
+        ((b.y - c.y) * a.z + (c.y - a.y) * b.z + (a.y - b.y) * c.z) / area,
+        ((c.x - b.x) * a.z + (a.x - c.x) * b.z + (b.x - a.x) * c.z) / area,
+    ];
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+
+            let mut coverage = 0;
+            for sample in 0..target.samples.len() {
+                let [offset_x, offset_y] = target.sample_offset(sample);
+                let (sx, sy) = (px + offset_x, py + offset_y);
+                if covers(edge(b, c, sx, sy), top_left[0])
+                    && covers(edge(c, a, sx, sy), top_left[1])
+                    && covers(edge(a, b, sx, sy), top_left[2])
+                {
+                    coverage |= 1 << sample;
+                }
+            }
+            if coverage == 0 {
+                continue;
+            }
+
+            // Attributes are interpolated at the pixel center, even if only
+            // some of the other samples are covered.
+            let (z, inv_w) = interpolate([a, b, c], area, px, py, &mut varyings);
+
+            // Like coarse derivatives on the GPU, the differences across the
+            // 2×2 quad the pixel lies in, whether or not the other pixels
+            // of the quad are covered.
+            let qx = (x & !1) as f32 + 0.5;
+            let qy = (y & !1) as f32 + 0.5;
+            interpolate([a, b, c], area, qx, qy, &mut quad_origin);
+            interpolate([a, b, c], area, qx + 1.0, qy, &mut ddx);
+            interpolate([a, b, c], area, qx, qy + 1.0, &mut ddy);
+            for (i, origin) in quad_origin.iter().enumerate() {
+                ddx[i] -= origin;
+                ddy[i] -= origin;
+            }
+
+            let fragment = Fragment {
+                x,
+                y,
+                z,
+                z_slope,
+                coverage,
+                inv_w,
+                varyings: &varyings,
+                ddx: &ddx,
+                ddy: &ddy,
+                front_facing,
+            };
+            shade_fragment(target, pipeline, resources, &fragment, depth_offset);
+        }
+    }
+}
+
+/// Interpolates the varyings of a triangle, perspective correctly, at a
+/// point of the window, and returns its depth and `1 / w`.
+fn interpolate(
+    [a, b, c]: [&WindowVertex; 3],
+    area: f32,
+    x: f32,
+    y: f32,
+    varyings: &mut [f32],
+) -> (f32, f32) {
+    let b0 = edge(b, c, x, y) / area;
+    let b1 = edge(c, a, x, y) / area;
+    let b2 = edge(a, b, x, y) / area;
+    let z = b0 * a.z + b1 * b.z + b2 * c.z;
+
+    let p0 = b0 * a.inv_w;
+    let p1 = b1 * b.inv_w;
+    let p2 = b2 * c.inv_w;
+    let inv_w = p0 + p1 + p2;
+    for (i, varying) in varyings.iter_mut().enumerate() {
+        *varying = (p0 * a.varyings[i] + p1 * b.varyings[i] + p2 * c.varyings[i]) / inv_w;
+    }
+    (z, inv_w)
+}
+
+/// Offsets the depth, runs the stencil and depth tests of every covered
+/// sample and, if one of them passes, the fragment function for the pixel,
+/// then blends the result into the samples that passed.
+///
+/// The tests run before the fragment function, so fragments failing them
+/// update the stencil even if the fragment function would have discarded
+/// them, like early fragment tests on the GPU.
+fn shade_fragment(
+    target: &mut Target,
+    pipeline: &Pipeline,
+    resources: &Resources,
+    fragment: &Fragment,
+    depth_offset: f32,
+) {
+    let (x, y) = (fragment.x, fragment.y);
+    let stencil = match (&pipeline.stencil, &target.depth) {
+        (Some(stencil), Some(depth)) if depth.format.has_stencil() => Some(stencil),
+        _ => None,
+    };
+    let face = stencil.map(|stencil| {
+        if fragment.front_facing {
+            &stencil.front
+        } else {
+            &stencil.back
+        }
+    });
+
+    let mut depths = [0.0; MAX_SAMPLE_COUNT as usize];
+    let mut passed = 0;
+    for (sample, depth) in depths.iter_mut().enumerate().take(target.samples.len()) {
+        if fragment.coverage & (1 << sample) == 0 {
+            continue;
+        }
+        let [offset_x, offset_y] = target.sample_offset(sample);
+        let z = fragment.z + fragment.z_slope[0] * offset_x + fragment.z_slope[1] * offset_y;
+        if !pipeline.rasterizer.depth_clamp && !(0.0..=1.0).contains(&z) {
+            continue;
+        }
+        *depth = (z + depth_offset).clamp(0.0, 1.0);
+
+        let stored = target
+            .depth
+            .as_ref()
+            .map(|depth| depth.layer_texel(x, y, sample as u32));
+        let stencil_passes = match (stencil, face, stored) {
+            (Some(stencil), Some(face), Some(stored)) => compare_function(
+                face.compare,
+                (stencil.reference & stencil.read_mask) as f32,
+                (stored[1] as u8 & stencil.read_mask) as f32,
+            ),
+            _ => true,
+        };
+        // Like the GPU, test the depth with the precision of the attachment.
+        let depth_passes = match (pipeline.depth_compare, &target.depth, stored) {
+            (Some(function), Some(attachment), Some(stored)) => {
+                compare_function(function, attachment.quantize_depth(*depth), stored[0])
+            }
+            _ => true,
+        };
+
+        if stencil_passes && depth_passes {
+            passed |= 1 << sample;
+        } else if let (Some(stencil), Some(face), Some(attachment)) =
+            (stencil, face, &mut target.depth)
+        {
+            let operation = if stencil_passes {
+                face.depth_fail_op
+            } else {
+                face.fail_op
+            };
+            let mut texel = attachment.layer_texel(x, y, sample as u32);
+            texel[1] = stencil_value(stencil, operation, texel[1] as u8) as f32;
+            attachment.set_layer_texel(x, y, sample as u32, texel);
+        }
+    }
+    if passed == 0 {
+        return;
+    }
+
+    let z = (fragment.z + depth_offset).clamp(0.0, 1.0);
+    let colors = match pipeline
+        .fragment_function
+        .shade_attachments(&FragmentInput {
+            position: [x as f32 + 0.5, y as f32 + 0.5, z, fragment.inv_w],
+            front_facing: fragment.front_facing,
+            varyings: fragment.varyings,
+            resources,
+            ddx: fragment.ddx,
+            ddy: fragment.ddy,
+        }) {
+        Some(colors) => colors,
+        None => return,
+    };
+    if pipeline.alpha_to_coverage && target.samples.len() > 1 {
+        let alpha = colors.first().map_or(1.0, |color| color[3]);
+        passed &= alpha_coverage(alpha, target.samples.len());
+    }
+
+    for (sample, depth) in depths.iter().enumerate().take(target.samples.len()) {
+        if passed & (1 << sample) == 0 {
+            continue;
+        }
+        let layer = sample as u32;
+
+        if let Some(attachment) = &mut target.depth {
+            let mut texel = attachment.layer_texel(x, y, layer);
+            if pipeline.depth_compare.is_some() && pipeline.depth_write {
+                texel[0] = *depth;
+            }
+            if let (Some(stencil), Some(face)) = (stencil, face) {
+                texel[1] = stencil_value(stencil, face.pass_op, texel[1] as u8) as f32;
+            }
+            attachment.set_layer_texel(x, y, layer, texel);
+        }
+
+        for (i, (surface, color)) in target.color.iter_mut().zip(&colors).enumerate() {
+            let state = pipeline.color_attachment(i);
+            let destination = surface.layer_texel(x, y, layer);
+            let color = match &state.blend {
+                Some(blend_state) => blend(
+                    blend_state,
+                    pipeline.blend_constant,
+                    *color,
+                    destination,
+                    !surface.format.is_float(),
+                ),
+                None => *color,
+            };
+            surface.set_layer_texel(
+                x,
+                y,
+                layer,
+                apply_write_mask(state.write_mask, color, destination),
+            );
+        }
+    }
+}
+
+/// The samples that alpha to coverage keeps: as many as the alpha is a
+/// fraction of the sample count.
+fn alpha_coverage(alpha: f32, sample_count: usize) -> u32 {
+    let covered = (alpha.clamp(0.0, 1.0) * sample_count as f32).round() as u32;
+    if covered == 0 {
+        0
+    } else {
+        u32::MAX >> (32 - covered)
+    }
+}
+
+/// Applies the stencil operation to the stored value, only changing the
+/// bits of the write mask.
+fn stencil_value(stencil: &StencilState, operation: StencilOperation, value: u8) -> u8 {
+    let result = match operation {
+        StencilOperation::Keep => value,
+        StencilOperation::Zero => 0,
+        StencilOperation::Replace => stencil.reference,
+        StencilOperation::IncrementClamp => value.saturating_add(1),
+        StencilOperation::DecrementClamp => value.saturating_sub(1),
+        StencilOperation::Invert => !value,
+        StencilOperation::IncrementWrap => value.wrapping_add(1),
+        StencilOperation::DecrementWrap => value.wrapping_sub(1),
+    };
+    (value & !stencil.write_mask) | (result & stencil.write_mask)
+}
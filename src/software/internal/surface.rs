@@ -0,0 +1,184 @@
+use crate::shared::image::{decode_texels, linear_to_srgb, srgb_to_linear};
+use crate::shared::{f16_to_f32, f32_to_f16};
+use crate::TextureFormat;
+use image::RgbaImage;
+use std::convert::TryInto;
+
+/// A 2D grid of RGBA texels, used both for textures and for the attachments
+/// being rendered to. Depth surfaces keep their value in the red channel, and
+/// `Depth24Stencil8` surfaces their stencil value in the green channel.
+/// Array layers, 3D slices and cube faces are stored as further grids after
+/// the first one. Textures with several mip levels keep the smaller levels
+/// in `mip_levels`.
+///
+/// Texels are kept as floats so the rasterizer can blend without converting
+/// back and forth, but every write drops the components the format does not
+/// have and is rounded to the precision the GPU would keep: 8 bits per
+/// channel for the normalized formats, half floats for the 16 bit float
+/// formats and 24 bits for the depth of `Depth24Stencil8`. 32 bit float
+/// formats keep their value. sRGB surfaces hold linear colors, like the GPU
+/// returns when sampling them.
+pub(crate) struct Surface {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) layers: u32,
+    pub(crate) format: TextureFormat,
+    pub(crate) texels: Vec<[f32; 4]>,
+    /// The mip levels after this one, each with its own size and layers.
+    pub(crate) mip_levels: Vec<Surface>,
+}
+
+impl Surface {
+    pub(crate) fn new(width: u32, height: u32, layers: u32, format: TextureFormat) -> Surface {
+        Surface {
+            width,
+            height,
+            layers,
+            format,
+            texels: vec![[0.0; 4]; width as usize * height as usize * layers as usize],
+            mip_levels: Vec::new(),
+        }
+    }
+
+    /// Decodes tightly packed texels, the data must hold exactly `width *
+    /// height * layers` texels. Missing components read as `(0, 0, 0, 1)`
+    /// and sRGB colors are converted to linear.
+    pub(crate) fn from_pixels(
+        width: u32,
+        height: u32,
+        layers: u32,
+        format: TextureFormat,
+        data: &[u8],
+    ) -> Surface {
+        let texels = decode_texels(format, data)
+            .chunks_exact(4)
+            .map(|texel| texel.try_into().unwrap())
+            .collect();
+
+        Surface {
+            width,
+            height,
+            layers,
+            format,
+            texels,
+            mip_levels: Vec::new(),
+        }
+    }
+
+    /// The mip level `level`, levels past the last one read the last one.
+    pub(crate) fn mip_level(&self, level: usize) -> &Surface {
+        match level.min(self.mip_levels.len()) {
+            0 => self,
+            level => &self.mip_levels[level - 1],
+        }
+    }
+
+    pub(crate) fn mip_level_mut(&mut self, level: usize) -> &mut Surface {
+        match level {
+            0 => self,
+            _ => &mut self.mip_levels[level - 1],
+        }
+    }
+
+    /// Converts the first layer of the surface to an 8 bit image, depth
+    /// surfaces are converted to grayscale.
+    pub(crate) fn to_rgba_image(&self) -> RgbaImage {
+        let mut img = RgbaImage::new(self.width, self.height);
+        for (pixel, texel) in img.pixels_mut().zip(&self.texels) {
+            let texel = match self.format {
+                TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F => {
+                    [texel[0], texel[0], texel[0], 1.0]
+                }
+                TextureFormat::Rgba8Srgb => [
+                    linear_to_srgb(texel[0]),
+                    linear_to_srgb(texel[1]),
+                    linear_to_srgb(texel[2]),
+                    texel[3],
+                ],
+                _ => *texel,
+            };
+            pixel.0 = [
+                to_unorm8(texel[0]),
+                to_unorm8(texel[1]),
+                to_unorm8(texel[2]),
+                to_unorm8(texel[3]),
+            ];
+        }
+        img
+    }
+
+    pub(crate) fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    pub(crate) fn layer_texel(&self, x: u32, y: u32, layer: u32) -> [f32; 4] {
+        self.texels[((layer * self.height + y) * self.width + x) as usize]
+    }
+
+    pub(crate) fn set_texel(&mut self, x: u32, y: u32, value: [f32; 4]) {
+        self.texels[(y * self.width + x) as usize] = self.quantize(value);
+    }
+
+    pub(crate) fn set_layer_texel(&mut self, x: u32, y: u32, layer: u32, value: [f32; 4]) {
+        self.texels[((layer * self.height + y) * self.width + x) as usize] = self.quantize(value);
+    }
+
+    pub(crate) fn fill(&mut self, value: [f32; 4]) {
+        let value = self.quantize(value);
+        for texel in &mut self.texels {
+            *texel = value;
+        }
+    }
+
+    /// Drops the components the format does not have and rounds the rest to
+    /// its precision.
+    /// The depth as it would be stored in this depth surface.
+    pub(crate) fn quantize_depth(&self, depth: f32) -> f32 {
+        self.quantize([depth, 0.0, 0.0, 1.0])[0]
+    }
+
+    fn quantize(&self, value: [f32; 4]) -> [f32; 4] {
+        let mut result = [0.0, 0.0, 0.0, 1.0];
+        result[..self.format.components()].copy_from_slice(&value[..self.format.components()]);
+
+        match self.format {
+            TextureFormat::R8 | TextureFormat::Rg8 | TextureFormat::Rgba8 => {
+                for component in &mut result {
+                    *component = quantize(*component);
+                }
+            }
+            TextureFormat::Rgba8Srgb => {
+                for component in &mut result[..3] {
+                    *component = srgb_to_linear(quantize(linear_to_srgb(*component)));
+                }
+                result[3] = quantize(result[3]);
+            }
+            TextureFormat::R16F | TextureFormat::Rgba16F => {
+                for component in &mut result[..self.format.components()] {
+                    *component = f16_to_f32(f32_to_f16(*component));
+                }
+            }
+            TextureFormat::Depth24Stencil8 => {
+                result[0] = quantize_depth24(result[0]);
+                result[1] = value[1].round().clamp(0.0, 255.0);
+            }
+            TextureFormat::R32F | TextureFormat::Rgba32F | TextureFormat::Depth32F => {}
+        }
+        result
+    }
+}
+
+fn to_unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn quantize(value: f32) -> f32 {
+    to_unorm8(value) as f32 / 255.0
+}
+
+/// Rounds a depth to the closest of the 2^24 values a 24 bit depth buffer
+/// holds.
+fn quantize_depth24(value: f32) -> f32 {
+    const MAX: f64 = ((1 << 24) - 1) as f64;
+    ((value.clamp(0.0, 1.0) as f64 * MAX).round() / MAX) as f32
+}
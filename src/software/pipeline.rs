@@ -0,0 +1,56 @@
+use crate::shared::{
+    Binding, BlendState, ColorAttachmentState, ColorWriteMask, CompareFunction, PipelineDesc,
+    PrimitiveTopology, RasterizerState, StencilState,
+};
+use crate::software::err::Result;
+use crate::software::{FragmentFunction, Shader, VertexFunction};
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub struct Pipeline {
+    pub(crate) vertex_function: Rc<dyn VertexFunction>,
+    pub(crate) fragment_function: Rc<dyn FragmentFunction>,
+    pub(crate) topology: PrimitiveTopology,
+    pub(crate) depth_compare: Option<CompareFunction>,
+    pub(crate) depth_write: bool,
+    pub(crate) stencil: Option<StencilState>,
+    pub(crate) blend: Option<BlendState>,
+    pub(crate) blend_constant: [f32; 4],
+    pub(crate) alpha_to_coverage: bool,
+    pub(crate) color_write_mask: ColorWriteMask,
+    pub(crate) color_attachments: Vec<ColorAttachmentState>,
+    pub(crate) rasterizer: RasterizerState,
+    pub(crate) bindings: Vec<Binding>,
+}
+
+impl Pipeline {
+    pub(crate) fn new(shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        Ok(Pipeline {
+            vertex_function: Rc::clone(&shader.vertex_function),
+            fragment_function: Rc::clone(&shader.fragment_function),
+            topology: desc.topology,
+            depth_compare: desc.depth_compare,
+            depth_write: desc.depth_write,
+            stencil: desc.stencil,
+            blend: desc.blend,
+            blend_constant: desc.blend_constant,
+            alpha_to_coverage: desc.alpha_to_coverage,
+            color_write_mask: desc.color_write_mask,
+            color_attachments: Vec::from(desc.color_attachments),
+            rasterizer: desc.rasterizer,
+            bindings: Vec::from(desc.bindings),
+        })
+    }
+
+    /// The state of the color attachment `index`, see
+    /// `PipelineDesc::color_attachment`.
+    pub(crate) fn color_attachment(&self, index: usize) -> ColorAttachmentState {
+        self.color_attachments
+            .get(index)
+            .copied()
+            .unwrap_or(ColorAttachmentState {
+                blend: self.blend,
+                write_mask: self.color_write_mask,
+            })
+    }
+}
@@ -0,0 +1,76 @@
+use crate::shared::image::check_sample_count;
+use crate::software::err::Result;
+use crate::software::internal::{Framebuffer, MAX_SAMPLE_COUNT};
+use crate::software::Texture;
+use crate::{Attachments, Error, TextureFormat};
+
+pub struct RenderTarget {
+    pub(crate) framebuffer: Framebuffer,
+    pub(crate) color_textures: Vec<Texture>,
+    pub(crate) depth_texture: Option<Texture>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        check_sample_count(sample_count)?;
+        if sample_count > MAX_SAMPLE_COUNT {
+            return Err(Error::Unsupported(format!(
+                "render targets with {} samples per pixel",
+                sample_count
+            )));
+        }
+
+        let framebuffer =
+            Framebuffer::new(width, height, color_formats, depth_format, sample_count);
+        let color_textures = framebuffer
+            .color
+            .iter()
+            .map(Texture::with_surface)
+            .collect();
+        let depth_texture = framebuffer.depth.as_ref().map(Texture::with_surface);
+
+        Ok(RenderTarget {
+            framebuffer,
+            color_textures,
+            depth_texture,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.framebuffer.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.framebuffer.height
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.framebuffer.sample_count
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.color_textures
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+}
+
+impl Attachments for RenderTarget {
+    type Texture = Texture;
+
+    fn color_textures(&self) -> &[Texture] {
+        RenderTarget::color_textures(self)
+    }
+
+    fn depth_texture(&self) -> Option<&Texture> {
+        RenderTarget::depth_texture(self)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::software::err::Result;
+use crate::software::internal::Buffer;
+use crate::software::{IndexBuffer, VertexBuffer};
+use std::rc::Rc;
+
+pub struct Renderable {
+    pub(crate) vertex_buffers: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) index_buffer: Option<Rc<Vec<u32>>>,
+}
+
+impl Renderable {
+    pub(crate) fn new(vertex_buffers: &[(u32, &VertexBuffer)]) -> Result<Renderable> {
+        Ok(Renderable {
+            vertex_buffers: vertex_buffers
+                .iter()
+                .map(|(i, vertex_buffer)| (*i, vertex_buffer.buffer.clone()))
+                .collect(),
+            index_buffer: None,
+        })
+    }
+
+    pub(crate) fn with_index(
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        let mut renderable = Renderable::new(vertex_buffers)?;
+        renderable.index_buffer = Some(Rc::clone(&index_buffer.indices));
+        Ok(renderable)
+    }
+}
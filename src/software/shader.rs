@@ -0,0 +1,445 @@
+use crate::software::err::Result;
+use crate::software::internal::Surface;
+use crate::software::texture::{
+    sample, sample_3d, sample_compare, sample_cube, sample_grad, sample_layer,
+};
+use crate::{Error, SamplerDesc};
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::mem::{size_of, size_of_val};
+use std::rc::Rc;
+
+/// A vertex shader written in Rust.
+///
+/// Implemented for every `Fn(&VertexInput) -> VertexOutput` closure.
+pub trait VertexFunction {
+    fn shade(&self, input: &VertexInput) -> VertexOutput;
+}
+
+impl<F> VertexFunction for F
+where
+    F: Fn(&VertexInput) -> VertexOutput,
+{
+    fn shade(&self, input: &VertexInput) -> VertexOutput {
+        self(input)
+    }
+}
+
+/// A fragment shader written in Rust. Returning `None` discards the fragment.
+///
+/// Implemented for every `Fn(&FragmentInput) -> Option<[f32; 4]>` closure,
+/// see `MultipleOutputs` for drawing into several color attachments.
+pub trait FragmentFunction {
+    fn shade(&self, input: &FragmentInput) -> Option<[f32; 4]>;
+
+    /// The colors of the color attachments, in order. Attachments without a
+    /// color are left as they are. Defaults to the color of `shade` for the
+    /// first attachment.
+    fn shade_attachments(&self, input: &FragmentInput) -> Option<Vec<[f32; 4]>> {
+        self.shade(input).map(|color| vec![color])
+    }
+}
+
+/// A fragment shader returning one color per color attachment, from a
+/// `Fn(&FragmentInput) -> Option<Vec<[f32; 4]>>` closure.
+pub struct MultipleOutputs<F>(pub F);
+
+impl<F> FragmentFunction for MultipleOutputs<F>
+where
+    F: Fn(&FragmentInput) -> Option<Vec<[f32; 4]>>,
+{
+    fn shade(&self, input: &FragmentInput) -> Option<[f32; 4]> {
+        (self.0)(input).and_then(|colors| colors.first().copied())
+    }
+
+    fn shade_attachments(&self, input: &FragmentInput) -> Option<Vec<[f32; 4]>> {
+        (self.0)(input)
+    }
+}
+
+impl<F> FragmentFunction for F
+where
+    F: Fn(&FragmentInput) -> Option<[f32; 4]>,
+{
+    fn shade(&self, input: &FragmentInput) -> Option<[f32; 4]> {
+        self(input)
+    }
+}
+
+/// A compute shader written in Rust, run once for every invocation of a
+/// dispatch.
+///
+/// Implemented for every `Fn(&ComputeInput)` closure.
+pub trait ComputeFunction {
+    fn run(&self, input: &ComputeInput);
+}
+
+impl<F> ComputeFunction for F
+where
+    F: Fn(&ComputeInput),
+{
+    fn run(&self, input: &ComputeInput) {
+        self(input)
+    }
+}
+
+/// The uniform buffers, textures, storage buffers and storage images bound
+/// to the command buffer when a draw or a dispatch was recorded.
+pub struct Resources<'a> {
+    pub(crate) uniforms: Vec<(u32, Ref<'a, Vec<u8>>)>,
+    pub(crate) textures: Vec<(u32, Ref<'a, Surface>, SamplerDesc)>,
+    pub(crate) storage: Vec<(u32, &'a RefCell<Vec<u8>>)>,
+    pub(crate) storage_textures: Vec<(u32, &'a RefCell<Surface>)>,
+}
+
+impl<'a> Resources<'a> {
+    /// The raw contents of the uniform buffer bound at `location`.
+    pub fn uniform_bytes(&self, location: u32) -> Option<&[u8]> {
+        self.uniforms
+            .iter()
+            .find(|(uniform_location, _data)| *uniform_location == location)
+            .map(|(_location, data)| data.as_slice())
+    }
+
+    /// Reads the uniform buffer bound at `location` as a value of type `T`.
+    ///
+    /// Returns `None` if no buffer is bound there, or if it is smaller than
+    /// `T`.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the buffer must be a valid value of `T`, which is
+    /// always the case when `T` is the type that was uploaded into it.
+    pub unsafe fn uniform<T>(&self, location: u32) -> Option<T>
+    where
+        T: Copy,
+    {
+        match self.uniform_bytes(location) {
+            Some(data) if data.len() >= size_of::<T>() => {
+                Some(std::ptr::read_unaligned(data.as_ptr() as *const T))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the element `index` of the storage buffer bound at `location`,
+    /// seen as an array of `T`.
+    ///
+    /// Returns `None` if no buffer is bound there, or if it ends before the
+    /// element.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the element must be a valid value of `T`.
+    pub unsafe fn read_storage<T>(&self, location: u32, index: usize) -> Option<T>
+    where
+        T: Copy,
+    {
+        let data = self.storage_buffer(location)?.try_borrow().ok()?;
+        let offset = index.checked_mul(size_of::<T>())?;
+        if offset.checked_add(size_of::<T>())? > data.len() {
+            return None;
+        }
+
+        Some(std::ptr::read_unaligned(
+            data.as_ptr().add(offset) as *const T
+        ))
+    }
+
+    /// Writes the element `index` of the storage buffer bound at `location`,
+    /// seen as an array of `T`. Writes past the end of the buffer are
+    /// dropped, and so are writes into a buffer that the current draw reads
+    /// vertices from.
+    ///
+    /// # Safety
+    ///
+    /// `T` must have no padding bytes, as every byte of the value is copied
+    /// into the buffer. Numbers, arrays of numbers and `#[repr(C)]` structs
+    /// whose fields leave no gaps qualify.
+    pub unsafe fn write_storage<T>(&self, location: u32, index: usize, value: T)
+    where
+        T: Copy,
+    {
+        let mut data = match self.storage_buffer(location).map(RefCell::try_borrow_mut) {
+            Some(Ok(data)) => data,
+            _ => return,
+        };
+        let bytes =
+            std::slice::from_raw_parts(&value as *const T as *const u8, size_of_val(&value));
+        let range = index
+            .checked_mul(bytes.len())
+            .and_then(|offset| Some(offset..offset.checked_add(bytes.len())?));
+        if let Some(element) = range.and_then(|range| data.get_mut(range)) {
+            element.copy_from_slice(bytes);
+        }
+    }
+
+    fn storage_buffer(&self, location: u32) -> Option<&RefCell<Vec<u8>>> {
+        self.storage
+            .iter()
+            .find(|(storage_location, _data)| *storage_location == location)
+            .map(|(_location, data)| *data)
+    }
+
+    /// Loads a texel of the storage image bound at `location`, `z` being the
+    /// layer, slice or face. Returns transparent black if no image is bound,
+    /// or if the texel lies outside of it.
+    pub fn load_texel(&self, location: u32, [x, y, z]: [u32; 3]) -> [f32; 4] {
+        let surface = match self.storage_texture(location).map(RefCell::try_borrow) {
+            Some(Ok(surface)) => surface,
+            _ => return [0.0; 4],
+        };
+        if x >= surface.width || y >= surface.height || z >= surface.layers {
+            return [0.0; 4];
+        }
+
+        surface.layer_texel(x, y, z)
+    }
+
+    /// Stores a texel of the storage image bound at `location`, rounded to
+    /// the image's format. Stores outside of the image are dropped, and so
+    /// are stores into an image that is also bound as a texture.
+    pub fn store_texel(&self, location: u32, [x, y, z]: [u32; 3], value: [f32; 4]) {
+        let mut surface = match self.storage_texture(location).map(RefCell::try_borrow_mut) {
+            Some(Ok(surface)) => surface,
+            _ => return,
+        };
+        if x < surface.width && y < surface.height && z < surface.layers {
+            surface.set_layer_texel(x, y, z, value);
+        }
+    }
+
+    fn storage_texture(&self, location: u32) -> Option<&RefCell<Surface>> {
+        self.storage_textures
+            .iter()
+            .find(|(texture_location, _surface)| *texture_location == location)
+            .map(|(_location, surface)| *surface)
+    }
+
+    /// The width and height of the texture bound at `location`.
+    pub fn texture_size(&self, location: u32) -> Option<(u32, u32)> {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, _sampler)| (surface.width, surface.height))
+    }
+
+    /// Samples the texture bound at `location` with its sampler. `(0, 0)`
+    /// is the first texel of the image that was loaded into the texture.
+    /// Returns transparent black if no texture is bound.
+    ///
+    /// The texture is sampled at a level of detail of 0, magnified unless
+    /// the sampler's `lod_min_clamp` is larger. Use `sample_grad` to pick
+    /// the mip level from the derivatives of the fragment like the GPU.
+    pub fn sample(&self, location: u32, u: f32, v: f32) -> [f32; 4] {
+        self.sample_level(location, u, v, 0.0)
+    }
+
+    /// Samples the texture bound at `location` at an explicit level of
+    /// detail, like `textureLod` does in GLSL. Returns transparent black if
+    /// no texture is bound.
+    pub fn sample_level(&self, location: u32, u: f32, v: f32, lod: f32) -> [f32; 4] {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample(surface, sampler, u, v, lod))
+            .unwrap_or([0.0; 4])
+    }
+
+    /// Samples the texture bound at `location` with the derivatives of
+    /// `(u, v)` along `x` and `y`, like `textureGrad` does in GLSL. They
+    /// pick the mip level and the direction of anisotropic filtering, see
+    /// `FragmentInput::ddx`. Returns transparent black if no texture is
+    /// bound.
+    pub fn sample_grad(
+        &self,
+        location: u32,
+        u: f32,
+        v: f32,
+        ddx: [f32; 2],
+        ddy: [f32; 2],
+    ) -> [f32; 4] {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample_grad(surface, sampler, u, v, ddx, ddy))
+            .unwrap_or([0.0; 4])
+    }
+
+    /// Samples the depth texture bound at `location` with its comparison
+    /// sampler, returning the fraction of texels for which `reference`
+    /// passes the sampler's compare function. Returns `0` if no texture is
+    /// bound.
+    pub fn sample_compare(&self, location: u32, u: f32, v: f32, reference: f32) -> f32 {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample_compare(surface, sampler, u, v, reference))
+            .unwrap_or(0.0)
+    }
+
+    /// Samples a layer of the texture array bound at `location`, layers
+    /// past the end read the last one. Returns transparent black if no
+    /// texture is bound.
+    pub fn sample_layer(&self, location: u32, u: f32, v: f32, layer: u32) -> [f32; 4] {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample_layer(surface, sampler, u, v, layer))
+            .unwrap_or([0.0; 4])
+    }
+
+    /// Samples the 3D texture bound at `location`, `w` runs from the front
+    /// slice at `0` to the back one at `1`. Returns transparent black if no
+    /// texture is bound.
+    pub fn sample_3d(&self, location: u32, u: f32, v: f32, w: f32) -> [f32; 4] {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample_3d(surface, sampler, u, v, w))
+            .unwrap_or([0.0; 4])
+    }
+
+    /// Samples the cube map bound at `location` in the direction of a
+    /// vector, like `texture(samplerCube, direction)` does in GLSL. Returns
+    /// transparent black if no texture is bound.
+    pub fn sample_cube(&self, location: u32, direction: [f32; 3]) -> [f32; 4] {
+        self.textures
+            .iter()
+            .find(|(texture_location, _surface, _sampler)| *texture_location == location)
+            .map(|(_location, surface, sampler)| sample_cube(surface, sampler, direction))
+            .unwrap_or([0.0; 4])
+    }
+}
+
+pub struct VertexInput<'a> {
+    pub vertex_id: usize,
+    pub instance_id: usize,
+    pub resources: &'a Resources<'a>,
+    pub(crate) attributes: &'a [(u32, [f32; 4])],
+}
+
+impl<'a> VertexInput<'a> {
+    /// The value of the vertex attribute, as described by the pipeline's
+    /// bindings. Attributes that are not bound read as `(0, 0, 0, 1)`.
+    pub fn attribute(&self, attribute: u32) -> [f32; 4] {
+        self.attributes
+            .iter()
+            .find(|(index, _value)| *index == attribute)
+            .map(|(_index, value)| *value)
+            .unwrap_or([0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+pub struct VertexOutput {
+    /// The clip space position of the vertex.
+    pub position: [f32; 4],
+    /// Values that are interpolated (perspective correctly) across the
+    /// primitive and handed to the fragment function.
+    pub varyings: Vec<f32>,
+}
+
+pub struct FragmentInput<'a> {
+    /// The window position of the fragment: pixel center `x` and `y` from
+    /// the top left corner, depth in `z`, and `1 / w` in `w`.
+    pub position: [f32; 4],
+    pub front_facing: bool,
+    pub varyings: &'a [f32],
+    pub resources: &'a Resources<'a>,
+    pub(crate) ddx: &'a [f32],
+    pub(crate) ddy: &'a [f32],
+}
+
+impl<'a> FragmentInput<'a> {
+    /// How much a varying changes from one pixel to the next along `x`,
+    /// within the 2×2 quad of pixels the fragment lies in, like `dFdx`
+    /// does in GLSL. Points and lines have no derivatives and read `0`.
+    pub fn ddx(&self, varying: usize) -> f32 {
+        self.ddx.get(varying).copied().unwrap_or(0.0)
+    }
+
+    /// How much a varying changes from one pixel to the next along `y`,
+    /// see `ddx`.
+    pub fn ddy(&self, varying: usize) -> f32 {
+        self.ddy.get(varying).copied().unwrap_or(0.0)
+    }
+}
+
+pub struct ComputeInput<'a> {
+    /// The position of the invocation among all the invocations of the
+    /// dispatch, `workgroup_id * workgroup_size + local_id`.
+    pub global_id: [u32; 3],
+    pub workgroup_id: [u32; 3],
+    /// The position of the invocation in its workgroup.
+    pub local_id: [u32; 3],
+    pub resources: &'a Resources<'a>,
+}
+
+pub struct Library {
+    pub(crate) vertex_functions: HashMap<String, Rc<dyn VertexFunction>>,
+    pub(crate) fragment_functions: HashMap<String, Rc<dyn FragmentFunction>>,
+    pub(crate) compute_functions: HashMap<String, Rc<dyn ComputeFunction>>,
+}
+
+impl Library {
+    pub(crate) fn new() -> Result<Library> {
+        Ok(Library {
+            vertex_functions: HashMap::new(),
+            fragment_functions: HashMap::new(),
+            compute_functions: HashMap::new(),
+        })
+    }
+
+    /// This is a SOFTWARE ONLY API, shader functions are registered by name
+    /// so they can be looked up the same way as in a compiled library.
+    pub fn add_vertex_function<F>(&mut self, name: &str, function: F)
+    where
+        F: VertexFunction + 'static,
+    {
+        self.vertex_functions
+            .insert(name.into(), Rc::new(function) as Rc<dyn VertexFunction>);
+    }
+
+    /// This is a SOFTWARE ONLY API, shader functions are registered by name
+    /// so they can be looked up the same way as in a compiled library.
+    pub fn add_fragment_function<F>(&mut self, name: &str, function: F)
+    where
+        F: FragmentFunction + 'static,
+    {
+        self.fragment_functions
+            .insert(name.into(), Rc::new(function) as Rc<dyn FragmentFunction>);
+    }
+
+    /// This is a SOFTWARE ONLY API, shader functions are registered by name
+    /// so they can be looked up the same way as in a compiled library.
+    pub fn add_compute_function<F>(&mut self, name: &str, function: F)
+    where
+        F: ComputeFunction + 'static,
+    {
+        self.compute_functions
+            .insert(name.into(), Rc::new(function) as Rc<dyn ComputeFunction>);
+    }
+}
+
+pub struct Shader {
+    pub(crate) vertex_function: Rc<dyn VertexFunction>,
+    pub(crate) fragment_function: Rc<dyn FragmentFunction>,
+}
+
+impl Shader {
+    pub(crate) fn new(library: &Library, vertex_name: &str, fragment_name: &str) -> Result<Shader> {
+        let vertex_function = match library.vertex_functions.get(vertex_name) {
+            Some(function) => Rc::clone(function),
+            None => return Err(Error::MissingFunction(vertex_name.into())),
+        };
+        let fragment_function = match library.fragment_functions.get(fragment_name) {
+            Some(function) => Rc::clone(function),
+            None => return Err(Error::MissingFunction(fragment_name.into())),
+        };
+
+        Ok(Shader {
+            vertex_function,
+            fragment_function,
+        })
+    }
+}
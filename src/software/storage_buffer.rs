@@ -0,0 +1,59 @@
+use crate::software::err::Result;
+use crate::software::internal::{as_bytes, Buffer};
+use crate::Error;
+use std::mem::size_of_val;
+use std::rc::Rc;
+
+pub struct StorageBuffer {
+    pub(crate) buffer: Rc<Buffer>,
+    /// The number of bytes that can be stored in this buffer.
+    pub(crate) capacity: usize,
+}
+
+impl StorageBuffer {
+    pub(crate) fn with_capacity(capacity: usize) -> Result<StorageBuffer> {
+        Ok(StorageBuffer {
+            buffer: Rc::from(Buffer::new(vec![0; capacity])),
+            capacity,
+        })
+    }
+
+    pub(crate) fn with_data<T>(data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        let contents = as_bytes(data).to_vec();
+        let capacity = contents.len();
+
+        Ok(StorageBuffer {
+            buffer: Rc::from(Buffer::new(contents)),
+            capacity,
+        })
+    }
+
+    /// Replaces the start of the buffer, the rest keeps its contents.
+    pub(crate) fn update<T>(&mut self, data: &[T]) -> Result<()>
+    where
+        T: Sized,
+    {
+        let length = size_of_val(data);
+        if length > self.capacity {
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
+        }
+
+        self.buffer.0.borrow_mut()[..length].copy_from_slice(as_bytes(data));
+
+        Ok(())
+    }
+
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        Ok(self.buffer.0.borrow().clone())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,515 @@
+use crate::software::*;
+use crate::{
+    Binding, BlendState, CompareFunction, FilterMode, Operations, PassDesc, PipelineDesc, Rect,
+    SamplerDesc, TextureFormat,
+};
+use image::RgbaImage;
+
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// A quad covering clip space at depth `z`, as a triangle strip whose two
+/// triangles share the diagonal from the top left to the bottom right
+/// corner.
+fn quad(context: &mut Context, z: f32) -> Renderable {
+    let vertices: [[f32; 4]; 4] = [
+        [-1.0, -1.0, z, 1.0],
+        [1.0, -1.0, z, 1.0],
+        [-1.0, 1.0, z, 1.0],
+        [1.0, 1.0, z, 1.0],
+    ];
+    let vertex_buffer = context.create_vertex_buffer_with_data(&vertices).unwrap();
+    context.create_renderable(&[(0, &vertex_buffer)]).unwrap()
+}
+
+/// A pipeline drawing a solid color, with the rest of its state taken from
+/// `desc`.
+fn pipeline(context: &mut Context, color: [f32; 4], desc: &PipelineDesc) -> Pipeline {
+    let mut library = context.create_library("").unwrap();
+    library.add_vertex_function("vertex", |input: &VertexInput| VertexOutput {
+        position: input.attribute(0),
+        varyings: Vec::new(),
+    });
+    library.add_fragment_function("fragment", move |_input: &FragmentInput| Some(color));
+    let shader = context
+        .create_shader(&library, "vertex", "fragment")
+        .unwrap();
+
+    let bindings = [Binding {
+        attribute: 0,
+        buffer: 0,
+        offset: 0,
+        stride: 16,
+        ..Default::default()
+    }];
+    context
+        .create_pipeline(
+            &shader,
+            &PipelineDesc {
+                bindings: &bindings,
+                ..*desc
+            },
+        )
+        .unwrap()
+}
+
+fn red_channel(img: &RgbaImage) -> Vec<Vec<u8>> {
+    img.rows()
+        .map(|row| row.map(|pixel| pixel.0[0]).collect())
+        .collect()
+}
+
+#[test]
+fn shared_edges_are_drawn_once() {
+    let mut context = Context::new(4, 4).unwrap();
+    let pipeline = pipeline(
+        &mut context,
+        [0.25, 0.0, 0.0, 1.0],
+        &PipelineDesc {
+            blend: Some(BlendState::additive()),
+            ..Default::default()
+        },
+    );
+    let quad = quad(&mut context, 0.0);
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.draw(&quad, 4, 1);
+    }
+
+    // The pixel centers on the diagonal lie exactly on the shared edge.
+    assert_eq!(red_channel(&context.color_image()), vec![vec![64; 4]; 4]);
+}
+
+#[test]
+fn depth_test_and_write() {
+    let mut context = Context::new(1, 1).unwrap();
+    let target = context
+        .create_render_target(
+            2,
+            2,
+            &[TextureFormat::Rgba8],
+            Some(TextureFormat::Depth32F),
+            1,
+        )
+        .unwrap();
+    let desc = |depth_write| PipelineDesc {
+        color_formats: &[TextureFormat::Rgba8],
+        depth_format: Some(TextureFormat::Depth32F),
+        depth_compare: Some(CompareFunction::Less),
+        depth_write,
+        ..Default::default()
+    };
+    let draws = [
+        (RED, 0.0, true),
+        // Behind the red quad.
+        ([0.0, 1.0, 0.0, 1.0], 0.5, true),
+        // In front, without writing its depth.
+        ([0.0, 0.0, 1.0, 1.0], -0.5, false),
+        // Behind the blue quad, but in front of the stored depth.
+        ([1.0, 1.0, 0.0, 1.0], -0.25, true),
+    ];
+    let draws: Vec<_> = draws
+        .iter()
+        .map(|&(color, z, depth_write)| {
+            (
+                pipeline(&mut context, color, &desc(depth_write)),
+                quad(&mut context, z),
+            )
+        })
+        .collect();
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.set_render_target(Some(&target));
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            depth: Operations::clear(1.0),
+            ..Default::default()
+        });
+        for (pipeline, quad) in &draws {
+            frame.use_pipeline(pipeline);
+            frame.draw(quad, 4, 1);
+        }
+    }
+
+    let color = context.read_texture(&target.color_textures()[0]).unwrap();
+    assert_eq!(color.get_pixel(1, 1).0, [255, 255, 0, 255]);
+    let depth = context
+        .read_texture(target.depth_texture().unwrap())
+        .unwrap();
+    assert_eq!(depth.get_pixel(1, 1).0, [96, 96, 96, 255]);
+}
+
+#[test]
+fn alpha_blending() {
+    let mut context = Context::new(2, 2).unwrap();
+    let pipeline = pipeline(
+        &mut context,
+        [1.0, 0.0, 0.0, 0.25],
+        &PipelineDesc {
+            blend: Some(BlendState::alpha()),
+            ..Default::default()
+        },
+    );
+    let quad = quad(&mut context, 0.0);
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear([0.0, 0.0, 1.0, 1.0])],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.draw(&quad, 4, 1);
+    }
+
+    assert_eq!(context.color_image().get_pixel(0, 0).0, [64, 0, 191, 255]);
+}
+
+#[test]
+fn scissor_clips_draws() {
+    let mut context = Context::new(4, 4).unwrap();
+    let pipeline = pipeline(&mut context, RED, &PipelineDesc::default());
+    let quad = quad(&mut context, 0.0);
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.set_scissor(Some(Rect {
+            x: 1,
+            y: 2,
+            width: 2,
+            height: 1,
+        }));
+        frame.draw(&quad, 4, 1);
+    }
+
+    assert_eq!(
+        red_channel(&context.color_image()),
+        vec![
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 255, 255, 0],
+            vec![0, 0, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn multisampled_render_targets_are_resolved() {
+    let mut context = Context::new(1, 1).unwrap();
+    let target = context
+        .create_render_target(4, 4, &[TextureFormat::Rgba8], None, 4)
+        .unwrap();
+    let desc = PipelineDesc {
+        color_formats: &[TextureFormat::Rgba8],
+        sample_count: 4,
+        ..Default::default()
+    };
+    let pipeline = pipeline(&mut context, RED, &desc);
+    let vertices: [[f32; 4]; 3] = [
+        [-1.0, -1.0, 0.0, 1.0],
+        [1.0, -1.0, 0.0, 1.0],
+        [-1.0, 1.0, 0.0, 1.0],
+    ];
+    let vertex_buffer = context.create_vertex_buffer_with_data(&vertices).unwrap();
+    let triangle = context.create_renderable(&[(0, &vertex_buffer)]).unwrap();
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.set_render_target(Some(&target));
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.draw(&triangle, 3, 1);
+        frame.end_pass();
+    }
+
+    // The hypotenuse runs through the pixel centers of the diagonal, which
+    // have two of their four samples covered.
+    let color = context.read_texture(&target.color_textures()[0]).unwrap();
+    assert_eq!(
+        red_channel(&color),
+        vec![
+            vec![128, 0, 0, 0],
+            vec![255, 128, 0, 0],
+            vec![255, 255, 128, 0],
+            vec![255, 255, 255, 128],
+        ]
+    );
+}
+
+#[test]
+fn alpha_to_coverage() {
+    let mut context = Context::new(1, 1).unwrap();
+    let target = context
+        .create_render_target(2, 2, &[TextureFormat::Rgba8], None, 4)
+        .unwrap();
+    let pipeline = pipeline(
+        &mut context,
+        [1.0, 0.0, 0.0, 0.5],
+        &PipelineDesc {
+            color_formats: &[TextureFormat::Rgba8],
+            sample_count: 4,
+            alpha_to_coverage: true,
+            ..Default::default()
+        },
+    );
+    let quad = quad(&mut context, 0.0);
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.set_render_target(Some(&target));
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.draw(&quad, 4, 1);
+    }
+
+    // Half of the samples are covered, the resolve happens when the frame
+    // ends the pass.
+    let color = context.read_texture(&target.color_textures()[0]).unwrap();
+    assert_eq!(color.get_pixel(0, 0).0, [128, 0, 0, 192]);
+}
+
+#[allow(deprecated)]
+#[test]
+fn set_clear_color_clears_when_using_a_pipeline() {
+    let mut context = Context::new(2, 2).unwrap();
+    let pipeline = pipeline(&mut context, RED, &PipelineDesc::default());
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.set_clear_color(Some((0.0, 0.0, 1.0, 1.0)));
+        frame.use_pipeline(&pipeline);
+    }
+
+    assert_eq!(context.color_image().get_pixel(1, 1).0, [0, 0, 255, 255]);
+}
+
+#[test]
+#[should_panic(expected = "bound as a texture")]
+fn drawing_into_a_bound_texture_panics() {
+    let mut context = Context::new(1, 1).unwrap();
+    let target = context
+        .create_render_target(2, 2, &[TextureFormat::Rgba8], None, 1)
+        .unwrap();
+    let pipeline = pipeline(
+        &mut context,
+        RED,
+        &PipelineDesc {
+            color_formats: &[TextureFormat::Rgba8],
+            ..Default::default()
+        },
+    );
+    let quad = quad(&mut context, 0.0);
+
+    let mut frame = context.next_frame().unwrap();
+    frame.set_render_target(Some(&target));
+    frame.use_pipeline(&pipeline);
+    frame.use_texture(&target.color_textures()[0], 0, 0);
+    frame.draw(&quad, 4, 1);
+}
+
+/// Draws a quad with texture coordinates running from `(0, 0)` in the top
+/// left corner to `(1, 1)` into a `size` by `size` context, with a 4×4
+/// texture whose three mip levels are red, green and blue bound at
+/// location 0. Returns the first pixel.
+fn sample_mip_levels<F>(size: u32, sampler: &SamplerDesc, sample: F) -> [u8; 4]
+where
+    F: Fn(&FragmentInput) -> [f32; 4] + 'static,
+{
+    let mut context = Context::new(size, size).unwrap();
+    let levels: Vec<Vec<u8>> = [
+        (4, [255, 0, 0, 255]),
+        (2, [0, 255, 0, 255]),
+        (1, [0, 0, 255, 255]),
+    ]
+    .iter()
+    .map(|(size, color)| color.repeat(size * size))
+    .collect();
+    let levels: Vec<&[u8]> = levels.iter().map(|level| level.as_slice()).collect();
+    let texture = context
+        .create_texture_from_mip_levels(4, 4, TextureFormat::Rgba8, &levels, sampler)
+        .unwrap();
+
+    let mut library = context.create_library("").unwrap();
+    library.add_vertex_function("vertex", |input: &VertexInput| {
+        let position = input.attribute(0);
+        VertexOutput {
+            position,
+            varyings: vec![(position[0] + 1.0) / 2.0, (1.0 - position[1]) / 2.0],
+        }
+    });
+    library.add_fragment_function("fragment", move |input: &FragmentInput| Some(sample(input)));
+    let shader = context
+        .create_shader(&library, "vertex", "fragment")
+        .unwrap();
+    let bindings = [Binding {
+        attribute: 0,
+        buffer: 0,
+        offset: 0,
+        stride: 16,
+        ..Default::default()
+    }];
+    let pipeline = context
+        .create_pipeline(
+            &shader,
+            &PipelineDesc {
+                bindings: &bindings,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let quad = quad(&mut context, 0.0);
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.begin_pass(&PassDesc {
+            color: &[Operations::clear(BLACK)],
+            ..Default::default()
+        });
+        frame.use_pipeline(&pipeline);
+        frame.use_texture(&texture, 0, 0);
+        frame.draw(&quad, 4, 1);
+    }
+
+    context.color_image().get_pixel(0, 0).0
+}
+
+#[test]
+fn explicit_lod_picks_mip_levels() {
+    let sampler = |mip_filter, lod_max_clamp| SamplerDesc {
+        mip_filter,
+        lod_max_clamp,
+        ..Default::default()
+    };
+    let at_lod = |lod| move |input: &FragmentInput| input.resources.sample_level(0, 0.5, 0.5, lod);
+
+    let nearest = sampler(Some(FilterMode::Nearest), f32::MAX);
+    assert_eq!(
+        sample_mip_levels(1, &nearest, at_lod(1.4)),
+        [0, 255, 0, 255]
+    );
+    assert_eq!(
+        sample_mip_levels(1, &nearest, at_lod(1.6)),
+        [0, 0, 255, 255]
+    );
+    // Levels past the last one read the last one.
+    assert_eq!(
+        sample_mip_levels(1, &nearest, at_lod(5.0)),
+        [0, 0, 255, 255]
+    );
+
+    let linear = sampler(Some(FilterMode::Linear), f32::MAX);
+    assert_eq!(
+        sample_mip_levels(1, &linear, at_lod(1.5)),
+        [0, 128, 128, 255]
+    );
+
+    let clamped = sampler(Some(FilterMode::Nearest), 1.0);
+    assert_eq!(
+        sample_mip_levels(1, &clamped, at_lod(2.0)),
+        [0, 255, 0, 255]
+    );
+
+    // Without a mip filter, only the first level is read.
+    let single = sampler(None, f32::MAX);
+    assert_eq!(sample_mip_levels(1, &single, at_lod(2.0)), [255, 0, 0, 255]);
+}
+
+#[test]
+fn derivatives_pick_mip_levels() {
+    let sampler = SamplerDesc {
+        mip_filter: Some(FilterMode::Nearest),
+        ..Default::default()
+    };
+    let grad = |input: &FragmentInput| {
+        let (u, v) = (input.varyings[0], input.varyings[1]);
+        let ddx = [input.ddx(0), input.ddx(1)];
+        let ddy = [input.ddy(0), input.ddy(1)];
+        input.resources.sample_grad(0, u, v, ddx, ddy)
+    };
+
+    // Two texels of the first level per pixel.
+    assert_eq!(sample_mip_levels(2, &sampler, grad), [0, 255, 0, 255]);
+    // Four texels per pixel.
+    assert_eq!(sample_mip_levels(1, &sampler, grad), [0, 0, 255, 255]);
+    // Without derivatives the texture is magnified.
+    let sample = |input: &FragmentInput| input.resources.sample(0, 0.5, 0.5);
+    assert_eq!(sample_mip_levels(1, &sampler, sample), [255, 0, 0, 255]);
+}
+
+#[test]
+fn anisotropic_filtering_samples_along_the_major_axis() {
+    let sampler = |max_anisotropy| SamplerDesc {
+        mip_filter: Some(FilterMode::Nearest),
+        max_anisotropy,
+        ..Default::default()
+    };
+    // Two texels per pixel along x, half a texel along y.
+    let grad = |input: &FragmentInput| {
+        input
+            .resources
+            .sample_grad(0, 0.5, 0.5, [0.5, 0.0], [0.0, 0.125])
+    };
+
+    assert_eq!(sample_mip_levels(1, &sampler(1), grad), [0, 255, 0, 255]);
+    assert_eq!(sample_mip_levels(1, &sampler(4), grad), [255, 0, 0, 255]);
+}
+
+#[test]
+fn textures_get_mip_chains_when_the_sampler_filters_between_levels() {
+    let mut context = Context::new(1, 1).unwrap();
+    let sampler = SamplerDesc {
+        mip_filter: Some(FilterMode::Nearest),
+        ..Default::default()
+    };
+    let pixels = [[255, 0, 0, 255], [0, 0, 255, 255]].concat();
+    let texture = context
+        .create_texture_from_pixels(2, 1, TextureFormat::Rgba8, &pixels, &sampler)
+        .unwrap();
+
+    let surface = texture.surface.borrow();
+    assert_eq!(surface.mip_levels.len(), 1);
+    assert_eq!(
+        surface.mip_levels[0].texel(0, 0),
+        [128.0 / 255.0, 0.0, 128.0 / 255.0, 1.0]
+    );
+}
+
+#[test]
+fn storage_accesses_past_the_end_are_dropped() {
+    let mut context = Context::new(1, 1).unwrap();
+    let mut library = context.create_library("").unwrap();
+    library.add_compute_function("compute", |input: &ComputeInput| unsafe {
+        let value: u32 = input.resources.read_storage(0, 0).unwrap();
+        assert_eq!(input.resources.read_storage::<u32>(0, 2), None);
+        assert_eq!(input.resources.read_storage::<u32>(0, usize::MAX), None);
+
+        input.resources.write_storage(0, 1, value + 1);
+        input.resources.write_storage(0, 2, 0u32);
+        input.resources.write_storage(0, usize::MAX, 0u32);
+    });
+    let pipeline = context
+        .create_compute_pipeline(&library, "compute", [1, 1, 1])
+        .unwrap();
+    let storage_buffer = context
+        .create_storage_buffer_with_data(&[41u32, 0])
+        .unwrap();
+    {
+        let mut frame = context.next_frame().unwrap();
+        frame.use_compute_pipeline(&pipeline);
+        frame.use_storage_buffer(&storage_buffer, 0);
+        frame.dispatch(1, 1, 1);
+    }
+
+    let data = context.read_storage_buffer(&storage_buffer).unwrap();
+    assert_eq!(data, [41u32.to_ne_bytes(), 42u32.to_ne_bytes()].concat());
+}
@@ -0,0 +1,446 @@
+use crate::shared::image::{
+    check_mip_levels, check_region, check_update_kind, generate_mip_levels, layer_count,
+    load_rgba_image, mip_level_size,
+};
+use crate::software::err::Result;
+use crate::software::internal::Surface;
+use crate::{
+    AddressMode, BorderColor, CompareFunction, FilterMode, SamplerDesc, TextureFormat, TextureKind,
+    TextureRegion,
+};
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::iter;
+use std::rc::Rc;
+
+pub struct Texture {
+    pub(crate) surface: Rc<RefCell<Surface>>,
+    pub(crate) kind: TextureKind,
+    pub(crate) sampler: SamplerDesc,
+}
+
+impl Texture {
+    pub(crate) fn new(image_path: &str, sampler: &SamplerDesc) -> Result<Texture> {
+        let img = load_rgba_image(image_path)?;
+
+        Texture::with_image(&img, sampler)
+    }
+
+    pub(crate) fn with_image(img: &RgbaImage, sampler: &SamplerDesc) -> Result<Texture> {
+        Texture::with_pixels(
+            TextureKind::D2,
+            img.width(),
+            img.height(),
+            TextureFormat::Rgba8,
+            img,
+            sampler,
+        )
+    }
+
+    /// Creates a texture from tightly packed texels, with a full mip chain
+    /// if the sampler filters between mip levels.
+    pub(crate) fn with_pixels(
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, &[data])?;
+        if sampler.mip_filter.is_none() || format.is_depth() {
+            return Texture::with_mip_levels(kind, width, height, format, &[data], sampler);
+        }
+
+        let generated = generate_mip_levels(kind, width, height, format, data)?;
+        let levels: Vec<&[u8]> = iter::once(data)
+            .chain(generated.iter().map(|level| level.as_slice()))
+            .collect();
+        Texture::with_mip_levels(kind, width, height, format, &levels, sampler)
+    }
+
+    /// Every level holds the layers, slices or cube faces of the texture one
+    /// after the other.
+    pub(crate) fn with_mip_levels(
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, levels)?;
+
+        let mut surfaces = levels.iter().enumerate().map(|(mip_level, data)| {
+            let (width, height) = mip_level_size(width, height, mip_level as u32);
+            let layers = layer_count(kind, mip_level as u32);
+            Surface::from_pixels(width, height, layers, format, data)
+        });
+        let mut surface = surfaces.next().unwrap();
+        surface.mip_levels = surfaces.collect();
+
+        Ok(Texture {
+            surface: Rc::new(RefCell::new(surface)),
+            kind,
+            sampler: *sampler,
+        })
+    }
+
+    pub(crate) fn with_surface(surface: &Rc<RefCell<Surface>>) -> Texture {
+        Texture {
+            surface: Rc::clone(surface),
+            kind: TextureKind::D2,
+            sampler: SamplerDesc::default(),
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        check_update_kind(self.kind)?;
+
+        let mut surface = self.surface.borrow_mut();
+        check_region(
+            surface.width,
+            surface.height,
+            1 + surface.mip_levels.len() as u32,
+            surface.format,
+            region,
+            mip_level,
+            data,
+        )?;
+
+        let surface = surface.mip_level_mut(mip_level as usize);
+        let texels = Surface::from_pixels(region.width, region.height, 1, surface.format, data);
+        for y in 0..region.height {
+            for x in 0..region.width {
+                surface.set_texel(region.x + x, region.y + y, texels.texel(x, y));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads back the texture as an 8 bit RGBA image with the first row at
+    /// the top. Float formats are clamped to `[0, 1]` and depth formats are
+    /// read as grayscale. Only the first layer, slice or face is read.
+    pub fn read_to_image(&self) -> Result<RgbaImage> {
+        Ok(self.surface.borrow().to_rgba_image())
+    }
+}
+
+/// Samples a 2D texture at the level of detail `lod`, see `sample_lod`.
+pub(crate) fn sample(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    u: f32,
+    v: f32,
+    lod: f32,
+) -> [f32; 4] {
+    sample_lod(surface, sampler, lod, |level, mode| {
+        filter(level, sampler, mode, u, v, 0, |texel| texel)
+    })
+}
+
+/// Samples a 2D texture with the derivatives of the texture coordinates
+/// along `x` and `y`, which pick the level of detail like the GPU does.
+/// Anisotropic samplers average up to `max_anisotropy` samples along the
+/// direction the texture is the most minified in, picking the level of
+/// detail of the other direction.
+pub(crate) fn sample_grad(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    u: f32,
+    v: f32,
+    ddx: [f32; 2],
+    ddy: [f32; 2],
+) -> [f32; 4] {
+    let (width, height) = (surface.width as f32, surface.height as f32);
+    let length_x = (ddx[0] * width).hypot(ddx[1] * height);
+    let length_y = (ddy[0] * width).hypot(ddy[1] * height);
+    let (major, minor, axis) = if length_x >= length_y {
+        (length_x, length_y, ddx)
+    } else {
+        (length_y, length_x, ddy)
+    };
+
+    let max_anisotropy = sampler.max_anisotropy.max(1) as f32;
+    let count = (major / minor.max(f32::MIN_POSITIVE))
+        .ceil()
+        .clamp(1.0, max_anisotropy);
+    let lod = (major / count).log2();
+
+    let mut result = [0.0; 4];
+    for i in 0..count as u32 {
+        let t = (i as f32 + 0.5) / count - 0.5;
+        let texel = sample(surface, sampler, u + axis[0] * t, v + axis[1] * t, lod);
+        for (result, texel) in result.iter_mut().zip(&texel) {
+            *result += texel / count;
+        }
+    }
+    result
+}
+
+/// Samples a layer of an array texture, layers past the end read the last
+/// one.
+pub(crate) fn sample_layer(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    u: f32,
+    v: f32,
+    layer: u32,
+) -> [f32; 4] {
+    sample_lod(surface, sampler, 0.0, |level, mode| {
+        let layer = layer.min(level.layers.saturating_sub(1));
+        filter(level, sampler, mode, u, v, layer, |texel| texel)
+    })
+}
+
+/// Samples a 3D texture, filtering between the slices around `w` like
+/// within them and addressing them with `address_mode_w`.
+pub(crate) fn sample_3d(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    u: f32,
+    v: f32,
+    w: f32,
+) -> [f32; 4] {
+    sample_lod(surface, sampler, 0.0, |level, mode| {
+        let z = w * level.layers as f32;
+
+        match mode {
+            FilterMode::Nearest => sample_slice(level, sampler, mode, u, v, z.floor()),
+            FilterMode::Linear => {
+                let z = z - 0.5;
+                let z0 = z.floor();
+                let fz = z - z0;
+
+                let front = sample_slice(level, sampler, mode, u, v, z0);
+                let back = sample_slice(level, sampler, mode, u, v, z0 + 1.0);
+                lerp(front, back, fz)
+            }
+        }
+    })
+}
+
+/// Samples a cube map in the direction of a vector, which does not need to
+/// be normalized. Faces are picked by the largest component, and oriented
+/// like OpenGL's, with the address modes ignored: every face is clamped to
+/// its edges.
+pub(crate) fn sample_cube(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    direction: [f32; 3],
+) -> [f32; 4] {
+    let [x, y, z] = direction;
+    let (face, sc, tc, ma) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x >= 0.0 {
+            (0, -z, -y, x)
+        } else {
+            (1, z, -y, x)
+        }
+    } else if y.abs() >= z.abs() {
+        if y >= 0.0 {
+            (2, x, z, y)
+        } else {
+            (3, x, -z, y)
+        }
+    } else if z >= 0.0 {
+        (4, x, -y, z)
+    } else {
+        (5, -x, -y, z)
+    };
+
+    let sampler = SamplerDesc {
+        address_mode_u: AddressMode::ClampToEdge,
+        address_mode_v: AddressMode::ClampToEdge,
+        ..*sampler
+    };
+    let u = (sc / ma.abs() + 1.0) / 2.0;
+    let v = (tc / ma.abs() + 1.0) / 2.0;
+    sample_lod(surface, &sampler, 0.0, |level, mode| {
+        let face = face.min(level.layers.saturating_sub(1));
+        filter(level, &sampler, mode, u, v, face, |texel| texel)
+    })
+}
+
+/// Compares `reference` with the red channel, the depth, of the texels
+/// `sample` would read, and returns the filtered fraction that passed.
+/// Samplers without a compare function return the filtered depth.
+pub(crate) fn sample_compare(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    u: f32,
+    v: f32,
+    reference: f32,
+) -> f32 {
+    match sampler.compare {
+        Some(compare) => sample_lod(surface, sampler, 0.0, |level, mode| {
+            filter(level, sampler, mode, u, v, 0, |texel| {
+                let passed = compare_function(compare, reference, texel[0]);
+                [if passed { 1.0 } else { 0.0 }, 0.0, 0.0, 0.0]
+            })
+        })[0],
+        None => sample(surface, sampler, u, v, 0.0)[0],
+    }
+}
+
+/// Whether `reference <function> value` holds.
+pub(crate) fn compare_function(function: CompareFunction, reference: f32, value: f32) -> bool {
+    match function {
+        CompareFunction::Never => false,
+        CompareFunction::Less => reference < value,
+        CompareFunction::Equal => reference == value,
+        CompareFunction::LessEqual => reference <= value,
+        CompareFunction::Greater => reference > value,
+        CompareFunction::NotEqual => reference != value,
+        CompareFunction::GreaterEqual => reference >= value,
+        CompareFunction::Always => true,
+    }
+}
+
+/// Picks the mip levels that a level of detail, clamped to the sampler's
+/// LOD range, selects and samples them with `sample`. A level of detail of
+/// 0 or less magnifies the first level with `mag_filter`, larger ones
+/// minify it with `min_filter`, or minify the closest level or the two
+/// closest ones when `mip_filter` is set. Levels past the last one read
+/// the last one.
+fn sample_lod<F>(surface: &Surface, sampler: &SamplerDesc, lod: f32, sample: F) -> [f32; 4]
+where
+    F: Fn(&Surface, FilterMode) -> [f32; 4],
+{
+    let lod = lod.max(sampler.lod_min_clamp).min(sampler.lod_max_clamp);
+    if lod <= 0.0 {
+        return sample(surface, sampler.mag_filter);
+    }
+
+    let last = surface.mip_levels.len() as f32;
+    match sampler.mip_filter {
+        None => sample(surface, sampler.min_filter),
+        Some(FilterMode::Nearest) => {
+            let level = ((lod + 0.5).ceil() - 1.0).min(last);
+            sample(surface.mip_level(level as usize), sampler.min_filter)
+        }
+        Some(FilterMode::Linear) => {
+            let lod = lod.min(last);
+            let level = lod.floor();
+            let fraction = lod - level;
+
+            let finer = sample(surface.mip_level(level as usize), sampler.min_filter);
+            if fraction == 0.0 {
+                return finer;
+            }
+            let coarser = sample(surface.mip_level(level as usize + 1), sampler.min_filter);
+            lerp(finer, coarser, fraction)
+        }
+    }
+}
+
+/// Samples the 3D slice at a whole, possibly out of range, slice
+/// coordinate.
+fn sample_slice(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    mode: FilterMode,
+    u: f32,
+    v: f32,
+    z: f32,
+) -> [f32; 4] {
+    match address(z, surface.layers, sampler.address_mode_w) {
+        Some(layer) => filter(surface, sampler, mode, u, v, layer, |texel| texel),
+        None => border_color(sampler.border_color),
+    }
+}
+
+/// Filters the texels around `(u, v)` of a layer after passing them through
+/// `map`.
+fn filter<F>(
+    surface: &Surface,
+    sampler: &SamplerDesc,
+    mode: FilterMode,
+    u: f32,
+    v: f32,
+    layer: u32,
+    map: F,
+) -> [f32; 4]
+where
+    F: Fn([f32; 4]) -> [f32; 4],
+{
+    if surface.width == 0 || surface.height == 0 {
+        return [0.0; 4];
+    }
+
+    let x = u * surface.width as f32;
+    let y = v * surface.height as f32;
+
+    match mode {
+        FilterMode::Nearest => map(fetch(surface, sampler, x.floor(), y.floor(), layer)),
+        FilterMode::Linear => {
+            let x = x - 0.5;
+            let y = y - 0.5;
+            let x0 = x.floor();
+            let y0 = y.floor();
+            let fx = x - x0;
+            let fy = y - y0;
+
+            let t00 = map(fetch(surface, sampler, x0, y0, layer));
+            let t10 = map(fetch(surface, sampler, x0 + 1.0, y0, layer));
+            let t01 = map(fetch(surface, sampler, x0, y0 + 1.0, layer));
+            let t11 = map(fetch(surface, sampler, x0 + 1.0, y0 + 1.0, layer));
+
+            lerp(lerp(t00, t10, fx), lerp(t01, t11, fx), fy)
+        }
+    }
+}
+
+fn lerp(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for i in 0..4 {
+        result[i] = from[i] + (to[i] - from[i]) * t;
+    }
+    result
+}
+
+/// Reads the texel at whole texel coordinates, which may be outside of the
+/// surface.
+fn fetch(surface: &Surface, sampler: &SamplerDesc, x: f32, y: f32, layer: u32) -> [f32; 4] {
+    let x = address(x, surface.width, sampler.address_mode_u);
+    let y = address(y, surface.height, sampler.address_mode_v);
+
+    match (x, y) {
+        (Some(x), Some(y)) => surface.layer_texel(x, y, layer),
+        _ => border_color(sampler.border_color),
+    }
+}
+
+fn border_color(border_color: BorderColor) -> [f32; 4] {
+    match border_color {
+        BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+        BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+        BorderColor::OpaqueWhite => [1.0, 1.0, 1.0, 1.0],
+    }
+}
+
+/// Maps a texel coordinate into `0..size`, `None` reads the border color.
+fn address(coord: f32, size: u32, mode: AddressMode) -> Option<u32> {
+    let coord = coord as i64;
+    let size = size as i64;
+
+    let coord = match mode {
+        AddressMode::ClampToEdge => coord.clamp(0, size - 1),
+        AddressMode::ClampToBorder if coord < 0 || coord >= size => return None,
+        AddressMode::ClampToBorder => coord,
+        AddressMode::Repeat => coord.rem_euclid(size),
+        AddressMode::MirrorRepeat => {
+            let coord = coord.rem_euclid(2 * size);
+            if coord < size {
+                coord
+            } else {
+                2 * size - 1 - coord
+            }
+        }
+    };
+    Some(coord as u32)
+}
@@ -0,0 +1,53 @@
+use crate::software::err::Result;
+use crate::software::internal::{as_bytes, Buffer};
+use crate::Error;
+use std::mem::size_of;
+use std::rc::Rc;
+
+pub struct UniformBuffer {
+    pub(crate) buffer: Rc<Buffer>,
+    /// The number of bytes that can be stored in this buffer.
+    pub(crate) capacity: usize,
+}
+
+impl UniformBuffer {
+    pub(crate) fn with_capacity(capacity: usize) -> Result<UniformBuffer> {
+        Ok(UniformBuffer {
+            buffer: Rc::from(Buffer::new(vec![0; capacity])),
+            capacity,
+        })
+    }
+
+    pub(crate) fn with_data<T>(data: &T) -> Result<UniformBuffer>
+    where
+        T: Sized,
+    {
+        let contents = as_bytes(std::slice::from_ref(data)).to_vec();
+
+        Ok(UniformBuffer {
+            buffer: Rc::from(Buffer::new(contents)),
+            capacity: size_of::<T>(),
+        })
+    }
+
+    pub(crate) fn update<T>(&mut self, data: &T) -> Result<()>
+    where
+        T: Sized,
+    {
+        let length = size_of::<T>();
+        if length > self.capacity {
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
+        }
+
+        *self.buffer.0.borrow_mut() = as_bytes(std::slice::from_ref(data)).to_vec();
+
+        Ok(())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
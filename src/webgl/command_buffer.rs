@@ -1,64 +1,208 @@
 use crate::webgl::err::Result;
 use crate::webgl::internal::*;
 use crate::webgl::*;
-use crate::Binding;
+use crate::{Binding, Frame, IndexFormat, LoadAction, Operations, PassDesc, Rect};
 use std::rc::Rc;
 use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
 
 pub struct CommandBuffer {
     pub(crate) context: Rc<WebGl2RenderingContext>,
-    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
     pub(crate) pipeline_index: u32,
+    pub(crate) primitive_mode: u32,
     /// A reference to the shader program is needed to be able to set the
     /// uniform block binding.
     pub(crate) shader_program: Option<Rc<Program>>,
     pub(crate) bindings: Vec<Binding>,
+    /// The size of the framebuffer that is drawn into.
+    pub(crate) target_size: (i32, i32),
+    pub(crate) color_attachment_count: usize,
+    pub(crate) scissor_test: bool,
+    /// The multisampled attachments drawn into and the framebuffer they are
+    /// resolved into when the pass ends.
+    pub(crate) resolve: Option<(Rc<Multisample>, Rc<Framebuffer>)>,
+    pub(crate) in_pass: bool,
+    /// See `set_clear_color`.
+    pub(crate) clear_color: Option<(f32, f32, f32, f32)>,
 }
 
 impl CommandBuffer {
     pub(crate) fn new(gl: &Rc<WebGl2RenderingContext>) -> Result<CommandBuffer> {
         Ok(CommandBuffer {
             context: Rc::clone(gl),
-            clear_color: None,
             pipeline_index: 0,
+            primitive_mode: WebGl2RenderingContext::TRIANGLE_STRIP,
             shader_program: None,
             bindings: Vec::new(),
+            target_size: (gl.drawing_buffer_width(), gl.drawing_buffer_height()),
+            color_attachment_count: 1,
+            scissor_test: false,
+            resolve: None,
+            in_pass: false,
+            clear_color: None,
         })
     }
 
+    pub fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        self.end_pass();
+
+        let gl = &self.context;
+        match render_target {
+            Some(render_target) => {
+                match &render_target.multisample {
+                    Some(multisample) => {
+                        gl.bind_framebuffer(
+                            WebGl2RenderingContext::FRAMEBUFFER,
+                            Some(&multisample.framebuffer.framebuffer),
+                        );
+                        self.resolve = Some((
+                            Rc::clone(multisample),
+                            Rc::clone(&render_target.framebuffer),
+                        ));
+                    }
+                    None => {
+                        gl.bind_framebuffer(
+                            WebGl2RenderingContext::FRAMEBUFFER,
+                            Some(&render_target.framebuffer.framebuffer),
+                        );
+                        self.resolve = None;
+                    }
+                }
+                self.target_size = (render_target.width as i32, render_target.height as i32);
+                self.color_attachment_count = render_target.color_textures.len();
+            }
+            None => {
+                gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+                self.resolve = None;
+                self.target_size = (gl.drawing_buffer_width(), gl.drawing_buffer_height());
+                self.color_attachment_count = 1;
+            }
+        }
+
+        self.set_viewport(None);
+        self.set_scissor(None);
+    }
+
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        let [x, y, width, height] = match viewport {
+            Some(viewport) => self.to_gl_rect(viewport),
+            None => [0, 0, self.target_size.0, self.target_size.1],
+        };
+        self.context.viewport(x, y, width, height);
+    }
+
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        let gl = &self.context;
+        match scissor {
+            Some(scissor) => {
+                let [x, y, width, height] = self.to_gl_rect(scissor);
+                gl.scissor(x, y, width, height);
+                gl.enable(WebGl2RenderingContext::SCISSOR_TEST);
+            }
+            None => gl.disable(WebGl2RenderingContext::SCISSOR_TEST),
+        }
+
+        self.scissor_test = scissor.is_some();
+    }
+
+    /// Converts a rectangle with the origin in the top left corner to the
+    /// bottom left origin of WebGL.
+    fn to_gl_rect(&self, rect: Rect) -> [i32; 4] {
+        [
+            rect.x as i32,
+            self.target_size.1 - rect.y as i32 - rect.height as i32,
+            rect.width as i32,
+            rect.height as i32,
+        ]
+    }
+
+    /// Deprecated, see `Frame::set_clear_color`.
+    #[deprecated(note = "clear the attachments with `begin_pass` instead")]
     pub fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
         self.clear_color = clear_color;
     }
 
-    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+    /// Clears the attachments that the pass clears. WebGL has no use for the
+    /// other actions.
+    pub fn begin_pass(&mut self, desc: &PassDesc) {
+        self.end_pass();
+
         let gl = &self.context;
 
-        if let Some((red, green, blue, alpha)) = self.clear_color {
-            gl.clear_color(red, green, blue, alpha);
-            gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+        // The write masks of the previous pipeline apply to clears, and so
+        // does the scissor test.
+        gl.disable(WebGl2RenderingContext::SCISSOR_TEST);
+        gl.color_mask(true, true, true, true);
+        for i in 0..self.color_attachment_count {
+            if let LoadAction::Clear(color) = desc.color_operations(i).load {
+                gl.clear_bufferfv_with_f32_array(WebGl2RenderingContext::COLOR, i as i32, &color);
+            }
         }
+        if let LoadAction::Clear(depth) = desc.depth.load {
+            gl.depth_mask(true);
+            gl.clear_bufferfv_with_f32_array(WebGl2RenderingContext::DEPTH, 0, &[depth]);
+        }
+        if let LoadAction::Clear(stencil) = desc.stencil.load {
+            gl.stencil_mask(0xff);
+            gl.clear_bufferiv_with_i32_array(WebGl2RenderingContext::STENCIL, 0, &[stencil as i32]);
+        }
+        if self.scissor_test {
+            gl.enable(WebGl2RenderingContext::SCISSOR_TEST);
+        }
+
+        self.in_pass = true;
+    }
 
-        if pipeline.depth_test {
-            gl.enable(WebGl2RenderingContext::DEPTH_TEST);
-        } else {
-            gl.disable(WebGl2RenderingContext::DEPTH_TEST);
+    /// Resolves the attachments of a multisampled render target. WebGL has
+    /// no passes, the attachments are always stored.
+    pub fn end_pass(&mut self) {
+        if !self.in_pass {
+            return;
         }
+        self.in_pass = false;
 
-        gl.depth_mask(pipeline.depth_write);
+        if let Some((multisample, framebuffer)) = &self.resolve {
+            let gl = &self.context;
+            gl.disable(WebGl2RenderingContext::SCISSOR_TEST);
+            multisample.resolve(framebuffer);
+            if self.scissor_test {
+                gl.enable(WebGl2RenderingContext::SCISSOR_TEST);
+            }
+        }
+    }
 
-        if pipeline.alpha_blend {
-            gl.blend_func(
-                WebGl2RenderingContext::SRC_ALPHA,
-                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
-            );
-            gl.enable(WebGl2RenderingContext::BLEND);
-        } else {
-            gl.disable(WebGl2RenderingContext::BLEND);
+    pub fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        if let Some((red, green, blue, alpha)) = self.clear_color {
+            let color =
+                vec![Operations::clear([red, green, blue, alpha]); self.color_attachment_count];
+            self.begin_pass(&PassDesc {
+                color: &color,
+                ..Default::default()
+            });
+        } else if !self.in_pass {
+            self.begin_pass(&PassDesc::default());
         }
 
+        let gl = &self.context;
+
+        set_depth_stencil_state(
+            gl,
+            pipeline.depth_compare,
+            pipeline.depth_write,
+            pipeline.stencil.as_ref(),
+        );
+        set_blend_state(
+            gl,
+            pipeline.blend.as_ref(),
+            pipeline.blend_constant,
+            pipeline.color_write_mask,
+            pipeline.alpha_to_coverage,
+        );
+        set_rasterizer_state(gl, &pipeline.rasterizer);
+
         gl.use_program(Some(&pipeline.shader_program.program));
 
         self.pipeline_index = pipeline.index;
+        self.primitive_mode = pipeline.primitive_mode;
         self.shader_program = Some(Rc::clone(&pipeline.shader_program));
         self.bindings = pipeline.bindings.clone();
     }
@@ -78,11 +222,81 @@ impl CommandBuffer {
     pub fn use_texture(&mut self, texture: &Texture, location: WebGlUniformLocation, binding: u32) {
         let gl = &self.context;
         gl.uniform1i(Some(&location), binding as i32);
-        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
-        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture.texture));
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0 + binding);
+        gl.bind_texture(texture_target(texture.kind), Some(&texture.texture));
+    }
+
+    pub fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        match *compute_pipeline {}
+    }
+
+    /// WebGL 2 has no storage buffers, and nothing could be dispatched to
+    /// use them anyway.
+    pub fn use_storage_vertex_buffer(&mut self, _vertex_buffer: &VertexBuffer, _location: u32) {}
+
+    pub fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, _location: u32) {
+        match *storage_buffer {}
     }
 
+    /// WebGL 2 has no image units, and nothing could be dispatched to use
+    /// them anyway.
+    pub fn use_storage_texture(&mut self, _texture: &Texture, _location: u32) {}
+
     pub fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        self.bind_vertex_array(renderable);
+
+        self.context.draw_arrays_instanced(
+            self.primitive_mode,
+            0,
+            vertex_count as i32,
+            instance_count as i32,
+        );
+    }
+
+    /// WebGL 2 has no base vertex draw call, a non zero `base_vertex` is
+    /// emulated by offsetting the per vertex attributes for the draw.
+    pub fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        let format = match &renderable.index_buffer {
+            Some((_buffer, format)) => *format,
+            None => return,
+        };
+
+        self.bind_vertex_array(renderable);
+        if base_vertex != 0 {
+            self.offset_vertex_attributes(renderable, base_vertex as isize);
+        }
+
+        self.context.draw_elements_instanced_with_i32(
+            self.primitive_mode,
+            index_count as i32,
+            match format {
+                IndexFormat::UInt16 => WebGl2RenderingContext::UNSIGNED_SHORT,
+                IndexFormat::UInt32 => WebGl2RenderingContext::UNSIGNED_INT,
+            },
+            (index_offset * format.size()) as i32,
+            instance_count as i32,
+        );
+
+        if base_vertex != 0 {
+            self.offset_vertex_attributes(renderable, 0);
+        }
+    }
+
+    /// No compute pipeline can be used, so there is nothing to dispatch.
+    pub fn dispatch(&mut self, _x: u32, _y: u32, _z: u32) {}
+
+    pub fn memory_barrier(&mut self) {}
+
+    /// Binds the vertex array object of the renderable for the current
+    /// pipeline, creating it on first use.
+    fn bind_vertex_array(&self, renderable: &Renderable) {
         let gl = &self.context;
         let mut vertex_arrays = renderable.vertex_arrays.take();
         match vertex_arrays
@@ -107,21 +321,18 @@ impl CommandBuffer {
                             continue;
                         }
 
-                        gl.vertex_attrib_pointer_with_i32(
-                            binding.attribute,
-                            4,
-                            WebGl2RenderingContext::FLOAT,
-                            false,
-                            binding.stride as i32,
-                            binding.offset as i32,
-                        );
-                        gl.vertex_attrib_divisor(
-                            binding.attribute,
-                            if binding.instanced { 1 } else { 0 },
-                        );
+                        vertex_attrib_pointer(gl, binding, binding.offset as i32);
+                        gl.vertex_attrib_divisor(binding.attribute, binding.instance_step_rate);
                         gl.enable_vertex_attrib_array(binding.attribute);
                     }
 
+                    if let Some((buffer, _format)) = &renderable.index_buffer {
+                        gl.bind_buffer(
+                            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                            Some(&buffer.buffer),
+                        );
+                    }
+
                     vertex_arrays.push((
                         self.pipeline_index,
                         VertexArray {
@@ -139,22 +350,145 @@ impl CommandBuffer {
         }
 
         renderable.vertex_arrays.set(vertex_arrays);
+    }
 
-        gl.draw_arrays_instanced(
-            WebGl2RenderingContext::TRIANGLE_STRIP,
-            0,
-            vertex_count as i32,
-            instance_count as i32,
+    /// Points the per vertex attributes of the bound vertex array object
+    /// `base_vertex` vertices further into their buffers.
+    fn offset_vertex_attributes(&self, renderable: &Renderable, base_vertex: isize) {
+        let gl = &self.context;
+        for binding in &self.bindings {
+            if binding.instance_step_rate != 0 {
+                continue;
+            }
+
+            if let Some((_buffer_index, buffer)) = renderable
+                .vertex_buffers
+                .iter()
+                .find(|(buffer_index, _buffer)| binding.buffer == *buffer_index)
+            {
+                gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer.buffer));
+                let offset = binding.offset as isize + base_vertex * binding.stride as isize;
+                vertex_attrib_pointer(gl, binding, offset as i32);
+            }
+        }
+    }
+}
+
+fn vertex_attrib_pointer(gl: &WebGl2RenderingContext, binding: &Binding, offset: i32) {
+    let format = vertex_attrib_format(binding.format);
+    if format.integer {
+        gl.vertex_attrib_i_pointer_with_i32(
+            binding.attribute,
+            format.size,
+            format.data_type,
+            binding.stride as i32,
+            offset,
+        );
+    } else {
+        gl.vertex_attrib_pointer_with_i32(
+            binding.attribute,
+            format.size,
+            format.data_type,
+            format.normalized,
+            binding.stride as i32,
+            offset,
         );
     }
+}
+
+impl Frame for CommandBuffer {
+    type Device = Context;
+    type TextureLocation = WebGlUniformLocation;
 
-    // pub fn draw_indexed(&self, renderable: &Renderable, index_count: usize, instance_count: usize) {
+    #[allow(deprecated)]
+    fn set_clear_color(&mut self, clear_color: Option<(f32, f32, f32, f32)>) {
+        CommandBuffer::set_clear_color(self, clear_color)
+    }
 
-    // }
+    fn begin_pass(&mut self, desc: &PassDesc) {
+        CommandBuffer::begin_pass(self, desc)
+    }
+
+    fn end_pass(&mut self) {
+        CommandBuffer::end_pass(self)
+    }
+
+    fn use_pipeline(&mut self, pipeline: &Pipeline) {
+        CommandBuffer::use_pipeline(self, pipeline)
+    }
+
+    fn use_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        CommandBuffer::use_compute_pipeline(self, compute_pipeline)
+    }
+
+    fn use_uniform(&mut self, uniform_buffer: &UniformBuffer, location: u32, binding: u32) {
+        CommandBuffer::use_uniform(self, uniform_buffer, location, binding)
+    }
+
+    fn use_texture(&mut self, texture: &Texture, location: WebGlUniformLocation, binding: u32) {
+        CommandBuffer::use_texture(self, texture, location, binding)
+    }
+
+    fn use_storage_vertex_buffer(&mut self, vertex_buffer: &VertexBuffer, location: u32) {
+        CommandBuffer::use_storage_vertex_buffer(self, vertex_buffer, location)
+    }
+
+    fn use_storage_buffer(&mut self, storage_buffer: &StorageBuffer, location: u32) {
+        CommandBuffer::use_storage_buffer(self, storage_buffer, location)
+    }
+
+    fn use_storage_texture(&mut self, texture: &Texture, location: u32) {
+        CommandBuffer::use_storage_texture(self, texture, location)
+    }
+
+    fn draw(&mut self, renderable: &Renderable, vertex_count: usize, instance_count: usize) {
+        CommandBuffer::draw(self, renderable, vertex_count, instance_count)
+    }
+
+    fn draw_indexed(
+        &mut self,
+        renderable: &Renderable,
+        index_count: usize,
+        instance_count: usize,
+        index_offset: usize,
+        base_vertex: i32,
+    ) {
+        CommandBuffer::draw_indexed(
+            self,
+            renderable,
+            index_count,
+            instance_count,
+            index_offset,
+            base_vertex,
+        )
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        CommandBuffer::dispatch(self, x, y, z)
+    }
+
+    fn memory_barrier(&mut self) {
+        CommandBuffer::memory_barrier(self)
+    }
+
+    fn set_render_target(&mut self, render_target: Option<&RenderTarget>) {
+        CommandBuffer::set_render_target(self, render_target)
+    }
+
+    fn set_viewport(&mut self, viewport: Option<Rect>) {
+        CommandBuffer::set_viewport(self, viewport)
+    }
+
+    fn set_scissor(&mut self, scissor: Option<Rect>) {
+        CommandBuffer::set_scissor(self, scissor)
+    }
 }
 
 impl Drop for CommandBuffer {
     fn drop(&mut self) {
+        // The next frame starts drawing to the whole screen.
+        self.set_render_target(None);
+
         self.context.finish();
     }
 }
@@ -0,0 +1,3 @@
+/// WebGL 2 has no compute shaders, so compute pipelines cannot be created
+/// and there is nothing to dispatch.
+pub enum ComputePipeline {}
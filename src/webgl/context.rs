@@ -1,7 +1,10 @@
-use crate::shared::PipelineDesc;
+use crate::shared::image::{decode_image, image_to_pixels, join_layers};
+use crate::shared::{Device, PipelineDesc};
 use crate::webgl::err::Result;
+use crate::webgl::texture::read_pixels;
 use crate::webgl::*;
-use crate::TextureFilter;
+use crate::{Error, Index, SamplerDesc, TextureFormat, TextureKind, TextureRegion};
+use image::{DynamicImage, GenericImageView, RgbaImage};
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlCanvasElement, HtmlElement, WebGl2RenderingContext, WebGlContextAttributes};
@@ -34,10 +37,17 @@ impl Context {
         //     obj
         // };
 
-        let context = canvas
-            .get_context_with_context_options("webgl2", &context_attributes)?
-            .unwrap()
-            .dyn_into::<WebGl2RenderingContext>()?;
+        let context =
+            match canvas.get_context_with_context_options("webgl2", &context_attributes)? {
+                Some(context) => context.dyn_into::<WebGl2RenderingContext>().map_err(|_| {
+                    Error::ContextCreation("casting to WebGl2RenderingContext".into())
+                })?,
+                None => {
+                    return Err(Error::ContextCreation(
+                        "webgl2 is not supported by the canvas".into(),
+                    ))
+                }
+            };
 
         Context::with_context(context)
     }
@@ -70,8 +80,17 @@ impl Context {
         Pipeline::new(shader, desc)
     }
 
+    pub fn create_compute_pipeline(
+        &mut self,
+        _library: &Library,
+        _function_name: &str,
+        _workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        Err(Error::Unsupported("compute pipelines on WebGL 2".into()))
+    }
+
     pub fn create_texture(&mut self, image_path: &str) -> Result<Texture> {
-        Texture::new(&self.context, image_path, TextureFilter::Nearest)
+        Texture::new(&self.context, image_path, &SamplerDesc::default())
     }
 
     pub fn create_uniform_buffer_with_capacity(
@@ -99,12 +118,162 @@ impl Context {
         uniform_buffer.update(data)
     }
 
-    pub fn create_texture_with_filter(
+    pub fn create_texture_with_sampler(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::new(&self.context, image_path, sampler)
+    }
+
+    pub fn create_texture_from_image(
+        &mut self,
+        image: &DynamicImage,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            &self.context,
+            TextureKind::D2,
+            image.width(),
+            image.height(),
+            format,
+            &image_to_pixels(image, format)?,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            &self.context,
+            TextureKind::D2,
+            width,
+            height,
+            format,
+            data,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_from_memory(
+        &mut self,
+        bytes: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        let img = decode_image(bytes)?;
+        self.create_texture_from_image(&img, TextureFormat::Rgba8, sampler)
+    }
+
+    pub fn create_texture_from_mip_levels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_mip_levels(
+            &self.context,
+            TextureKind::D2,
+            width,
+            height,
+            format,
+            levels,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_array_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        let kind = TextureKind::D2Array {
+            layers: layers.len() as u32,
+        };
+        Texture::with_pixels(
+            &self.context,
+            kind,
+            width,
+            height,
+            format,
+            &join_layers(width, height, format, layers)?,
+            sampler,
+        )
+    }
+
+    pub fn create_texture_3d_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_pixels(
+            &self.context,
+            TextureKind::D3 { depth },
+            width,
+            height,
+            format,
+            data,
+            sampler,
+        )
+    }
+
+    pub fn create_cube_texture(
+        &mut self,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_cube_images(&self.context, image_paths, sampler)
+    }
+
+    pub fn create_cube_texture_from_cross(
         &mut self,
         image_path: &str,
-        filter: TextureFilter,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Texture::with_cube_cross(&self.context, image_path, sampler)
+    }
+
+    pub fn create_cube_texture_from_pixels(
+        &mut self,
+        size: u32,
+        format: TextureFormat,
+        faces: &[&[u8]; 6],
+        sampler: &SamplerDesc,
     ) -> Result<Texture> {
-        Texture::new(&self.context, image_path, filter)
+        Texture::with_pixels(
+            &self.context,
+            TextureKind::Cube,
+            size,
+            size,
+            format,
+            &join_layers(size, size, format, faces)?,
+            sampler,
+        )
+    }
+
+    pub fn update_texture(
+        &mut self,
+        texture: &mut Texture,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        texture.update(region, mip_level, data)
     }
 
     pub fn create_vertex_buffer_with_capacity(&mut self, capacity: usize) -> Result<VertexBuffer> {
@@ -135,6 +304,359 @@ impl Context {
     ) -> Result<Renderable> {
         Renderable::new(vertex_buffers)
     }
+
+    pub fn create_storage_buffer_with_capacity(
+        &mut self,
+        _capacity: usize,
+    ) -> Result<StorageBuffer> {
+        Err(Error::Unsupported("storage buffers on WebGL 2".into()))
+    }
+
+    pub fn create_storage_buffer_with_data<T>(&mut self, _data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        Err(Error::Unsupported("storage buffers on WebGL 2".into()))
+    }
+
+    pub fn update_storage_buffer<T>(
+        &mut self,
+        storage_buffer: &mut StorageBuffer,
+        _data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        match *storage_buffer {}
+    }
+
+    pub fn read_storage_buffer(&mut self, storage_buffer: &StorageBuffer) -> Result<Vec<u8>> {
+        match *storage_buffer {}
+    }
+
+    pub fn create_index_buffer<T>(&mut self, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        IndexBuffer::with_data(&self.context, indices)
+    }
+
+    pub fn create_renderable_with_index(
+        &mut self,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        Renderable::with_index(vertex_buffers, index_buffer)
+    }
+
+    pub fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        RenderTarget::new(
+            &self.context,
+            width,
+            height,
+            color_formats,
+            depth_format,
+            sample_count,
+        )
+    }
+
+    /// Reads back the drawing buffer of the canvas.
+    pub fn read_pixels(&mut self) -> Result<RgbaImage> {
+        let gl = &self.context;
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+
+        let mut img = read_pixels(
+            gl,
+            TextureFormat::Rgba8,
+            gl.drawing_buffer_width() as u32,
+            gl.drawing_buffer_height() as u32,
+        )?;
+
+        // WebGL returns the bottom row first.
+        image::imageops::flip_vertical_in_place(&mut img);
+        Ok(img)
+    }
+
+    pub fn read_texture(&mut self, texture: &Texture) -> Result<RgbaImage> {
+        texture.read_to_image()
+    }
+}
+
+impl Device for Context {
+    type Frame = CommandBuffer;
+    type Library = Library;
+    type Shader = Shader;
+    type Pipeline = Pipeline;
+    type ComputePipeline = ComputePipeline;
+    type Texture = Texture;
+    type UniformBuffer = UniformBuffer;
+    type VertexBuffer = VertexBuffer;
+    type StorageBuffer = StorageBuffer;
+    type IndexBuffer = IndexBuffer;
+    type Renderable = Renderable;
+    type RenderTarget = RenderTarget;
+
+    fn next_frame(&mut self) -> Result<CommandBuffer> {
+        Context::next_frame(self)
+    }
+
+    fn create_library(&mut self, library_path: &str) -> Result<Library> {
+        Context::create_library(self, library_path)
+    }
+
+    fn create_shader(
+        &mut self,
+        library: &Library,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) -> Result<Shader> {
+        Context::create_shader(self, library, vertex_name, fragment_name)
+    }
+
+    fn create_pipeline(&mut self, shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        Context::create_pipeline(self, shader, desc)
+    }
+
+    fn create_compute_pipeline(
+        &mut self,
+        library: &Library,
+        function_name: &str,
+        workgroup_size: [u32; 3],
+    ) -> Result<ComputePipeline> {
+        Context::create_compute_pipeline(self, library, function_name, workgroup_size)
+    }
+
+    fn create_texture(&mut self, image_path: &str) -> Result<Texture> {
+        Context::create_texture(self, image_path)
+    }
+
+    fn create_texture_with_sampler(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_with_sampler(self, image_path, sampler)
+    }
+
+    fn create_texture_from_image(
+        &mut self,
+        image: &DynamicImage,
+        format: TextureFormat,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_image(self, image, format, sampler)
+    }
+
+    fn create_texture_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_pixels(self, width, height, format, data, sampler)
+    }
+
+    fn create_texture_from_memory(
+        &mut self,
+        bytes: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_memory(self, bytes, sampler)
+    }
+
+    fn create_texture_from_mip_levels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_from_mip_levels(self, width, height, format, levels, sampler)
+    }
+
+    fn create_texture_array_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_array_from_pixels(self, width, height, format, layers, sampler)
+    }
+
+    fn create_texture_3d_from_pixels(
+        &mut self,
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_texture_3d_from_pixels(self, width, height, depth, format, data, sampler)
+    }
+
+    fn create_cube_texture(
+        &mut self,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture(self, image_paths, sampler)
+    }
+
+    fn create_cube_texture_from_cross(
+        &mut self,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture_from_cross(self, image_path, sampler)
+    }
+
+    fn create_cube_texture_from_pixels(
+        &mut self,
+        size: u32,
+        format: TextureFormat,
+        faces: &[&[u8]; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        Context::create_cube_texture_from_pixels(self, size, format, faces, sampler)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Texture,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        Context::update_texture(self, texture, region, mip_level, data)
+    }
+
+    fn create_uniform_buffer_with_capacity(&mut self, capacity: usize) -> Result<UniformBuffer> {
+        Context::create_uniform_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_uniform_buffer_with_value<T>(&mut self, value: &T) -> Result<UniformBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_uniform_buffer_with_value(self, value)
+    }
+
+    fn update_uniform_buffer<T>(
+        &mut self,
+        uniform_buffer: &mut UniformBuffer,
+        data: &T,
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_uniform_buffer(self, uniform_buffer, data)
+    }
+
+    fn create_vertex_buffer_with_capacity(&mut self, capacity: usize) -> Result<VertexBuffer> {
+        Context::create_vertex_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_vertex_buffer_with_data<T>(&mut self, data: &[T]) -> Result<VertexBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_vertex_buffer_with_data(self, data)
+    }
+
+    fn update_vertex_buffer<T>(
+        &mut self,
+        vertex_buffer: &mut VertexBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_vertex_buffer(self, vertex_buffer, data)
+    }
+
+    fn create_renderable(&mut self, vertex_buffers: &[(u32, &VertexBuffer)]) -> Result<Renderable> {
+        Context::create_renderable(self, vertex_buffers)
+    }
+
+    fn create_storage_buffer_with_capacity(&mut self, capacity: usize) -> Result<StorageBuffer> {
+        Context::create_storage_buffer_with_capacity(self, capacity)
+    }
+
+    fn create_storage_buffer_with_data<T>(&mut self, data: &[T]) -> Result<StorageBuffer>
+    where
+        T: Sized,
+    {
+        Context::create_storage_buffer_with_data(self, data)
+    }
+
+    fn update_storage_buffer<T>(
+        &mut self,
+        storage_buffer: &mut StorageBuffer,
+        data: &[T],
+    ) -> Result<()>
+    where
+        T: Sized,
+    {
+        Context::update_storage_buffer(self, storage_buffer, data)
+    }
+
+    fn read_storage_buffer(&mut self, storage_buffer: &StorageBuffer) -> Result<Vec<u8>> {
+        Context::read_storage_buffer(self, storage_buffer)
+    }
+
+    fn create_index_buffer<T>(&mut self, indices: &[T]) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        Context::create_index_buffer(self, indices)
+    }
+
+    fn create_renderable_with_index(
+        &mut self,
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        Context::create_renderable_with_index(self, vertex_buffers, index_buffer)
+    }
+
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        Context::create_render_target(
+            self,
+            width,
+            height,
+            color_formats,
+            depth_format,
+            sample_count,
+        )
+    }
+
+    fn read_pixels(&mut self) -> Result<RgbaImage> {
+        Context::read_pixels(self)
+    }
+
+    fn read_texture(&mut self, texture: &Texture) -> Result<RgbaImage> {
+        Context::read_texture(self, texture)
+    }
 }
 
 fn element_contents_from_id(id: &str) -> Result<String> {
@@ -148,10 +670,12 @@ fn element_contents_from_id(id: &str) -> Result<String> {
 
     match contents.text_content() {
         Some(contents) => Ok(contents),
-        None => Err(format!(
-            "reading HTML element text content for element with id=\"{}\"",
-            id
-        )
-        .into()),
+        None => Err(Error::Backend(
+            format!(
+                "reading HTML element text content for element with id=\"{}\"",
+                id
+            )
+            .into(),
+        )),
     }
 }
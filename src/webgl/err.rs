@@ -1,3 +1 @@
-use wasm_bindgen::JsValue;
-
-pub type Result<T> = std::result::Result<T, JsValue>;
+pub type Result<T> = std::result::Result<T, crate::Error>;
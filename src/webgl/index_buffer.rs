@@ -0,0 +1,45 @@
+use crate::webgl::err::Result;
+use crate::webgl::internal::Buffer;
+use crate::{Error, Index, IndexFormat};
+use std::mem::size_of_val;
+use std::rc::Rc;
+use web_sys::WebGl2RenderingContext;
+
+pub struct IndexBuffer {
+    pub(crate) buffer: Rc<Buffer>,
+    pub(crate) format: IndexFormat,
+}
+
+impl IndexBuffer {
+    pub(crate) fn with_data<T>(
+        gl: &Rc<WebGl2RenderingContext>,
+        indices: &[T],
+    ) -> Result<IndexBuffer>
+    where
+        T: Index,
+    {
+        if let Some(buffer) = gl.create_buffer() {
+            // Binding to ELEMENT_ARRAY_BUFFER would change the index buffer of
+            // whichever vertex array object is currently bound.
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+            gl.buffer_data_with_u8_array(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                unsafe {
+                    std::slice::from_raw_parts(indices.as_ptr() as *const u8, size_of_val(indices))
+                },
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+
+            Ok(IndexBuffer {
+                buffer: Rc::from(Buffer {
+                    context: Rc::clone(gl),
+                    buffer,
+                }),
+                format: T::FORMAT,
+            })
+        } else {
+            Err(Error::Backend("creating index buffer".into()))
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::webgl::err::Result;
+use crate::{
+    BlendFactor, BlendOperation, BlendState, ColorAttachmentState, ColorWriteMask, Error,
+    PipelineDesc,
+};
+use web_sys::WebGl2RenderingContext;
+
+/// WebGL 2 blends and masks all color attachments the same way, so
+/// attachments with their own state are rejected.
+pub(crate) fn check_color_attachments(desc: &PipelineDesc) -> Result<()> {
+    let state = ColorAttachmentState {
+        blend: desc.blend,
+        write_mask: desc.color_write_mask,
+    };
+    if desc.color_attachments.iter().any(|other| *other != state) {
+        return Err(Error::Unsupported(
+            "color attachments with their own blend state on WebGL".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sets the blend equations, constant, write mask and alpha to coverage used
+/// by the following draws. `None` disables blending.
+pub(crate) fn set_blend_state(
+    gl: &WebGl2RenderingContext,
+    blend: Option<&BlendState>,
+    blend_constant: [f32; 4],
+    color_write_mask: ColorWriteMask,
+    alpha_to_coverage: bool,
+) {
+    if alpha_to_coverage {
+        gl.enable(WebGl2RenderingContext::SAMPLE_ALPHA_TO_COVERAGE);
+    } else {
+        gl.disable(WebGl2RenderingContext::SAMPLE_ALPHA_TO_COVERAGE);
+    }
+
+    match blend {
+        Some(blend) => {
+            gl.blend_func_separate(
+                blend_factor(blend.color.src_factor),
+                blend_factor(blend.color.dst_factor),
+                blend_factor(blend.alpha.src_factor),
+                blend_factor(blend.alpha.dst_factor),
+            );
+            gl.blend_equation_separate(
+                blend_operation(blend.color.operation),
+                blend_operation(blend.alpha.operation),
+            );
+            let [red, green, blue, alpha] = blend_constant;
+            gl.blend_color(red, green, blue, alpha);
+            gl.enable(WebGl2RenderingContext::BLEND);
+        }
+        None => gl.disable(WebGl2RenderingContext::BLEND),
+    }
+
+    gl.color_mask(
+        color_write_mask.red,
+        color_write_mask.green,
+        color_write_mask.blue,
+        color_write_mask.alpha,
+    );
+}
+
+fn blend_factor(factor: BlendFactor) -> u32 {
+    match factor {
+        BlendFactor::Zero => WebGl2RenderingContext::ZERO,
+        BlendFactor::One => WebGl2RenderingContext::ONE,
+        BlendFactor::SrcColor => WebGl2RenderingContext::SRC_COLOR,
+        BlendFactor::OneMinusSrcColor => WebGl2RenderingContext::ONE_MINUS_SRC_COLOR,
+        BlendFactor::SrcAlpha => WebGl2RenderingContext::SRC_ALPHA,
+        BlendFactor::OneMinusSrcAlpha => WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+        BlendFactor::DstColor => WebGl2RenderingContext::DST_COLOR,
+        BlendFactor::OneMinusDstColor => WebGl2RenderingContext::ONE_MINUS_DST_COLOR,
+        BlendFactor::DstAlpha => WebGl2RenderingContext::DST_ALPHA,
+        BlendFactor::OneMinusDstAlpha => WebGl2RenderingContext::ONE_MINUS_DST_ALPHA,
+        BlendFactor::SrcAlphaSaturated => WebGl2RenderingContext::SRC_ALPHA_SATURATE,
+        BlendFactor::Constant => WebGl2RenderingContext::CONSTANT_COLOR,
+        BlendFactor::OneMinusConstant => WebGl2RenderingContext::ONE_MINUS_CONSTANT_COLOR,
+        BlendFactor::ConstantAlpha => WebGl2RenderingContext::CONSTANT_ALPHA,
+        BlendFactor::OneMinusConstantAlpha => WebGl2RenderingContext::ONE_MINUS_CONSTANT_ALPHA,
+    }
+}
+
+fn blend_operation(operation: BlendOperation) -> u32 {
+    match operation {
+        BlendOperation::Add => WebGl2RenderingContext::FUNC_ADD,
+        BlendOperation::Subtract => WebGl2RenderingContext::FUNC_SUBTRACT,
+        BlendOperation::ReverseSubtract => WebGl2RenderingContext::FUNC_REVERSE_SUBTRACT,
+        BlendOperation::Min => WebGl2RenderingContext::MIN,
+        BlendOperation::Max => WebGl2RenderingContext::MAX,
+    }
+}
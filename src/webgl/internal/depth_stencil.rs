@@ -0,0 +1,59 @@
+use crate::webgl::internal::compare_function;
+use crate::{CompareFunction, StencilOperation, StencilState};
+use web_sys::WebGl2RenderingContext;
+
+/// Sets the depth and stencil tests used by the following draws, `None`
+/// disables them.
+pub(crate) fn set_depth_stencil_state(
+    gl: &WebGl2RenderingContext,
+    depth_compare: Option<CompareFunction>,
+    depth_write: bool,
+    stencil: Option<&StencilState>,
+) {
+    match depth_compare {
+        Some(function) => {
+            gl.depth_func(compare_function(function));
+            gl.enable(WebGl2RenderingContext::DEPTH_TEST);
+        }
+        None => gl.disable(WebGl2RenderingContext::DEPTH_TEST),
+    }
+    gl.depth_mask(depth_write);
+
+    match stencil {
+        Some(stencil) => {
+            for (face, state) in [
+                (WebGl2RenderingContext::FRONT, &stencil.front),
+                (WebGl2RenderingContext::BACK, &stencil.back),
+            ] {
+                gl.stencil_func_separate(
+                    face,
+                    compare_function(state.compare),
+                    stencil.reference as i32,
+                    stencil.read_mask as u32,
+                );
+                gl.stencil_op_separate(
+                    face,
+                    stencil_operation(state.fail_op),
+                    stencil_operation(state.depth_fail_op),
+                    stencil_operation(state.pass_op),
+                );
+            }
+            gl.stencil_mask(stencil.write_mask as u32);
+            gl.enable(WebGl2RenderingContext::STENCIL_TEST);
+        }
+        None => gl.disable(WebGl2RenderingContext::STENCIL_TEST),
+    }
+}
+
+fn stencil_operation(operation: StencilOperation) -> u32 {
+    match operation {
+        StencilOperation::Keep => WebGl2RenderingContext::KEEP,
+        StencilOperation::Zero => WebGl2RenderingContext::ZERO,
+        StencilOperation::Replace => WebGl2RenderingContext::REPLACE,
+        StencilOperation::IncrementClamp => WebGl2RenderingContext::INCR,
+        StencilOperation::DecrementClamp => WebGl2RenderingContext::DECR,
+        StencilOperation::Invert => WebGl2RenderingContext::INVERT,
+        StencilOperation::IncrementWrap => WebGl2RenderingContext::INCR_WRAP,
+        StencilOperation::DecrementWrap => WebGl2RenderingContext::DECR_WRAP,
+    }
+}
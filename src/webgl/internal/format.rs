@@ -0,0 +1,125 @@
+use crate::{TextureFormat, TextureKind, VertexFormat};
+use web_sys::WebGl2RenderingContext;
+
+pub(crate) struct VertexAttribFormat {
+    pub(crate) size: i32,
+    pub(crate) data_type: u32,
+    pub(crate) normalized: bool,
+    /// Integer attributes have to be specified with `vertexAttribIPointer`,
+    /// otherwise the shader would see them converted to floats.
+    pub(crate) integer: bool,
+}
+
+pub(crate) fn vertex_attrib_format(format: VertexFormat) -> VertexAttribFormat {
+    let data_type = match format {
+        VertexFormat::Float
+        | VertexFormat::Float2
+        | VertexFormat::Float3
+        | VertexFormat::Float4 => WebGl2RenderingContext::FLOAT,
+        VertexFormat::Half2 | VertexFormat::Half4 => WebGl2RenderingContext::HALF_FLOAT,
+        VertexFormat::UChar4 | VertexFormat::UChar4Normalized => {
+            WebGl2RenderingContext::UNSIGNED_BYTE
+        }
+        VertexFormat::Char4 | VertexFormat::Char4Normalized => WebGl2RenderingContext::BYTE,
+        VertexFormat::UShort2
+        | VertexFormat::UShort4
+        | VertexFormat::UShort2Normalized
+        | VertexFormat::UShort4Normalized => WebGl2RenderingContext::UNSIGNED_SHORT,
+        VertexFormat::Short2
+        | VertexFormat::Short4
+        | VertexFormat::Short2Normalized
+        | VertexFormat::Short4Normalized => WebGl2RenderingContext::SHORT,
+        VertexFormat::Int | VertexFormat::Int2 | VertexFormat::Int3 | VertexFormat::Int4 => {
+            WebGl2RenderingContext::INT
+        }
+        VertexFormat::UInt | VertexFormat::UInt2 | VertexFormat::UInt3 | VertexFormat::UInt4 => {
+            WebGl2RenderingContext::UNSIGNED_INT
+        }
+    };
+
+    VertexAttribFormat {
+        size: format.components() as i32,
+        data_type,
+        normalized: !format.is_integer()
+            && data_type != WebGl2RenderingContext::FLOAT
+            && data_type != WebGl2RenderingContext::HALF_FLOAT,
+        integer: format.is_integer(),
+    }
+}
+
+pub(crate) struct TexImageFormat {
+    pub(crate) internal_format: i32,
+    pub(crate) format: u32,
+    pub(crate) data_type: u32,
+}
+
+pub(crate) fn tex_image_format(format: TextureFormat) -> TexImageFormat {
+    let (internal_format, format, data_type) = match format {
+        TextureFormat::R8 => (
+            WebGl2RenderingContext::R8,
+            WebGl2RenderingContext::RED,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+        ),
+        TextureFormat::Rg8 => (
+            WebGl2RenderingContext::RG8,
+            WebGl2RenderingContext::RG,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+        ),
+        TextureFormat::Rgba8 => (
+            WebGl2RenderingContext::RGBA8,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+        ),
+        TextureFormat::Rgba8Srgb => (
+            WebGl2RenderingContext::SRGB8_ALPHA8,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+        ),
+        TextureFormat::R16F => (
+            WebGl2RenderingContext::R16F,
+            WebGl2RenderingContext::RED,
+            WebGl2RenderingContext::HALF_FLOAT,
+        ),
+        TextureFormat::Rgba16F => (
+            WebGl2RenderingContext::RGBA16F,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::HALF_FLOAT,
+        ),
+        TextureFormat::R32F => (
+            WebGl2RenderingContext::R32F,
+            WebGl2RenderingContext::RED,
+            WebGl2RenderingContext::FLOAT,
+        ),
+        TextureFormat::Rgba32F => (
+            WebGl2RenderingContext::RGBA32F,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::FLOAT,
+        ),
+        TextureFormat::Depth24Stencil8 => (
+            WebGl2RenderingContext::DEPTH24_STENCIL8,
+            WebGl2RenderingContext::DEPTH_STENCIL,
+            WebGl2RenderingContext::UNSIGNED_INT_24_8,
+        ),
+        TextureFormat::Depth32F => (
+            WebGl2RenderingContext::DEPTH_COMPONENT32F,
+            WebGl2RenderingContext::DEPTH_COMPONENT,
+            WebGl2RenderingContext::FLOAT,
+        ),
+    };
+
+    TexImageFormat {
+        internal_format: internal_format as i32,
+        format,
+        data_type,
+    }
+}
+
+/// The target a texture of the kind is bound to.
+pub(crate) fn texture_target(kind: TextureKind) -> u32 {
+    match kind {
+        TextureKind::D2 => WebGl2RenderingContext::TEXTURE_2D,
+        TextureKind::D2Array { .. } => WebGl2RenderingContext::TEXTURE_2D_ARRAY,
+        TextureKind::D3 { .. } => WebGl2RenderingContext::TEXTURE_3D,
+        TextureKind::Cube => WebGl2RenderingContext::TEXTURE_CUBE_MAP,
+    }
+}
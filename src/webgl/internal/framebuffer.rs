@@ -0,0 +1,125 @@
+use crate::webgl::err::Result;
+use crate::Error;
+use std::rc::Rc;
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer, WebGlRenderbuffer};
+
+pub(crate) struct Framebuffer {
+    pub(crate) context: Rc<WebGl2RenderingContext>,
+    pub(crate) framebuffer: WebGlFramebuffer,
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.context.delete_framebuffer(Some(&self.framebuffer));
+    }
+}
+
+pub(crate) struct Renderbuffer {
+    pub(crate) context: Rc<WebGl2RenderingContext>,
+    pub(crate) renderbuffer: WebGlRenderbuffer,
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        self.context.delete_renderbuffer(Some(&self.renderbuffer));
+    }
+}
+
+/// The multisampled attachments of a render target, which passes draw into
+/// instead of its textures.
+pub(crate) struct Multisample {
+    pub(crate) framebuffer: Framebuffer,
+    /// Kept alive as long as the framebuffer.
+    pub(crate) _renderbuffers: Vec<Renderbuffer>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) color_attachment_count: u32,
+    pub(crate) depth: bool,
+}
+
+impl Multisample {
+    /// Blits the samples of the color and depth attachments into the
+    /// textures of `destination`, leaving the multisampled framebuffer bound.
+    /// The scissor test applies to blits, so it has to be disabled.
+    pub(crate) fn resolve(&self, destination: &Framebuffer) {
+        let gl = &self.framebuffer.context;
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::READ_FRAMEBUFFER,
+            Some(&self.framebuffer.framebuffer),
+        );
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+            Some(&destination.framebuffer),
+        );
+        let (width, height) = (self.width as i32, self.height as i32);
+
+        // Colors are blitted from the read buffer into every draw buffer, so
+        // the attachments are resolved one at a time.
+        for i in 0..self.color_attachment_count {
+            let attachment = WebGl2RenderingContext::COLOR_ATTACHMENT0 + i;
+            let draw_buffers = js_sys::Array::new();
+            for _ in 0..i {
+                draw_buffers.push(&WebGl2RenderingContext::NONE.into());
+            }
+            draw_buffers.push(&attachment.into());
+            gl.read_buffer(attachment);
+            gl.draw_buffers(&draw_buffers);
+            gl.blit_framebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                WebGl2RenderingContext::COLOR_BUFFER_BIT,
+                WebGl2RenderingContext::NEAREST,
+            );
+        }
+        // The draw buffers belong to the destination, which is drawn into
+        // with all of them when it is not multisampled.
+        if self.color_attachment_count > 1 {
+            let draw_buffers = js_sys::Array::new();
+            for i in 0..self.color_attachment_count {
+                draw_buffers.push(&(WebGl2RenderingContext::COLOR_ATTACHMENT0 + i).into());
+            }
+            gl.draw_buffers(&draw_buffers);
+        }
+        if self.depth {
+            gl.blit_framebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                WebGl2RenderingContext::DEPTH_BUFFER_BIT,
+                WebGl2RenderingContext::NEAREST,
+            );
+        }
+
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            Some(&self.framebuffer.framebuffer),
+        );
+    }
+}
+
+/// Checks that the bound framebuffer can be drawn into.
+pub(crate) fn check_framebuffer_status(gl: &WebGl2RenderingContext) -> Result<()> {
+    let status = gl.check_framebuffer_status(WebGl2RenderingContext::FRAMEBUFFER);
+    if status != WebGl2RenderingContext::FRAMEBUFFER_COMPLETE {
+        return Err(Error::Backend(
+            format!(
+                "creating render target: incomplete framebuffer ({:#x})",
+                status
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::webgl::err::Result;
+use crate::{CullMode, Error, FrontFace, PolygonMode, RasterizerState};
+use web_sys::WebGl2RenderingContext;
+
+/// WebGL has neither polygon modes nor depth clamping, so drawing the edges
+/// of triangles and clamping the depth are rejected.
+pub(crate) fn check_rasterizer_state(rasterizer: &RasterizerState) -> Result<()> {
+    if rasterizer.polygon_mode != PolygonMode::Fill {
+        return Err(Error::Unsupported("line polygon mode on WebGL".into()));
+    }
+    if rasterizer.depth_clamp {
+        return Err(Error::Unsupported("depth clamping on WebGL".into()));
+    }
+
+    Ok(())
+}
+
+/// Sets the face culling and depth bias used by the following draws, from
+/// a state that passed `check_rasterizer_state`.
+pub(crate) fn set_rasterizer_state(gl: &WebGl2RenderingContext, rasterizer: &RasterizerState) {
+    match rasterizer.cull_mode {
+        CullMode::None => gl.disable(WebGl2RenderingContext::CULL_FACE),
+        CullMode::Front => {
+            gl.cull_face(WebGl2RenderingContext::FRONT);
+            gl.enable(WebGl2RenderingContext::CULL_FACE);
+        }
+        CullMode::Back => {
+            gl.cull_face(WebGl2RenderingContext::BACK);
+            gl.enable(WebGl2RenderingContext::CULL_FACE);
+        }
+    }
+
+    gl.front_face(match rasterizer.front_face {
+        FrontFace::CounterClockwise => WebGl2RenderingContext::CCW,
+        FrontFace::Clockwise => WebGl2RenderingContext::CW,
+    });
+
+    if rasterizer.depth_bias != 0.0 || rasterizer.depth_bias_slope_scale != 0.0 {
+        gl.polygon_offset(rasterizer.depth_bias_slope_scale, rasterizer.depth_bias);
+        gl.enable(WebGl2RenderingContext::POLYGON_OFFSET_FILL);
+    } else {
+        gl.disable(WebGl2RenderingContext::POLYGON_OFFSET_FILL);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::webgl::err::Result;
+use crate::{AddressMode, CompareFunction, Error, FilterMode, SamplerDesc};
+use web_sys::WebGl2RenderingContext;
+
+/// From `EXT_texture_filter_anisotropic`, which web-sys has no constants for.
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// WebGL has no border color, so `ClampToBorder` is rejected.
+pub(crate) fn check_sampler(sampler: &SamplerDesc) -> Result<()> {
+    let address_modes = [
+        sampler.address_mode_u,
+        sampler.address_mode_v,
+        sampler.address_mode_w,
+    ];
+    if address_modes.contains(&AddressMode::ClampToBorder) {
+        return Err(Error::Unsupported("clamping to the border on WebGL".into()));
+    }
+
+    Ok(())
+}
+
+/// Applies a sampler that passed `check_sampler` to the texture bound to
+/// `target`, which has `mip_level_count` levels. Mip filters on a single
+/// level texture sample that level.
+pub(crate) fn set_sampler_parameters(
+    gl: &WebGl2RenderingContext,
+    target: u32,
+    sampler: &SamplerDesc,
+    mip_level_count: u32,
+) {
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_WRAP_S,
+        address_mode(sampler.address_mode_u),
+    );
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_WRAP_T,
+        address_mode(sampler.address_mode_v),
+    );
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_WRAP_R,
+        address_mode(sampler.address_mode_w),
+    );
+
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+        match sampler.mag_filter {
+            FilterMode::Nearest => WebGl2RenderingContext::NEAREST,
+            FilterMode::Linear => WebGl2RenderingContext::LINEAR,
+        } as i32,
+    );
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+        min_filter(sampler.min_filter, sampler.mip_filter) as i32,
+    );
+    gl.tex_parameteri(
+        target,
+        WebGl2RenderingContext::TEXTURE_MAX_LEVEL,
+        mip_level_count as i32 - 1,
+    );
+    gl.tex_parameterf(
+        target,
+        WebGl2RenderingContext::TEXTURE_MIN_LOD,
+        sampler.lod_min_clamp,
+    );
+    gl.tex_parameterf(
+        target,
+        WebGl2RenderingContext::TEXTURE_MAX_LOD,
+        sampler.lod_max_clamp,
+    );
+
+    if sampler.max_anisotropy > 1 {
+        if let Ok(Some(_)) = gl.get_extension("EXT_texture_filter_anisotropic") {
+            let max_anisotropy = gl
+                .get_parameter(MAX_TEXTURE_MAX_ANISOTROPY)
+                .ok()
+                .and_then(|value| value.as_f64())
+                .unwrap_or(1.0);
+            gl.tex_parameterf(
+                target,
+                TEXTURE_MAX_ANISOTROPY,
+                (sampler.max_anisotropy as f32).min(max_anisotropy as f32),
+            );
+        }
+    }
+
+    match sampler.compare {
+        Some(compare) => {
+            gl.tex_parameteri(
+                target,
+                WebGl2RenderingContext::TEXTURE_COMPARE_MODE,
+                WebGl2RenderingContext::COMPARE_REF_TO_TEXTURE as i32,
+            );
+            gl.tex_parameteri(
+                target,
+                WebGl2RenderingContext::TEXTURE_COMPARE_FUNC,
+                compare_function(compare) as i32,
+            );
+        }
+        None => gl.tex_parameteri(
+            target,
+            WebGl2RenderingContext::TEXTURE_COMPARE_MODE,
+            WebGl2RenderingContext::NONE as i32,
+        ),
+    }
+}
+
+fn address_mode(mode: AddressMode) -> i32 {
+    (match mode {
+        // `ClampToBorder` is rejected by `check_sampler`.
+        AddressMode::ClampToEdge | AddressMode::ClampToBorder => {
+            WebGl2RenderingContext::CLAMP_TO_EDGE
+        }
+        AddressMode::Repeat => WebGl2RenderingContext::REPEAT,
+        AddressMode::MirrorRepeat => WebGl2RenderingContext::MIRRORED_REPEAT,
+    }) as i32
+}
+
+fn min_filter(min_filter: FilterMode, mip_filter: Option<FilterMode>) -> u32 {
+    match (min_filter, mip_filter) {
+        (FilterMode::Nearest, None) => WebGl2RenderingContext::NEAREST,
+        (FilterMode::Linear, None) => WebGl2RenderingContext::LINEAR,
+        (FilterMode::Nearest, Some(FilterMode::Nearest)) => {
+            WebGl2RenderingContext::NEAREST_MIPMAP_NEAREST
+        }
+        (FilterMode::Linear, Some(FilterMode::Nearest)) => {
+            WebGl2RenderingContext::LINEAR_MIPMAP_NEAREST
+        }
+        (FilterMode::Nearest, Some(FilterMode::Linear)) => {
+            WebGl2RenderingContext::NEAREST_MIPMAP_LINEAR
+        }
+        (FilterMode::Linear, Some(FilterMode::Linear)) => {
+            WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR
+        }
+    }
+}
+
+pub(crate) fn compare_function(function: CompareFunction) -> u32 {
+    match function {
+        CompareFunction::Never => WebGl2RenderingContext::NEVER,
+        CompareFunction::Less => WebGl2RenderingContext::LESS,
+        CompareFunction::Equal => WebGl2RenderingContext::EQUAL,
+        CompareFunction::LessEqual => WebGl2RenderingContext::LEQUAL,
+        CompareFunction::Greater => WebGl2RenderingContext::GREATER,
+        CompareFunction::NotEqual => WebGl2RenderingContext::NOTEQUAL,
+        CompareFunction::GreaterEqual => WebGl2RenderingContext::GEQUAL,
+        CompareFunction::Always => WebGl2RenderingContext::ALWAYS,
+    }
+}
@@ -1,22 +1,35 @@
-use crate::shared::{Binding, PipelineDesc};
+use crate::shared::{
+    Binding, BlendState, ColorWriteMask, CompareFunction, PipelineDesc, PrimitiveTopology,
+    RasterizerState, StencilState,
+};
 use crate::webgl::err::Result;
-use crate::webgl::internal::Program;
+use crate::webgl::internal::{check_color_attachments, check_rasterizer_state, Program};
 use crate::webgl::Shader;
 use std::rc::Rc;
+use web_sys::WebGl2RenderingContext;
 
 pub struct Pipeline {
     /// An internal counter used by renderables to match the pipeline with a
     /// corresponding vertex array object.
     pub(crate) index: u32,
     pub(crate) shader_program: Rc<Program>,
-    pub(crate) depth_test: bool,
+    pub(crate) primitive_mode: u32,
+    pub(crate) depth_compare: Option<CompareFunction>,
     pub(crate) depth_write: bool,
-    pub(crate) alpha_blend: bool,
+    pub(crate) stencil: Option<StencilState>,
+    pub(crate) blend: Option<BlendState>,
+    pub(crate) blend_constant: [f32; 4],
+    pub(crate) alpha_to_coverage: bool,
+    pub(crate) color_write_mask: ColorWriteMask,
+    pub(crate) rasterizer: RasterizerState,
     pub(crate) bindings: Vec<Binding>,
 }
 
 impl Pipeline {
     pub(crate) fn new(shader: &Shader, desc: &PipelineDesc) -> Result<Pipeline> {
+        check_rasterizer_state(&desc.rasterizer)?;
+        check_color_attachments(desc)?;
+
         static mut NEXT_INDEX: u32 = 0;
 
         let index = unsafe {
@@ -29,9 +42,21 @@ impl Pipeline {
         Ok(Pipeline {
             index,
             shader_program: shader.program.clone(),
-            depth_test: desc.depth_test,
+            primitive_mode: match desc.topology {
+                PrimitiveTopology::Points => WebGl2RenderingContext::POINTS,
+                PrimitiveTopology::Lines => WebGl2RenderingContext::LINES,
+                PrimitiveTopology::LineStrip => WebGl2RenderingContext::LINE_STRIP,
+                PrimitiveTopology::Triangles => WebGl2RenderingContext::TRIANGLES,
+                PrimitiveTopology::TriangleStrip => WebGl2RenderingContext::TRIANGLE_STRIP,
+            },
+            depth_compare: desc.depth_compare,
             depth_write: desc.depth_write,
-            alpha_blend: desc.alpha_blend,
+            stencil: desc.stencil,
+            blend: desc.blend,
+            blend_constant: desc.blend_constant,
+            alpha_to_coverage: desc.alpha_to_coverage,
+            color_write_mask: desc.color_write_mask,
+            rasterizer: desc.rasterizer,
             bindings: Vec::from(desc.bindings),
         })
     }
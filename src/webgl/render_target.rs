@@ -0,0 +1,219 @@
+use crate::shared::image::check_sample_count;
+use crate::webgl::err::Result;
+use crate::webgl::internal::{
+    check_framebuffer_status, tex_image_format, Framebuffer, Multisample, Renderbuffer,
+};
+use crate::webgl::Texture;
+use crate::{Attachments, Error, TextureFormat};
+use std::rc::Rc;
+use web_sys::WebGl2RenderingContext;
+
+pub struct RenderTarget {
+    /// The framebuffer of the textures.
+    pub(crate) framebuffer: Rc<Framebuffer>,
+    /// Drawn into instead of `framebuffer` with more than one sample, and
+    /// resolved into it when a pass ends.
+    pub(crate) multisample: Option<Rc<Multisample>>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) sample_count: u32,
+    pub(crate) color_textures: Vec<Texture>,
+    pub(crate) depth_texture: Option<Texture>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        gl: &Rc<WebGl2RenderingContext>,
+        width: u32,
+        height: u32,
+        color_formats: &[TextureFormat],
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<RenderTarget> {
+        check_sample_count(sample_count)?;
+
+        let framebuffer = new_framebuffer(gl)?;
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            Some(&framebuffer.framebuffer),
+        );
+
+        let mut color_textures = Vec::with_capacity(color_formats.len());
+        let draw_buffers = js_sys::Array::new();
+        for (i, format) in color_formats.iter().enumerate() {
+            let texture = Texture::with_format(gl, width, height, *format)?;
+            let attachment = WebGl2RenderingContext::COLOR_ATTACHMENT0 + i as u32;
+            gl.framebuffer_texture_2d(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                attachment,
+                WebGl2RenderingContext::TEXTURE_2D,
+                Some(&texture.texture),
+                0,
+            );
+            color_textures.push(texture);
+            draw_buffers.push(&attachment.into());
+        }
+        gl.draw_buffers(&draw_buffers);
+
+        let depth_texture = match depth_format {
+            Some(format) => {
+                let texture = Texture::with_format(gl, width, height, format)?;
+                gl.framebuffer_texture_2d(
+                    WebGl2RenderingContext::FRAMEBUFFER,
+                    depth_attachment(format),
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    Some(&texture.texture),
+                    0,
+                );
+                Some(texture)
+            }
+            None => None,
+        };
+
+        let status = check_framebuffer_status(gl);
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+        status?;
+
+        let multisample = if sample_count > 1 {
+            Some(Rc::new(new_multisample(
+                gl,
+                width,
+                height,
+                color_formats,
+                depth_format,
+                sample_count,
+            )?))
+        } else {
+            None
+        };
+
+        Ok(RenderTarget {
+            framebuffer: Rc::new(framebuffer),
+            multisample,
+            width,
+            height,
+            sample_count,
+            color_textures,
+            depth_texture,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.color_textures
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+}
+
+impl Attachments for RenderTarget {
+    type Texture = Texture;
+
+    fn color_textures(&self) -> &[Texture] {
+        RenderTarget::color_textures(self)
+    }
+
+    fn depth_texture(&self) -> Option<&Texture> {
+        RenderTarget::depth_texture(self)
+    }
+}
+
+fn new_framebuffer(gl: &Rc<WebGl2RenderingContext>) -> Result<Framebuffer> {
+    match gl.create_framebuffer() {
+        Some(framebuffer) => Ok(Framebuffer {
+            context: Rc::clone(gl),
+            framebuffer,
+        }),
+        None => Err(Error::Backend("creating render target".into())),
+    }
+}
+
+/// Creates a framebuffer of multisampled renderbuffers in the formats.
+fn new_multisample(
+    gl: &Rc<WebGl2RenderingContext>,
+    width: u32,
+    height: u32,
+    color_formats: &[TextureFormat],
+    depth_format: Option<TextureFormat>,
+    sample_count: u32,
+) -> Result<Multisample> {
+    let framebuffer = new_framebuffer(gl)?;
+    gl.bind_framebuffer(
+        WebGl2RenderingContext::FRAMEBUFFER,
+        Some(&framebuffer.framebuffer),
+    );
+
+    let mut renderbuffers = Vec::with_capacity(color_formats.len() + 1);
+    let draw_buffers = js_sys::Array::new();
+    let mut attach = |attachment, format| -> Result<()> {
+        let renderbuffer = match gl.create_renderbuffer() {
+            Some(renderbuffer) => Renderbuffer {
+                context: Rc::clone(gl),
+                renderbuffer,
+            },
+            None => return Err(Error::Backend("creating render target".into())),
+        };
+        gl.bind_renderbuffer(
+            WebGl2RenderingContext::RENDERBUFFER,
+            Some(&renderbuffer.renderbuffer),
+        );
+        gl.renderbuffer_storage_multisample(
+            WebGl2RenderingContext::RENDERBUFFER,
+            sample_count as i32,
+            tex_image_format(format).internal_format as u32,
+            width as i32,
+            height as i32,
+        );
+        gl.framebuffer_renderbuffer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            attachment,
+            WebGl2RenderingContext::RENDERBUFFER,
+            Some(&renderbuffer.renderbuffer),
+        );
+        renderbuffers.push(renderbuffer);
+        Ok(())
+    };
+    for (i, format) in color_formats.iter().enumerate() {
+        let attachment = WebGl2RenderingContext::COLOR_ATTACHMENT0 + i as u32;
+        attach(attachment, *format)?;
+        draw_buffers.push(&attachment.into());
+    }
+    if let Some(format) = depth_format {
+        attach(depth_attachment(format), format)?;
+    }
+    gl.draw_buffers(&draw_buffers);
+
+    let status = check_framebuffer_status(gl);
+    gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+    status?;
+
+    Ok(Multisample {
+        framebuffer,
+        _renderbuffers: renderbuffers,
+        width,
+        height,
+        color_attachment_count: color_formats.len() as u32,
+        depth: depth_format.is_some(),
+    })
+}
+
+fn depth_attachment(format: TextureFormat) -> u32 {
+    if format.has_stencil() {
+        WebGl2RenderingContext::DEPTH_STENCIL_ATTACHMENT
+    } else {
+        WebGl2RenderingContext::DEPTH_ATTACHMENT
+    }
+}
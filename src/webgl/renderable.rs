@@ -1,6 +1,7 @@
 use crate::webgl::err::Result;
 use crate::webgl::internal::Buffer;
-use crate::webgl::VertexBuffer;
+use crate::webgl::{IndexBuffer, VertexBuffer};
+use crate::IndexFormat;
 use std::cell::Cell;
 use std::rc::Rc;
 use web_sys::{WebGl2RenderingContext, WebGlVertexArrayObject};
@@ -8,6 +9,7 @@ use web_sys::{WebGl2RenderingContext, WebGlVertexArrayObject};
 pub struct Renderable {
     pub(crate) vertex_arrays: Cell<Vec<(u32, VertexArray)>>,
     pub(crate) vertex_buffers: Vec<(u32, Rc<Buffer>)>,
+    pub(crate) index_buffer: Option<(Rc<Buffer>, IndexFormat)>,
 }
 
 impl Renderable {
@@ -18,8 +20,18 @@ impl Renderable {
                 .iter()
                 .map(|(i, vertex_buffer)| (*i, vertex_buffer.buffer.clone()))
                 .collect(),
+            index_buffer: None,
         })
     }
+
+    pub(crate) fn with_index(
+        vertex_buffers: &[(u32, &VertexBuffer)],
+        index_buffer: &IndexBuffer,
+    ) -> Result<Renderable> {
+        let mut renderable = Renderable::new(vertex_buffers)?;
+        renderable.index_buffer = Some((Rc::clone(&index_buffer.buffer), index_buffer.format));
+        Ok(renderable)
+    }
 }
 
 pub(crate) struct VertexArray {
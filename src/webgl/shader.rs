@@ -1,5 +1,6 @@
 use crate::webgl::err::Result;
 use crate::webgl::internal::Program;
+use crate::{Error, ShaderStage};
 use std::rc::Rc;
 use std::str;
 use web_sys::{WebGl2RenderingContext, WebGlShader, WebGlUniformLocation};
@@ -28,17 +29,15 @@ impl Shader {
         vertex_source: &str,
         fragment_source: &str,
     ) -> Result<Shader> {
-        let vertex_shader =
-            compile_shader(gl, WebGl2RenderingContext::VERTEX_SHADER, vertex_source)?;
-        let fragment_shader =
-            compile_shader(gl, WebGl2RenderingContext::FRAGMENT_SHADER, fragment_source)?;
+        let vertex_shader = compile_shader(gl, ShaderStage::Vertex, vertex_source)?;
+        let fragment_shader = compile_shader(gl, ShaderStage::Fragment, fragment_source)?;
         if let Some(program) = gl.create_program() {
             gl.attach_shader(&program, &vertex_shader);
             gl.attach_shader(&program, &fragment_shader);
             gl.link_program(&program);
             if let Some(log) = gl.get_program_info_log(&program) {
                 if log.len() > 0 {
-                    return Err(log.into());
+                    return Err(Error::ProgramLink { log });
                 }
             }
             Ok(Shader {
@@ -49,7 +48,7 @@ impl Shader {
                 }),
             })
         } else {
-            Err("creating shader program".into())
+            Err(Error::Backend("creating shader program".into()))
         }
     }
 
@@ -62,7 +61,7 @@ impl Shader {
         if location != WebGl2RenderingContext::INVALID_INDEX {
             Ok(location)
         } else {
-            Err(format!("shader uniform \"{}\" not found", uniform_name).into())
+            Err(Error::MissingUniform(uniform_name.into()))
         }
     }
 
@@ -75,27 +74,35 @@ impl Shader {
         {
             Ok(location)
         } else {
-            Err(format!("shader texture \"{}\" not found", texture_name).into())
+            Err(Error::MissingTexture(texture_name.into()))
         }
     }
 }
 
 fn compile_shader(
     gl: &WebGl2RenderingContext,
-    shader_type: u32,
+    stage: ShaderStage,
     shader_source: &str,
 ) -> Result<WebGlShader> {
+    let shader_type = match stage {
+        ShaderStage::Vertex => WebGl2RenderingContext::VERTEX_SHADER,
+        ShaderStage::Fragment => WebGl2RenderingContext::FRAGMENT_SHADER,
+        ShaderStage::Compute => {
+            return Err(Error::Unsupported("compute shaders on WebGL 2".into()))
+        }
+    };
+
     if let Some(shader) = gl.create_shader(shader_type) {
         gl.shader_source(&shader, shader_source);
         gl.compile_shader(&shader);
         if let Some(log) = gl.get_shader_info_log(&shader) {
             if log.len() > 0 {
-                return Err(log.into());
+                return Err(Error::ShaderCompile { stage, log });
             }
         }
 
         Ok(shader)
     } else {
-        Err("failed to create shader".into())
+        Err(Error::Backend("creating shader".into()))
     }
 }
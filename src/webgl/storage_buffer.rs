@@ -0,0 +1,2 @@
+/// WebGL 2 has no storage buffers, so none can be created.
+pub enum StorageBuffer {}
@@ -1,7 +1,17 @@
-use crate::shared::image::is_power_of_2;
+use crate::shared::image::{
+    check_mip_levels, check_region, check_update_kind, generate_mip_levels, kind_mip_level_count,
+    layer_count, mip_level_size, rgba_image_from_f32, CUBE_CROSS_FACES,
+};
 use crate::webgl::err::Result;
-use crate::TextureFilter;
+use crate::webgl::internal::{
+    check_sampler, set_sampler_parameters, tex_image_format, texture_target, Framebuffer,
+};
+use crate::Error;
+use crate::{SamplerDesc, TextureFormat, TextureKind, TextureRegion};
+use image::RgbaImage;
 use js_sys::Function;
+use std::cell::Cell;
+use std::iter;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -10,14 +20,25 @@ use web_sys::{HtmlImageElement, WebGl2RenderingContext, WebGlTexture};
 pub struct Texture {
     pub(crate) context: Rc<WebGl2RenderingContext>,
     pub(crate) texture: WebGlTexture,
+    /// The width and height, which change once an image has been loaded.
+    pub(crate) size: Rc<Cell<(u32, u32)>>,
+    pub(crate) mip_level_count: Rc<Cell<u32>>,
+    pub(crate) kind: TextureKind,
+    pub(crate) format: TextureFormat,
+    /// Whether the rows are stored bottom up, as they are when the texture
+    /// is rendered into.
+    pub(crate) flip_y: bool,
 }
 
 impl Texture {
     pub(crate) fn new(
         gl: &Rc<WebGl2RenderingContext>,
         image_path: &str,
-        filter: TextureFilter,
+        sampler: &SamplerDesc,
     ) -> Result<Texture> {
+        check_sampler(sampler)?;
+        let sampler = *sampler;
+
         if let Some(texture) = gl.create_texture() {
             gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
 
@@ -39,15 +60,20 @@ impl Texture {
                 Some(&[255, 255, 255, 255]),
             )?;
 
+            let size = Rc::new(Cell::new((1, 1)));
+            let mip_level_count = Rc::new(Cell::new(1));
             let image = Rc::from(HtmlImageElement::new()?);
             image.set_onload(Some({
                 let gl = Rc::clone(gl);
                 let texture = texture.clone();
                 let image = Rc::clone(&image);
+                let size = Rc::clone(&size);
+                let mip_level_count = Rc::clone(&mip_level_count);
 
                 &Closure::once_into_js(Box::from(move || {
                         let w = image.width();
                         let h = image.height();
+                        size.set((w, h));
 
                         gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
                         gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_html_image_element(
@@ -62,45 +88,7 @@ impl Texture {
                             &image,
                         ).unwrap();
 
-                        gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
-                        gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
-
-                        match filter {
-                            TextureFilter::Nearest => {
-                                gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::NEAREST as i32);
-                                gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::NEAREST as i32);
-                            }
-                            TextureFilter::Linear => {
-                                gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::LINEAR as i32);
-                                gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::LINEAR as i32);
-                            }
-                            TextureFilter::MipMap => {
-                                if is_power_of_2(w) && is_power_of_2(h) {
-                                    gl.generate_mipmap(WebGl2RenderingContext::TEXTURE_2D);
-                                    gl.tex_parameteri(
-                                        WebGl2RenderingContext::TEXTURE_2D,
-                                        WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-                                        WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR as i32,
-                                    );
-                                    gl.tex_parameteri(
-                                        WebGl2RenderingContext::TEXTURE_2D,
-                                        WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-                                        WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR as i32,
-                                    );
-                                } else {
-                                    gl.tex_parameteri(
-                                        WebGl2RenderingContext::TEXTURE_2D,
-                                        WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-                                        WebGl2RenderingContext::LINEAR as i32,
-                                    );
-                                    gl.tex_parameteri(
-                                        WebGl2RenderingContext::TEXTURE_2D,
-                                        WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-                                        WebGl2RenderingContext::LINEAR as i32,
-                                    );
-                                }
-                            }
-                        }
+                        mip_level_count.set(set_parameters(&gl, TextureKind::D2, &sampler, w, h));
                 }))
                 .dyn_into::<Function>()
                 .unwrap()
@@ -111,10 +99,568 @@ impl Texture {
             Ok(Texture {
                 context: Rc::clone(gl),
                 texture,
+                size,
+                mip_level_count,
+                kind: TextureKind::D2,
+                format: TextureFormat::Rgba8,
+                flip_y: false,
+            })
+        } else {
+            Err(Error::Backend("creating texture".into()))
+        }
+    }
+
+    /// Creates a cube map from six images, which is filled in once all of
+    /// them have been downloaded. Until then every face is a single white
+    /// pixel. Faces that are not square and the same size leave the cube map
+    /// incomplete, which samples as black.
+    pub(crate) fn with_cube_images(
+        gl: &Rc<WebGl2RenderingContext>,
+        image_paths: &[&str; 6],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_sampler(sampler)?;
+        let sampler = *sampler;
+        let texture = create_cube_placeholder(gl)?;
+
+        let size = Rc::new(Cell::new((1, 1)));
+        let mip_level_count = Rc::new(Cell::new(1));
+        let mut images = Vec::new();
+        for _ in 0..6 {
+            images.push(HtmlImageElement::new()?);
+        }
+        let images = Rc::new(images);
+        let loaded = Rc::new(Cell::new(0));
+
+        for (image, image_path) in images.iter().zip(image_paths) {
+            image.set_onload(Some({
+                let gl = Rc::clone(gl);
+                let texture = texture.clone();
+                let images = Rc::clone(&images);
+                let loaded = Rc::clone(&loaded);
+                let size = Rc::clone(&size);
+                let mip_level_count = Rc::clone(&mip_level_count);
+
+                &Closure::once_into_js(move || {
+                    loaded.set(loaded.get() + 1);
+                    if loaded.get() < images.len() {
+                        return;
+                    }
+
+                    let w = images[0].width();
+                    let h = images[0].height();
+                    size.set((w, h));
+
+                    gl.bind_texture(WebGl2RenderingContext::TEXTURE_CUBE_MAP, Some(&texture));
+                    for (face, image) in images.iter().enumerate() {
+                        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_html_image_element(
+                            WebGl2RenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                            0,
+                            WebGl2RenderingContext::RGBA as i32,
+                            image.width() as i32,
+                            image.height() as i32,
+                            0,
+                            WebGl2RenderingContext::RGBA,
+                            WebGl2RenderingContext::UNSIGNED_BYTE,
+                            image,
+                        )
+                        .unwrap();
+                    }
+
+                    mip_level_count.set(set_parameters(&gl, TextureKind::Cube, &sampler, w, h));
+                })
+                .dyn_into::<Function>()
+                .unwrap()
+            }));
+            image.set_src(image_path);
+        }
+
+        Ok(Texture {
+            context: Rc::clone(gl),
+            texture,
+            size,
+            mip_level_count,
+            kind: TextureKind::Cube,
+            format: TextureFormat::Rgba8,
+            flip_y: false,
+        })
+    }
+
+    /// Creates a cube map from an image laid out as a horizontal cross,
+    /// which is filled in once the image has been downloaded. Until then
+    /// every face is a single white pixel, which they stay if the image is
+    /// not 4 faces wide and 3 faces high.
+    pub(crate) fn with_cube_cross(
+        gl: &Rc<WebGl2RenderingContext>,
+        image_path: &str,
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_sampler(sampler)?;
+        let sampler = *sampler;
+        let texture = create_cube_placeholder(gl)?;
+
+        let size = Rc::new(Cell::new((1, 1)));
+        let mip_level_count = Rc::new(Cell::new(1));
+        let image = Rc::new(HtmlImageElement::new()?);
+        image.set_onload(Some({
+            let gl = Rc::clone(gl);
+            let texture = texture.clone();
+            let image = Rc::clone(&image);
+            let size = Rc::clone(&size);
+            let mip_level_count = Rc::clone(&mip_level_count);
+
+            &Closure::once_into_js(move || {
+                let face_size = image.width() / 4;
+                if face_size == 0
+                    || image.width() != face_size * 4
+                    || image.height() != face_size * 3
+                {
+                    return;
+                }
+                size.set((face_size, face_size));
+
+                // The faces are cut out of the image with the unpack
+                // parameters, which WebGL 2 applies to images as well.
+                gl.bind_texture(WebGl2RenderingContext::TEXTURE_CUBE_MAP, Some(&texture));
+                gl.pixel_storei(WebGl2RenderingContext::UNPACK_ROW_LENGTH, image.width() as i32);
+                for (face, (column, row)) in CUBE_CROSS_FACES.iter().enumerate() {
+                    gl.pixel_storei(
+                        WebGl2RenderingContext::UNPACK_SKIP_PIXELS,
+                        (column * face_size) as i32,
+                    );
+                    gl.pixel_storei(
+                        WebGl2RenderingContext::UNPACK_SKIP_ROWS,
+                        (row * face_size) as i32,
+                    );
+                    gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_html_image_element(
+                        WebGl2RenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                        0,
+                        WebGl2RenderingContext::RGBA as i32,
+                        face_size as i32,
+                        face_size as i32,
+                        0,
+                        WebGl2RenderingContext::RGBA,
+                        WebGl2RenderingContext::UNSIGNED_BYTE,
+                        &image,
+                    )
+                    .unwrap();
+                }
+                gl.pixel_storei(WebGl2RenderingContext::UNPACK_ROW_LENGTH, 0);
+                gl.pixel_storei(WebGl2RenderingContext::UNPACK_SKIP_PIXELS, 0);
+                gl.pixel_storei(WebGl2RenderingContext::UNPACK_SKIP_ROWS, 0);
+
+                mip_level_count.set(set_parameters(
+                    &gl,
+                    TextureKind::Cube,
+                    &sampler,
+                    face_size,
+                    face_size,
+                ));
+            })
+            .dyn_into::<Function>()
+            .unwrap()
+        }));
+        image.set_src(image_path);
+
+        Ok(Texture {
+            context: Rc::clone(gl),
+            texture,
+            size,
+            mip_level_count,
+            kind: TextureKind::Cube,
+            format: TextureFormat::Rgba8,
+            flip_y: false,
+        })
+    }
+
+    /// Creates a texture from tightly packed texels, which are viewed as the
+    /// typed array WebGL expects for the format. If the sampler filters
+    /// between mip levels a full mip chain is generated, on the CPU for sRGB
+    /// formats, which may be filtered in gamma space, and float formats, which
+    /// WebGL can only render to and filter with extensions.
+    pub(crate) fn with_pixels(
+        gl: &Rc<WebGl2RenderingContext>,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, &[data])?;
+        if sampler.mip_filter.is_none() || format.is_depth() {
+            return Texture::with_mip_levels(gl, kind, width, height, format, &[data], sampler);
+        }
+
+        if format == TextureFormat::Rgba8Srgb || format.is_float() {
+            let generated = generate_mip_levels(kind, width, height, format, data)?;
+            let levels: Vec<&[u8]> = iter::once(data)
+                .chain(generated.iter().map(|level| level.as_slice()))
+                .collect();
+
+            return Texture::with_mip_levels(gl, kind, width, height, format, &levels, sampler);
+        }
+
+        let texture = Texture::with_mip_levels(gl, kind, width, height, format, &[data], sampler)?;
+        texture
+            .mip_level_count
+            .set(set_parameters(gl, kind, sampler, width, height));
+
+        Ok(texture)
+    }
+
+    /// Every level holds the layers, slices or cube faces of the texture one
+    /// after the other.
+    pub(crate) fn with_mip_levels(
+        gl: &Rc<WebGl2RenderingContext>,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        levels: &[&[u8]],
+        sampler: &SamplerDesc,
+    ) -> Result<Texture> {
+        check_mip_levels(kind, width, height, format, levels)?;
+        check_sampler(sampler)?;
+
+        if let Some(texture) = gl.create_texture() {
+            let target = texture_target(kind);
+            gl.bind_texture(target, Some(&texture));
+
+            gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 1);
+            let tex_image_format = tex_image_format(format);
+            for (mip_level, data) in levels.iter().enumerate() {
+                let (width, height) = mip_level_size(width, height, mip_level as u32);
+                let layers = layer_count(kind, mip_level as u32);
+                match kind {
+                    TextureKind::D2 => {
+                        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+                            target,
+                            mip_level as i32,
+                            tex_image_format.internal_format,
+                            width as i32,
+                            height as i32,
+                            0,
+                            tex_image_format.format,
+                            tex_image_format.data_type,
+                            Some(&pixels_view(format, data)),
+                        )?;
+                    }
+                    TextureKind::D2Array { .. } | TextureKind::D3 { .. } => {
+                        gl.tex_image_3d_with_opt_array_buffer_view(
+                            target,
+                            mip_level as i32,
+                            tex_image_format.internal_format,
+                            width as i32,
+                            height as i32,
+                            layers as i32,
+                            0,
+                            tex_image_format.format,
+                            tex_image_format.data_type,
+                            Some(&pixels_view(format, data)),
+                        )?;
+                    }
+                    TextureKind::Cube => {
+                        let face_size = data.len() / layers as usize;
+                        for (face, data) in data.chunks_exact(face_size).enumerate() {
+                            gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+                                WebGl2RenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                                mip_level as i32,
+                                tex_image_format.internal_format,
+                                width as i32,
+                                height as i32,
+                                0,
+                                tex_image_format.format,
+                                tex_image_format.data_type,
+                                Some(&pixels_view(format, data)),
+                            )?;
+                        }
+                    }
+                }
+            }
+            set_sampler_parameters(gl, target, sampler, levels.len() as u32);
+
+            Ok(Texture {
+                context: Rc::clone(gl),
+                texture,
+                size: Rc::new(Cell::new((width, height))),
+                mip_level_count: Rc::new(Cell::new(levels.len() as u32)),
+                kind,
+                format,
+                flip_y: false,
+            })
+        } else {
+            Err(Error::Backend("creating texture".into()))
+        }
+    }
+
+    /// Creates a texture with undefined contents, to be rendered into.
+    pub(crate) fn with_format(
+        gl: &Rc<WebGl2RenderingContext>,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<Texture> {
+        if let Some(texture) = gl.create_texture() {
+            gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+            let tex_image_format = tex_image_format(format);
+            gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                tex_image_format.internal_format,
+                width as i32,
+                height as i32,
+                0,
+                tex_image_format.format,
+                tex_image_format.data_type,
+                None,
+            )?;
+
+            set_sampler_parameters(
+                gl,
+                WebGl2RenderingContext::TEXTURE_2D,
+                &SamplerDesc::default(),
+                1,
+            );
+
+            Ok(Texture {
+                context: Rc::clone(gl),
+                texture,
+                size: Rc::new(Cell::new((width, height))),
+                mip_level_count: Rc::new(Cell::new(1)),
+                kind: TextureKind::D2,
+                format,
+                flip_y: true,
             })
         } else {
-            Err("creating texture".into())
+            Err(Error::Backend("creating texture".into()))
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        region: TextureRegion,
+        mip_level: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        check_update_kind(self.kind)?;
+
+        let (width, height) = self.size.get();
+        check_region(
+            width,
+            height,
+            self.mip_level_count.get(),
+            self.format,
+            region,
+            mip_level,
+            data,
+        )?;
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
+        }
+
+        // Rendered textures are stored bottom up, so the region and its rows
+        // are flipped to keep `y` counting from the top.
+        let (y, pixels) = if self.flip_y {
+            let row_size = region.width as usize * self.format.texel_size();
+            let mip_height = (height >> mip_level).max(1);
+            let rows: Vec<u8> = data
+                .chunks_exact(row_size)
+                .rev()
+                .flatten()
+                .copied()
+                .collect();
+            (
+                mip_height - region.y - region.height,
+                pixels_view(self.format, &rows),
+            )
+        } else {
+            (region.y, pixels_view(self.format, data))
+        };
+
+        let gl = &self.context;
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&self.texture));
+        gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 1);
+
+        let tex_image_format = tex_image_format(self.format);
+        gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+            WebGl2RenderingContext::TEXTURE_2D,
+            mip_level as i32,
+            region.x as i32,
+            y as i32,
+            region.width as i32,
+            region.height as i32,
+            tex_image_format.format,
+            tex_image_format.data_type,
+            Some(&pixels),
+        )?;
+        Ok(())
+    }
+
+    /// Reads back the texture as an 8 bit RGBA image with the first row at
+    /// the top. Float formats are clamped to `[0, 1]`. Only the first layer,
+    /// slice or face is read. WebGL can not read depth textures back.
+    pub fn read_to_image(&self) -> Result<RgbaImage> {
+        if self.format.is_depth() {
+            return Err(Error::Unsupported("reading depth textures on WebGL".into()));
+        }
+
+        let gl = &self.context;
+        let framebuffer = match gl.create_framebuffer() {
+            Some(framebuffer) => Framebuffer {
+                context: Rc::clone(gl),
+                framebuffer,
+            },
+            None => return Err(Error::Backend("creating framebuffer".into())),
+        };
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            Some(&framebuffer.framebuffer),
+        );
+        match self.kind {
+            TextureKind::D2 | TextureKind::Cube => gl.framebuffer_texture_2d(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                WebGl2RenderingContext::COLOR_ATTACHMENT0,
+                if self.kind == TextureKind::Cube {
+                    WebGl2RenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X
+                } else {
+                    WebGl2RenderingContext::TEXTURE_2D
+                },
+                Some(&self.texture),
+                0,
+            ),
+            TextureKind::D2Array { .. } | TextureKind::D3 { .. } => gl.framebuffer_texture_layer(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                WebGl2RenderingContext::COLOR_ATTACHMENT0,
+                Some(&self.texture),
+                0,
+                0,
+            ),
+        }
+
+        let (width, height) = self.size.get();
+        let result = read_pixels(gl, self.format, width, height);
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+
+        let mut img = result?;
+        if self.flip_y {
+            image::imageops::flip_vertical_in_place(&mut img);
+        }
+        Ok(img)
+    }
+}
+
+/// Copies tightly packed texels into the typed array WebGL expects for the
+/// format.
+fn pixels_view(format: TextureFormat, data: &[u8]) -> js_sys::Object {
+    let bytes = js_sys::Uint8Array::from(data);
+    let length = data.len() as u32;
+    match format {
+        TextureFormat::R8
+        | TextureFormat::Rg8
+        | TextureFormat::Rgba8
+        | TextureFormat::Rgba8Srgb => bytes.into(),
+        TextureFormat::R16F | TextureFormat::Rgba16F => {
+            js_sys::Uint16Array::new_with_byte_offset_and_length(&bytes.buffer(), 0, length / 2)
+                .into()
         }
+        TextureFormat::R32F | TextureFormat::Rgba32F | TextureFormat::Depth32F => {
+            js_sys::Float32Array::new_with_byte_offset_and_length(&bytes.buffer(), 0, length / 4)
+                .into()
+        }
+        TextureFormat::Depth24Stencil8 => {
+            js_sys::Uint32Array::new_with_byte_offset_and_length(&bytes.buffer(), 0, length / 4)
+                .into()
+        }
+    }
+}
+
+/// Applies the sampler to the bound texture, generating mipmaps from the
+/// first level if it filters between them. Returns the number of mip levels.
+fn set_parameters(
+    gl: &WebGl2RenderingContext,
+    kind: TextureKind,
+    sampler: &SamplerDesc,
+    w: u32,
+    h: u32,
+) -> u32 {
+    let mip_level_count = if sampler.mip_filter.is_some() {
+        kind_mip_level_count(kind, w, h)
+    } else {
+        1
+    };
+    // Mipmaps are only generated up to the max level.
+    let target = texture_target(kind);
+    set_sampler_parameters(gl, target, sampler, mip_level_count);
+    if mip_level_count > 1 {
+        gl.generate_mipmap(target);
+    }
+
+    mip_level_count
+}
+
+/// Creates a cube map whose faces are a single white pixel, for images that
+/// are still being downloaded.
+fn create_cube_placeholder(gl: &WebGl2RenderingContext) -> Result<WebGlTexture> {
+    let texture = match gl.create_texture() {
+        Some(texture) => texture,
+        None => return Err(Error::Backend("creating texture".into())),
+    };
+
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_CUBE_MAP, Some(&texture));
+    for face in 0..6 {
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGl2RenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+            0,
+            WebGl2RenderingContext::RGBA as i32,
+            1,
+            1,
+            0,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            Some(&[255, 255, 255, 255]),
+        )?;
+    }
+    Ok(texture)
+}
+
+/// Reads the bound framebuffer's first color attachment as RGBA, starting at
+/// the bottom row. Float attachments can only be read as floats.
+pub(crate) fn read_pixels(
+    gl: &WebGl2RenderingContext,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage> {
+    gl.pixel_storei(WebGl2RenderingContext::PACK_ALIGNMENT, 1);
+
+    if !format.is_float() {
+        let mut data = vec![0; width as usize * height as usize * 4];
+        gl.read_pixels_with_opt_u8_array(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            Some(&mut data),
+        )?;
+
+        Ok(RgbaImage::from_raw(width, height, data).unwrap())
+    } else {
+        let data = js_sys::Float32Array::new_with_length(width * height * 4);
+        gl.read_pixels_with_opt_array_buffer_view(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::FLOAT,
+            Some(&data),
+        )?;
+
+        Ok(rgba_image_from_f32(width, height, 4, &data.to_vec()))
     }
 }
 
@@ -1,5 +1,6 @@
 use crate::webgl::err::Result;
 use crate::webgl::internal::Buffer;
+use crate::Error;
 use std::mem::size_of;
 use std::rc::Rc;
 use web_sys::WebGl2RenderingContext;
@@ -33,7 +34,7 @@ impl UniformBuffer {
                 capacity,
             })
         } else {
-            Err("creating uniform buffer".into())
+            Err(Error::Backend("creating uniform buffer".into()))
         }
     }
 
@@ -64,7 +65,7 @@ impl UniformBuffer {
                 capacity,
             })
         } else {
-            Err("creating vertex buffer".into())
+            Err(Error::Backend("creating uniform buffer".into()))
         }
     }
 
@@ -74,7 +75,10 @@ impl UniformBuffer {
     {
         let length = size_of::<T>();
         if length > self.capacity {
-            return Err("updating vertex buffer: new data is longer than buffer capacity".into());
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
         }
 
         let gl = &self.context;
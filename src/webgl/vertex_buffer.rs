@@ -1,5 +1,6 @@
 use crate::webgl::err::Result;
 use crate::webgl::internal::Buffer;
+use crate::Error;
 use std::mem::size_of;
 use std::rc::Rc;
 use web_sys::WebGl2RenderingContext;
@@ -33,7 +34,7 @@ impl VertexBuffer {
                 capacity,
             })
         } else {
-            Err("creating vertex buffer".into())
+            Err(Error::Backend("creating vertex buffer".into()))
         }
     }
 
@@ -65,7 +66,7 @@ impl VertexBuffer {
                 capacity,
             })
         } else {
-            Err("creating vertex buffer".into())
+            Err(Error::Backend("creating vertex buffer".into()))
         }
     }
 
@@ -75,7 +76,10 @@ impl VertexBuffer {
     {
         let length = data.len() * size_of::<T>();
         if length > self.capacity {
-            return Err("updating vertex buffer: new data is longer than buffer capacity".into());
+            return Err(Error::CapacityOverflow {
+                capacity: self.capacity,
+                length,
+            });
         }
 
         let gl = &self.context;